@@ -0,0 +1,89 @@
+//! Anything sqlparser reads has to come out of the default
+//! `SqlWriter` methods as SQL that reads back into the same
+//! statements, and no dialect writer may fail with anything
+//! but `Unsupported`.
+//!
+//! cargo +nightly fuzz run parse_write_parse
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use sql_generate::{
+    BigQueryWriter, MsSqlWriter, MySqlWriter, OracleWriter, PostgresWriter, SnowflakeWriter,
+    SqlWriter, Unsupported,
+};
+use sqlparser::{ast::Statement, dialect::GenericDialect, parser::Parser};
+
+type Res<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Only the required methods, so everything
+/// is written with the generic spellings
+#[derive(Default)]
+struct GenericWriter {
+    buf: String,
+    indent: usize,
+}
+
+impl SqlWriter for GenericWriter {
+    fn write(&mut self, s: &str) -> Res<()> {
+        self.buf.push_str(s);
+        Ok(())
+    }
+    fn write_new_line(&mut self) -> Res<()> {
+        self.buf.push('\n');
+        Ok(())
+    }
+    fn write_prefix(&mut self) -> Res<()> {
+        for _ in 0..self.indent {
+            self.buf.push_str("    ");
+        }
+        Ok(())
+    }
+    fn indent(&mut self) {
+        self.indent += 1;
+    }
+    fn dedent(&mut self) {
+        self.indent -= 1;
+    }
+}
+
+fn write_all(w: &mut impl SqlWriter, stmts: &[Statement]) -> Res<()> {
+    for (i, stmt) in stmts.iter().enumerate() {
+        if i > 0 {
+            w.write(";")?;
+            w.write_new_line()?;
+        }
+        w.write_statement(stmt)?;
+    }
+    Ok(())
+}
+
+fn check_error(name: &str, result: Res<()>) {
+    if let Err(e) = result {
+        assert!(e.downcast_ref::<Unsupported>().is_some(), "{}: {}", name, e);
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let sql = match std::str::from_utf8(data) {
+        Ok(sql) => sql,
+        Err(_) => return,
+    };
+    let stmts = match Parser::parse_sql(&GenericDialect {}, sql.to_string()) {
+        Ok(stmts) => stmts,
+        Err(_) => return,
+    };
+    let mut w = GenericWriter::default();
+    match write_all(&mut w, &stmts) {
+        Ok(()) => {
+            let again = Parser::parse_sql(&GenericDialect {}, w.buf.clone())
+                .unwrap_or_else(|e| panic!("output does not parse: {}\n{}", e, w.buf));
+            assert_eq!(again, stmts, "{}", w.buf);
+        },
+        Err(e) => check_error("generic", Err(e)),
+    }
+    check_error("mssql", write_all(&mut MsSqlWriter::new("    ", Vec::new()), &stmts));
+    check_error("snowflake", write_all(&mut SnowflakeWriter::new("    ", Vec::new()), &stmts));
+    check_error("bigquery", write_all(&mut BigQueryWriter::new("    ", Vec::new()), &stmts));
+    check_error("oracle", write_all(&mut OracleWriter::new("    ", Vec::new()), &stmts));
+    check_error("postgres", write_all(&mut PostgresWriter::new("    ", Vec::new()), &stmts));
+    check_error("mysql", write_all(&mut MySqlWriter::new("    ", Vec::new()), &stmts));
+});
@@ -0,0 +1,235 @@
+//! T-SQL scripts are split into batches by `GO` lines, which
+//! are a client command rather than SQL so sqlparser never sees
+//! them. `split_batches` finds those lines, skipping over strings,
+//! quoted identifiers and comments, and `parse_batches` parses
+//! each batch on its own.
+use crate::tsql::{parse_tsql_lines, TsqlStatement};
+use sqlparser::dialect::Dialect;
+use std::fmt;
+
+/// The `GO [count]` line that ends a batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Go {
+    /// How many times the batch runs, `GO 5`
+    pub count: Option<u64>,
+}
+
+/// One batch of a script, as text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Batch<'a> {
+    pub sql: &'a str,
+    /// The line the batch starts on, counting from 1
+    pub line: usize,
+    /// `None` for a last batch that has no `GO` after it
+    pub go: Option<Go>,
+}
+
+/// One batch of a script, parsed
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedBatch {
+    pub statements: Vec<TsqlStatement>,
+    /// The line the batch starts on, counting from 1
+    pub line: usize,
+    pub go: Option<Go>,
+}
+
+/// A batch sqlparser could not read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchError {
+    /// Which batch failed, counting from 1
+    pub batch: usize,
+    /// The line of the script the error was found on, counting from 1
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "batch {} (line {}): {}", self.batch, self.line, self.message)
+    }
+}
+
+/// Every batch of a script that failed to parse
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchErrors(pub Vec<BatchError>);
+
+impl fmt::Display for BatchErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut after_first = false;
+        for error in &self.0 {
+            if after_first {
+                writeln!(f)?;
+            }
+            write!(f, "{}", error)?;
+            after_first = true;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for BatchErrors {}
+
+/// Parse each batch of `sql` with `dialect`, a script without
+/// any `GO` lines is a single batch
+pub fn parse_batches(sql: &str, dialect: &dyn Dialect) -> Result<Vec<ParsedBatch>, BatchErrors> {
+    let mut parsed = Vec::new();
+    let mut errors = Vec::new();
+    for (i, batch) in split_batches(sql).into_iter().enumerate() {
+        match parse_tsql_lines(batch.sql, dialect) {
+            Ok(statements) => parsed.push(ParsedBatch {
+                statements,
+                line: batch.line,
+                go: batch.go,
+            }),
+            Err((e, line)) => errors.push(BatchError {
+                batch: i + 1,
+                line: batch.line + line,
+                message: e.to_string(),
+            }),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(BatchErrors(errors));
+    }
+    Ok(parsed)
+}
+
+/// Split `sql` on the lines that hold nothing but `GO`, an
+/// optional count and an optional `--` comment
+pub fn split_batches(sql: &str) -> Vec<Batch<'_>> {
+    let mut batches = Vec::new();
+    let mut scanner = Scanner::default();
+    let mut start = 0;
+    let mut start_line = 1;
+    let mut offset = 0;
+    for (i, line) in sql.split_inclusive('\n').enumerate() {
+        if scanner.is_code() {
+            if let Some(go) = go_line(line) {
+                batches.push(Batch {
+                    sql: &sql[start..offset],
+                    line: start_line,
+                    go: Some(go),
+                });
+                offset += line.len();
+                start = offset;
+                start_line = i + 2;
+                continue;
+            }
+        }
+        scanner.scan(line);
+        offset += line.len();
+    }
+    if start < sql.len() || batches.is_empty() {
+        batches.push(Batch {
+            sql: &sql[start..],
+            line: start_line,
+            go: None,
+        });
+    }
+    batches
+}
+
+fn go_line(line: &str) -> Option<Go> {
+    let line = match line.find("--") {
+        Some(idx) => &line[..idx],
+        None => line,
+    };
+    let mut words = line.split_whitespace();
+    if !words.next()?.eq_ignore_ascii_case("GO") {
+        return None;
+    }
+    let count = match words.next() {
+        Some(count) => Some(count.parse().ok()?),
+        None => None,
+    };
+    if words.next().is_some() {
+        return None;
+    }
+    Some(Go { count })
+}
+
+/// What the end of the text scanned so far is inside of
+#[derive(Debug, Default)]
+struct Scanner {
+    /// The closing character of an open string or quoted identifier
+    quote: Option<char>,
+    /// T-SQL block comments nest
+    comment_depth: usize,
+}
+
+impl Scanner {
+    fn is_code(&self) -> bool {
+        self.quote.is_none() && self.comment_depth == 0
+    }
+
+    fn scan(&mut self, line: &str) {
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            if let Some(quote) = self.quote {
+                if c == quote {
+                    // a doubled quote is an escaped one
+                    if chars.peek() == Some(&quote) {
+                        chars.next();
+                    } else {
+                        self.quote = None;
+                    }
+                }
+                continue;
+            }
+            match (c, chars.peek()) {
+                ('/', Some('*')) => {
+                    chars.next();
+                    self.comment_depth += 1;
+                },
+                ('*', Some('/')) if self.comment_depth > 0 => {
+                    chars.next();
+                    self.comment_depth -= 1;
+                },
+                _ if self.comment_depth > 0 => (),
+                ('-', Some('-')) => return,
+                ('\'', _) => self.quote = Some('\''),
+                ('"', _) => self.quote = Some('"'),
+                ('[', _) => self.quote = Some(']'),
+                _ => (),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn split_on_go() {
+        let sql = "CREATE TABLE t (a INT)
+GO
+INSERT INTO t VALUES ('
+GO
+')
+/* GO
+*/
+go 5 -- five times
+SELECT a FROM t";
+        let batches = split_batches(sql);
+        assert_eq!(batches, vec![
+            Batch {
+                sql: "CREATE TABLE t (a INT)\n",
+                line: 1,
+                go: Some(Go { count: None }),
+            },
+            Batch {
+                sql: "INSERT INTO t VALUES ('\nGO\n')\n/* GO\n*/\n",
+                line: 3,
+                go: Some(Go { count: Some(5) }),
+            },
+            Batch {
+                sql: "SELECT a FROM t",
+                line: 9,
+                go: None,
+            },
+        ]);
+        assert_eq!(split_batches("SELECT 1\nGO\n").len(), 1);
+        assert_eq!(split_batches("SELECT 1 AS go").len(), 1);
+    }
+}
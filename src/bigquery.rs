@@ -0,0 +1,221 @@
+use crate::{error::unsupported, generic, output::{self, Output}, pivot, write_clause, unquote_ident, write_bare_name, SqlWriter, Res};
+use std::io::Write;
+use sqlparser::ast::*;
+
+/// Writes BigQuery Standard SQL.
+///
+/// Multi part names are written as a single backtick quoted
+/// path (`` `project.dataset.table` ``) and set operations always
+/// spell out `ALL` or `DISTINCT`, since BigQuery has no default.
+pub struct BigQueryWriter<W> {
+    out: Output<W>,
+    safe_cast: bool,
+}
+
+impl<W> BigQueryWriter<W>
+where W: Write {
+    pub fn new(prefix: &'static str, writer: W) -> Self {
+        Self {
+            out: Output::new(prefix, writer),
+            safe_cast: false,
+        }
+    }
+
+    /// Write every `CAST` as `SAFE_CAST`, which returns
+    /// NULL instead of failing the query
+    pub fn safe_cast(mut self, safe: bool) -> Self {
+        self.safe_cast = safe;
+        self
+    }
+
+    output::writer_builders!();
+}
+
+impl<W> SqlWriter for BigQueryWriter<W>
+where W: Write {
+    output::output_primitives!();
+
+    fn write_ident(&mut self, id: &str) -> Res<()> {
+        match unquote_ident(id) {
+            Some((_, inner)) => self.write(&format!("`{}`", inner)),
+            None => self.write_unquoted_ident(id),
+        }
+    }
+
+    fn write_object_name(&mut self, node: &ObjectName) -> Res<()> {
+        let quoted = node.0.iter().any(|part| unquote_ident(part).is_some());
+        if node.0.len() < 2 && !quoted {
+            return self.write_ident(&node.0.join("."));
+        }
+        let path: Vec<&str> = node.0.iter()
+            .map(|part| unquote_ident(part).map(|(_, inner)| inner).unwrap_or(part))
+            .collect();
+        self.write(&format!("`{}`", path.join(".")))
+    }
+
+    fn write_cast(&mut self, expr: &Expr, data_type: &DataType) -> Res<()> {
+        if self.safe_cast {
+            self.write_keyword("SAFE_CAST(")?;
+        } else {
+            self.write_keyword("CAST(")?;
+        }
+        self.write_expr(expr)?;
+        self.write_keyword(" AS ")?;
+        self.write_data_type(data_type)?;
+        self.write(")")
+    }
+
+    fn write_limit(&mut self, node: &Query) -> Res<()> {
+        let limit = match (&node.limit, &node.fetch) {
+            (Some(_), Some(_)) => {
+                return unsupported("BigQuery does not allow LIMIT and FETCH in the same query")
+            },
+            (Some(limit), None) => limit.clone(),
+            (None, Some(fetch)) => generic::fetch_as_limit("BigQuery", fetch)?,
+            (None, None) => {
+                if node.offset.is_some() {
+                    return unsupported("BigQuery does not support OFFSET without LIMIT");
+                }
+                return Ok(());
+            },
+        };
+        self.write_new_line()?;
+        self.write_prefix()?;
+        write_clause(self, "LIMIT ")?;
+        self.write_expr(&limit)?;
+        if let Some(offset) = &node.offset {
+            self.write_new_line()?;
+            self.write_prefix()?;
+            write_clause(self, "OFFSET ")?;
+            self.write_expr(offset)?;
+        }
+        Ok(())
+    }
+
+    fn write_set_expr(&mut self, node: &SetExpr) -> Res<()> {
+        match node {
+            SetExpr::SetOperation {
+                op,
+                all,
+                left,
+                right,
+            } => {
+                if *all && *op != SetOperator::Union {
+                    return unsupported(format!("BigQuery does not support {:?} ALL", op));
+                }
+                self.write_set_expr(left)?;
+                self.write_new_line()?;
+                self.write_prefix()?;
+                self.write_set_operator(op)?;
+                if *all {
+                    self.write_keyword(" ALL")?;
+                } else {
+                    self.write_keyword(" DISTINCT")?;
+                }
+                self.write_new_line()?;
+                self.write_prefix()?;
+                self.write_set_expr(right)
+            },
+            _ => generic::write_set_expr(self, node),
+        }
+    }
+
+    fn write_join(&mut self, node: &Join) -> Res<()> {
+        match node.join_operator {
+            JoinOperator::CrossApply | JoinOperator::OuterApply if !pivot::is_pivot(node) => {
+                unsupported("BigQuery has no CROSS APPLY, OUTER APPLY or LATERAL join")
+            },
+            _ => generic::write_join(self, node),
+        }
+    }
+
+    fn write_data_type(&mut self, node: &DataType) -> Res<()> {
+        if !self.out.translate_types {
+            return generic::write_data_type(self, node);
+        }
+        match node {
+            DataType::Char(size) | DataType::Varchar(size) => {
+                self.write_keyword("STRING")?;
+                if let Some(size) = size {
+                    self.write(&format!("({})", size))?;
+                }
+                Ok(())
+            },
+            DataType::Text | DataType::Clob(_) | DataType::Uuid => self.write_keyword("STRING"),
+            DataType::Binary(_) | DataType::Varbinary(_) | DataType::Blob(_) | DataType::Bytea => {
+                self.write_keyword("BYTES")
+            },
+            DataType::SmallInt | DataType::Int | DataType::BigInt => self.write_keyword("INT64"),
+            DataType::Float(_) | DataType::Real | DataType::Double => self.write_keyword("FLOAT64"),
+            DataType::Boolean => self.write_keyword("BOOL"),
+            DataType::Timestamp => self.write_keyword("DATETIME"),
+            DataType::Array(inner) => {
+                self.write_keyword("ARRAY<")?;
+                self.write_data_type(inner)?;
+                self.write(">")
+            },
+            DataType::Regclass => unsupported("BigQuery has no REGCLASS data type"),
+            DataType::Custom(name) => {
+                let last = name.0.last().map(|n| n.to_uppercase()).unwrap_or_default();
+                match last.as_str() {
+                    "NVARCHAR" | "NCHAR" | "NTEXT" | "UNIQUEIDENTIFIER" => self.write_keyword("STRING"),
+                    "TINYINT" | "INTEGER" => self.write_keyword("INT64"),
+                    "BIT" => self.write_keyword("BOOL"),
+                    "DATETIME2" | "SMALLDATETIME" => self.write_keyword("DATETIME"),
+                    "DATETIMEOFFSET" => self.write_keyword("TIMESTAMP"),
+                    "MONEY" => self.write_keyword("NUMERIC"),
+                    _ => write_bare_name(self, name),
+                }
+            },
+            _ => generic::write_data_type(self, node),
+        }
+    }
+
+    fn write_value(&mut self, node: &Value) -> Res<()> {
+        match node {
+            Value::NationalStringLiteral(s) => self.write(&generic::quote_string(s)),
+            Value::HexStringLiteral(s) => self.write(&format!("FROM_HEX('{}')", s)),
+            Value::Boolean(true) => self.write_keyword("TRUE"),
+            Value::Boolean(false) => self.write_keyword("FALSE"),
+            _ => generic::write_value(self, node),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn names_and_types() {
+        let s = Select {
+            distinct: false,
+            projection: vec![
+                SelectItem::UnnamedExpr(Expr::Cast {
+                    expr: Box::new(Expr::Identifier("\"id\"".to_string())),
+                    data_type: DataType::BigInt,
+                }),
+                SelectItem::UnnamedExpr(Expr::Cast {
+                    expr: Box::new(Expr::Identifier("name".to_string())),
+                    data_type: DataType::Varchar(None),
+                }),
+            ],
+            from: vec![TableWithJoins {
+                relation: TableFactor::Table {
+                    name: ObjectName(vec!["my-project".to_string(), "sales".to_string(), "orders".to_string()]),
+                    alias: None,
+                    args: vec![],
+                    with_hints: vec![],
+                },
+                joins: vec![],
+            }],
+            selection: None,
+            group_by: vec![],
+            having: None,
+        };
+        let mut w = BigQueryWriter::new("    ", Vec::new()).safe_cast(true);
+        w.write_select(&s).unwrap();
+        let out = String::from_utf8(w.into_inner()).unwrap();
+        assert_eq!(out, "SELECT SAFE_CAST(`id` AS INT64), SAFE_CAST(name AS STRING)
+FROM `my-project.sales.orders`")
+    }
+}
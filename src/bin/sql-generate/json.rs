@@ -0,0 +1,228 @@
+//! sqlparser 0.5 has no serde support, so the AST is dumped
+//! by reading its `Debug` output back in. Structs and enum
+//! variants become `{"Name": ...}`, unit variants their name,
+//! `None` is `null` and `Some(x)` is just `x`.
+use serde_json::{Map, Value};
+use std::{iter::Peekable, str::Chars};
+
+type Res<T> = Result<T, String>;
+
+/// Convert the `{:?}` text of a value into JSON
+pub fn from_debug(s: &str) -> Res<Value> {
+    let mut chars = s.chars().peekable();
+    let value = parse_value(&mut chars)?;
+    skip_whitespace(&mut chars);
+    match chars.next() {
+        None => Ok(value),
+        Some(c) => Err(format!("unexpected {:?} after the value", c)),
+    }
+}
+
+fn parse_value(chars: &mut Peekable<Chars>) -> Res<Value> {
+    skip_whitespace(chars);
+    match chars.peek().copied() {
+        Some('"') => {
+            chars.next();
+            Ok(Value::String(parse_escaped(chars, '"')?))
+        },
+        Some('\'') => {
+            chars.next();
+            Ok(Value::String(parse_escaped(chars, '\'')?))
+        },
+        Some('[') => {
+            chars.next();
+            Ok(Value::Array(parse_list(chars, ']')?))
+        },
+        Some(c) if c == '-' || c.is_ascii_digit() => {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek() {
+                if c == '-' || c == '.' || c.is_ascii_alphanumeric() {
+                    number.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            number.parse::<serde_json::Number>()
+                .map(Value::Number)
+                .map_err(|_| format!("invalid number {}", number))
+        },
+        Some(c) if c.is_alphabetic() || c == '_' => {
+            let name = parse_name(chars);
+            skip_whitespace(chars);
+            match (name.as_str(), chars.peek()) {
+                ("true", _) => Ok(Value::Bool(true)),
+                ("false", _) => Ok(Value::Bool(false)),
+                ("None", _) => Ok(Value::Null),
+                (_, Some('{')) => {
+                    chars.next();
+                    let fields = parse_fields(chars)?;
+                    Ok(tagged(name, Value::Object(fields)))
+                },
+                (_, Some('(')) => {
+                    chars.next();
+                    let mut items = parse_list(chars, ')')?;
+                    if name == "Some" && items.len() == 1 {
+                        return Ok(items.remove(0));
+                    }
+                    if items.len() == 1 {
+                        return Ok(tagged(name, items.remove(0)));
+                    }
+                    Ok(tagged(name, Value::Array(items)))
+                },
+                _ => Ok(Value::String(name)),
+            }
+        },
+        Some(c) => Err(format!("unexpected {:?}", c)),
+        None => Err("unexpected end of input".to_string()),
+    }
+}
+
+fn tagged(name: String, value: Value) -> Value {
+    let mut map = Map::new();
+    map.insert(name, value);
+    Value::Object(map)
+}
+
+fn parse_name(chars: &mut Peekable<Chars>) -> String {
+    let mut name = String::new();
+    while let Some(&c) = chars.peek() {
+        if c.is_alphanumeric() || c == '_' {
+            name.push(c);
+            chars.next();
+        } else {
+            break;
+        }
+    }
+    name
+}
+
+/// Items separated by `,` up to `close`, allowing a trailing comma
+fn parse_list(chars: &mut Peekable<Chars>, close: char) -> Res<Vec<Value>> {
+    let mut items = Vec::new();
+    loop {
+        skip_whitespace(chars);
+        if chars.peek() == Some(&close) {
+            chars.next();
+            return Ok(items);
+        }
+        items.push(parse_value(chars)?);
+        skip_whitespace(chars);
+        match chars.next() {
+            Some(',') => (),
+            Some(c) if c == close => return Ok(items),
+            other => return Err(format!("expected , or {} found {:?}", close, other)),
+        }
+    }
+}
+
+/// `name: value` pairs separated by `,` up to `}`
+fn parse_fields(chars: &mut Peekable<Chars>) -> Res<Map<String, Value>> {
+    let mut fields = Map::new();
+    loop {
+        skip_whitespace(chars);
+        if chars.peek() == Some(&'}') {
+            chars.next();
+            return Ok(fields);
+        }
+        let name = parse_name(chars);
+        skip_whitespace(chars);
+        if chars.next() != Some(':') {
+            return Err(format!("expected : after field {}", name));
+        }
+        let value = parse_value(chars)?;
+        fields.insert(name, value);
+        skip_whitespace(chars);
+        match chars.next() {
+            Some(',') => (),
+            Some('}') => return Ok(fields),
+            other => return Err(format!("expected , or }} found {:?}", other)),
+        }
+    }
+}
+
+/// The rest of a string or char literal, undoing the escapes `Debug` adds
+fn parse_escaped(chars: &mut Peekable<Chars>, quote: char) -> Res<String> {
+    let mut s = String::new();
+    loop {
+        match chars.next() {
+            Some('\\') => match chars.next() {
+                Some('n') => s.push('\n'),
+                Some('r') => s.push('\r'),
+                Some('t') => s.push('\t'),
+                Some('0') => s.push('\0'),
+                Some('u') => {
+                    let hex: String = chars.by_ref()
+                        .skip_while(|c| *c == '{')
+                        .take_while(|c| *c != '}')
+                        .collect();
+                    let c = u32::from_str_radix(&hex, 16).ok()
+                        .and_then(std::char::from_u32)
+                        .ok_or_else(|| format!("invalid escape \\u{{{}}}", hex))?;
+                    s.push(c);
+                },
+                Some(c) => s.push(c),
+                None => break,
+            },
+            Some(c) if c == quote => return Ok(s),
+            Some(c) => s.push(c),
+            None => break,
+        }
+    }
+    Err("unterminated string".to_string())
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.peek().map(|c| c.is_whitespace()).unwrap_or(false) {
+        chars.next();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::from_debug;
+    use sqlparser::ast::*;
+
+    #[test]
+    fn statement_as_json() {
+        let stmt = Statement::Query(Box::new(Query {
+            ctes: vec![],
+            body: SetExpr::Select(Box::new(Select {
+                distinct: false,
+                projection: vec![SelectItem::UnnamedExpr(Expr::Identifier("[say \"hi\"]".to_string()))],
+                from: vec![],
+                selection: Some(Expr::Value(Value::Null)),
+                group_by: vec![],
+                having: None,
+            })),
+            order_by: vec![],
+            limit: Some(Expr::Value(Value::Number("10".to_string()))),
+            offset: None,
+            fetch: None,
+        }));
+        let json = from_debug(&format!("{:?}", vec![stmt])).unwrap();
+        assert_eq!(json, serde_json::json!([{
+            "Query": {
+                "Query": {
+                    "ctes": [],
+                    "body": {
+                        "Select": {
+                            "Select": {
+                                "distinct": false,
+                                "projection": [{ "UnnamedExpr": { "Identifier": "[say \"hi\"]" } }],
+                                "from": [],
+                                "selection": { "Value": "Null" },
+                                "group_by": [],
+                                "having": null,
+                            }
+                        }
+                    },
+                    "order_by": [],
+                    "limit": { "Value": { "Number": "10" } },
+                    "offset": null,
+                    "fetch": null,
+                }
+            }
+        }]));
+    }
+}
@@ -0,0 +1,207 @@
+use similar::TextDiff;
+use sql_generate::{parse_batches, transpile_with_warnings, Config, Target};
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+use structopt::StructOpt;
+
+mod json;
+
+type Res<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Format, check and transpile SQL.
+///
+/// Every command reads stdin when no path is given and
+/// the .sql files of any directory recursively. Settings
+/// come from the closest .sqlgenerate.toml.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "sql-generate")]
+enum Command {
+    /// Format files in place, or stdin to stdout
+    Fmt {
+        /// Print the formatted SQL instead of rewriting the files
+        #[structopt(long)]
+        stdout: bool,
+        /// The dialect of the files, overrides the config
+        #[structopt(long)]
+        dialect: Option<Target>,
+        #[structopt(parse(from_os_str))]
+        paths: Vec<PathBuf>,
+    },
+    /// Print a diff and exit with an error for every file that is not formatted
+    Check {
+        /// The dialect of the files, overrides the config
+        #[structopt(long)]
+        dialect: Option<Target>,
+        #[structopt(parse(from_os_str))]
+        paths: Vec<PathBuf>,
+    },
+    /// Rewrite SQL from one dialect for another, printing the result
+    Transpile {
+        #[structopt(long)]
+        from: Target,
+        #[structopt(long)]
+        to: Target,
+        #[structopt(parse(from_os_str))]
+        paths: Vec<PathBuf>,
+    },
+    /// Print the parsed statements as JSON
+    Ast {
+        /// The dialect of the files, overrides the config
+        #[structopt(long)]
+        dialect: Option<Target>,
+        #[structopt(parse(from_os_str))]
+        paths: Vec<PathBuf>,
+    },
+}
+
+/// Where a piece of SQL comes from
+#[derive(Debug)]
+enum Input {
+    Stdin,
+    File(PathBuf),
+}
+
+impl Input {
+    fn read(&self) -> Res<String> {
+        match self {
+            Input::Stdin => {
+                let mut s = String::new();
+                std::io::stdin().read_to_string(&mut s)?;
+                Ok(s)
+            },
+            Input::File(path) => Ok(std::fs::read_to_string(path)?),
+        }
+    }
+
+    fn config(&self) -> Res<Config> {
+        match self {
+            Input::Stdin => Config::discover(std::env::current_dir()?),
+            Input::File(path) => Config::discover(path),
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Input::Stdin => "<stdin>".to_string(),
+            Input::File(path) => path.display().to_string(),
+        }
+    }
+}
+
+/// The given paths with every directory replaced by
+/// the .sql files under it, stdin when there are none
+fn inputs(paths: &[PathBuf]) -> Res<Vec<Input>> {
+    if paths.is_empty() {
+        return Ok(vec![Input::Stdin]);
+    }
+    let mut inputs = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            inputs.push(Input::File(path.clone()));
+            continue;
+        }
+        for entry in walkdir::WalkDir::new(path).sort_by(|a, b| a.file_name().cmp(b.file_name())) {
+            let entry = entry?;
+            if entry.file_type().is_file() && is_sql(entry.path()) {
+                inputs.push(Input::File(entry.into_path()));
+            }
+        }
+    }
+    Ok(inputs)
+}
+
+fn is_sql(path: &Path) -> bool {
+    path.extension().map(|e| e.eq_ignore_ascii_case("sql")).unwrap_or(false)
+}
+
+/// `sql` formatted for its own dialect, ending with a new line
+fn format(sql: &str, dialect: Option<Target>, config: &Config) -> Res<String> {
+    let target = dialect.or(config.dialect).unwrap_or(Target::MsSql);
+    let mut formatted = sql_generate::format(sql, target, config.format_options())?;
+    formatted.push('\n');
+    Ok(formatted)
+}
+
+/// Run `f` for every input, reporting errors and carrying on
+/// with the rest. Returns false if any of them failed.
+fn each_input(paths: &[PathBuf], mut f: impl FnMut(&Input, String) -> Res<bool>) -> Res<bool> {
+    let mut ok = true;
+    for input in inputs(paths)? {
+        let result = input.read().and_then(|sql| f(&input, sql));
+        match result {
+            Ok(passed) => ok &= passed,
+            Err(e) => {
+                eprintln!("error: {}: {}", input.name(), e);
+                ok = false;
+            },
+        }
+    }
+    Ok(ok)
+}
+
+fn run(command: Command) -> Res<bool> {
+    let stdout = std::io::stdout();
+    match command {
+        Command::Fmt { stdout: to_stdout, dialect, paths } => each_input(&paths, |input, sql| {
+            let formatted = format(&sql, dialect, &input.config()?)?;
+            match input {
+                Input::File(path) if !to_stdout => {
+                    if formatted != sql {
+                        std::fs::write(path, formatted)?;
+                    }
+                },
+                _ => stdout.lock().write_all(formatted.as_bytes())?,
+            }
+            Ok(true)
+        }),
+        Command::Check { dialect, paths } => each_input(&paths, |input, sql| {
+            let formatted = format(&sql, dialect, &input.config()?)?;
+            if formatted == sql {
+                return Ok(true);
+            }
+            let name = input.name();
+            let diff = TextDiff::from_lines(&sql, &formatted);
+            write!(stdout.lock(), "{}", diff.unified_diff().header(&name, &name))?;
+            Ok(false)
+        }),
+        Command::Transpile { from, to, paths } => {
+            let many = paths.len() > 1 || paths.iter().any(|p| p.is_dir());
+            each_input(&paths, |input, sql| {
+                let options = input.config()?.format_options();
+                let (transpiled, warnings) = transpile_with_warnings(&sql, &*from.dialect(), to, options)?;
+                for warning in warnings {
+                    eprintln!("warning: {}: {}", input.name(), warning);
+                }
+                let mut out = stdout.lock();
+                if many {
+                    writeln!(out, "-- {}", input.name())?;
+                }
+                writeln!(out, "{}", transpiled)?;
+                Ok(true)
+            })
+        },
+        Command::Ast { dialect, paths } => each_input(&paths, |input, sql| {
+            let target = dialect.or(input.config()?.dialect).unwrap_or(Target::MsSql);
+            let stmts: Vec<_> = parse_batches(&sql, &*target.dialect())?
+                .into_iter()
+                .flat_map(|batch| batch.statements)
+                .collect();
+            let ast = json::from_debug(&format!("{:?}", stmts))?;
+            writeln!(stdout.lock(), "{}", serde_json::to_string_pretty(&ast)?)?;
+            Ok(true)
+        }),
+    }
+}
+
+fn main() {
+    match run(Command::from_args()) {
+        Ok(true) => (),
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(2);
+        },
+    }
+}
@@ -0,0 +1,370 @@
+//! Comments are dropped by the parser so they travel next
+//! to the AST instead: `Comments::extract` scans the source for
+//! them and anchors each one to a word of the source (a
+//! string literal or punctuation mark counts as a word), the
+//! writer then re-emits a comment when it writes that word.
+//!
+//! A word is identified by its text and how many times that
+//! text appeared before it, so rewrites that add or remove
+//! words ahead of a comment can move it. Comments whose word
+//! is never written come out with `SqlWriter::flush_comments`.
+use std::collections::HashMap;
+
+/// The comments of a SQL source, see `Comments::extract`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Comments {
+    pending: Vec<Comment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Comment {
+    /// The comment including its `--` or `/* */`
+    text: String,
+    anchor: Anchor,
+    /// Nothing but whitespace shares the source line with it
+    own_line: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Anchor {
+    /// Written right before this occurrence of a word
+    Before(String, usize),
+    /// Written right after this occurrence of a word
+    After(String, usize),
+    /// The source has no words at all
+    None,
+}
+
+impl Comments {
+    /// Find every `--` and `/* */` comment in `sql`
+    pub fn extract(sql: &str) -> Self {
+        let (words, comments) = lex(sql);
+        let mut seen = HashMap::new();
+        let words: Vec<(Word, usize)> = words.into_iter()
+            .map(|word| {
+                let count = seen.entry(word.text.clone()).or_insert(0);
+                let ordinal = *count;
+                *count += 1;
+                (word, ordinal)
+            })
+            .collect();
+        let pending = comments.into_iter()
+            .map(|c| {
+                let next = words.iter().position(|(w, _)| w.start >= c.start);
+                let prev = next.unwrap_or(words.len()).checked_sub(1).map(|i| &words[i]);
+                let after_prev_on_line = prev.map(|(w, _)| w.line == c.line).unwrap_or(false);
+                let anchor = match (prev, next) {
+                    (Some((w, n)), _) if after_prev_on_line => Anchor::After(w.text.clone(), *n),
+                    (_, Some(i)) => Anchor::Before(words[i].0.text.clone(), words[i].1),
+                    (Some((w, n)), None) => Anchor::After(w.text.clone(), *n),
+                    (None, None) => Anchor::None,
+                };
+                Comment {
+                    text: c.text,
+                    anchor,
+                    own_line: !after_prev_on_line && !c.follows_text,
+                }
+            })
+            .collect();
+        Self { pending }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+/// Where a pending comment should be written
+pub(crate) enum Placement {
+    /// On its own line before the word
+    LineBefore(String),
+    /// On the same line before the word
+    InlineBefore(String),
+    /// On the same line after the word
+    InlineAfter(String),
+    /// On its own line after the word
+    LineAfter(String),
+}
+
+impl Placement {
+    pub fn is_line_comment(&self) -> bool {
+        let text = match self {
+            Placement::LineBefore(text)
+            | Placement::InlineBefore(text)
+            | Placement::InlineAfter(text)
+            | Placement::LineAfter(text) => text,
+        };
+        text.starts_with("--")
+    }
+}
+
+/// Tracks the words written so far and hands
+/// out comments as their words come up
+#[derive(Debug, Default)]
+pub(crate) struct CommentQueue {
+    pending: Vec<Comment>,
+    seen: HashMap<String, usize>,
+}
+
+impl CommentQueue {
+    pub fn new(comments: Comments) -> Self {
+        Self {
+            pending: comments.pending,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Split a chunk of output into the text between words, each
+    /// word and the comments to write before and after that word
+    pub fn place(&mut self, s: &str) -> Vec<(String, Vec<Placement>, Vec<Placement>)> {
+        let (words, _) = lex(s);
+        let mut out = Vec::new();
+        let mut written = 0;
+        for word in words {
+            let count = self.seen.entry(word.text.clone()).or_insert(0);
+            let ordinal = *count;
+            *count += 1;
+            let before = self.take_through(|a| *a == Anchor::Before(word.text.clone(), ordinal));
+            let after = self.take_through(|a| *a == Anchor::After(word.text.clone(), ordinal));
+            if before.is_empty() && after.is_empty() {
+                continue;
+            }
+            if written < word.start {
+                out.push((s[written..word.start].to_string(), vec![], vec![]));
+            }
+            out.push((s[word.start..word.end].to_string(), before, after));
+            written = word.end;
+        }
+        if written < s.len() {
+            out.push((s[written..].to_string(), vec![], vec![]));
+        }
+        out
+    }
+
+    /// Every comment whose word never came up
+    pub fn remaining(&mut self) -> Vec<Placement> {
+        self.pending.drain(..).map(|c| Placement::LineAfter(c.text)).collect()
+    }
+
+    /// Remove the first comment with an anchor matching `f`
+    /// and every comment that came before it in the source
+    fn take_through(&mut self, f: impl Fn(&Anchor) -> bool) -> Vec<Placement> {
+        let idx = match self.pending.iter().position(|c| f(&c.anchor)) {
+            Some(idx) => idx,
+            None => return Vec::new(),
+        };
+        self.pending.drain(..=idx)
+            .map(|c| match (&c.anchor, c.own_line) {
+                (Anchor::After(..), true) => Placement::LineAfter(c.text),
+                (Anchor::After(..), false) => Placement::InlineAfter(c.text),
+                (_, true) => Placement::LineBefore(c.text),
+                (_, false) => Placement::InlineBefore(c.text),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+struct Word {
+    /// Upper case, without any identifier quotes, string
+    /// literals keep their quotes and case
+    text: String,
+    start: usize,
+    end: usize,
+    line: usize,
+}
+
+#[derive(Debug)]
+struct RawComment {
+    text: String,
+    start: usize,
+    line: usize,
+    /// Something other than whitespace precedes it on its line
+    follows_text: bool,
+}
+
+/// Split `s` into words, string literals, punctuation
+/// and comments
+fn lex(s: &str) -> (Vec<Word>, Vec<RawComment>) {
+    let chars: Vec<(usize, char)> = s.char_indices().collect();
+    let byte_at = |i: usize| chars.get(i).map(|(b, _)| *b).unwrap_or(s.len());
+    let mut words = Vec::new();
+    let mut comments = Vec::new();
+    let mut line = 0;
+    let mut line_has_text = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i].1;
+        let next = chars.get(i + 1).map(|(_, c)| *c);
+        let start = i;
+        match c {
+            '\n' => {
+                line += 1;
+                line_has_text = false;
+                i += 1;
+                continue;
+            },
+            '-' if next == Some('-') => {
+                while i < chars.len() && chars[i].1 != '\n' {
+                    i += 1;
+                }
+                comments.push(RawComment {
+                    text: s[byte_at(start)..byte_at(i)].trim_end().to_string(),
+                    start: byte_at(start),
+                    line,
+                    follows_text: line_has_text,
+                });
+                continue;
+            },
+            '/' if next == Some('*') => {
+                let first_line = line;
+                i += 2;
+                while i < chars.len() && !(chars[i].1 == '*' && chars.get(i + 1).map(|(_, c)| *c) == Some('/')) {
+                    if chars[i].1 == '\n' {
+                        line += 1;
+                    }
+                    i += 1;
+                }
+                i = (i + 2).min(chars.len());
+                comments.push(RawComment {
+                    text: s[byte_at(start)..byte_at(i)].to_string(),
+                    start: byte_at(start),
+                    line: first_line,
+                    follows_text: line_has_text,
+                });
+                line_has_text = true;
+                continue;
+            },
+            '\'' => {
+                i += 1;
+                while i < chars.len() {
+                    if chars[i].1 == '\'' {
+                        if chars.get(i + 1).map(|(_, c)| *c) == Some('\'') {
+                            i += 2;
+                            continue;
+                        }
+                        break;
+                    }
+                    if chars[i].1 == '\n' {
+                        line += 1;
+                    }
+                    i += 1;
+                }
+                i += 1;
+                words.push(Word {
+                    text: s[byte_at(start)..byte_at(i)].to_string(),
+                    start: byte_at(start),
+                    end: byte_at(i),
+                    line,
+                });
+            },
+            '"' | '[' | '`' => {
+                let close = match c {
+                    '[' => ']',
+                    c => c,
+                };
+                i += 1;
+                while i < chars.len() && chars[i].1 != close {
+                    i += 1;
+                }
+                words.push(Word {
+                    text: s[byte_at(start + 1)..byte_at(i)].to_uppercase(),
+                    start: byte_at(start),
+                    end: byte_at(i + 1),
+                    line,
+                });
+                i += 1;
+            },
+            c if is_word_char(c) => {
+                while i < chars.len() && is_word_char(chars[i].1) {
+                    i += 1;
+                }
+                words.push(Word {
+                    text: s[byte_at(start)..byte_at(i)].to_uppercase(),
+                    start: byte_at(start),
+                    end: byte_at(i),
+                    line,
+                });
+            },
+            c if c.is_whitespace() => i += 1,
+            c => {
+                words.push(Word {
+                    text: c.to_string(),
+                    start: byte_at(i),
+                    end: byte_at(i + 1),
+                    line,
+                });
+                i += 1;
+            },
+        }
+        if !c.is_whitespace() {
+            line_has_text = true;
+        }
+    }
+    (words, comments)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '@' || c == '#' || c == '$'
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{MsSqlWriter, SqlWriter};
+    use sqlparser::ast::*;
+
+    #[test]
+    fn re_emit_in_place() {
+        let sql = "-- monthly totals
+SELECT id, total -- both columns
+FROM orders /* every order */
+WHERE total > 10
+-- done";
+        let comments = Comments::extract(sql);
+        assert_eq!(comments.len(), 4);
+        let s = Select {
+            distinct: false,
+            projection: vec![
+                SelectItem::UnnamedExpr(Expr::Identifier("id".to_string())),
+                SelectItem::UnnamedExpr(Expr::Identifier("total".to_string())),
+            ],
+            from: vec![TableWithJoins {
+                relation: TableFactor::Table {
+                    name: ObjectName(vec!["orders".to_string()]),
+                    alias: None,
+                    args: vec![],
+                    with_hints: vec![],
+                },
+                joins: vec![],
+            }],
+            selection: Some(Expr::BinaryOp {
+                left: Box::new(Expr::Identifier("total".to_string())),
+                op: BinaryOperator::Gt,
+                right: Box::new(Expr::Value(Value::Number("10".to_string()))),
+            }),
+            group_by: vec![],
+            having: None,
+        };
+        let mut w = MsSqlWriter::new("    ", Vec::new()).comments(comments);
+        w.write_select(&s).unwrap();
+        w.flush_comments().unwrap();
+        let out = String::from_utf8(w.into_inner()).unwrap();
+        assert_eq!(out, sql)
+    }
+
+    #[test]
+    fn after_literals_and_commas() {
+        let sql = "SELECT a, -- first
+b
+FROM t
+WHERE x = 'a' -- c
+AND y = 2";
+        let out = crate::format(sql, crate::Target::MsSql, Default::default()).unwrap();
+        assert_eq!(out, sql);
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{Case, FormatOptions, Layout, Quoting, Res, Target};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// The file `Config::discover` looks for
+pub const CONFIG_FILE: &str = ".sqlgenerate.toml";
+
+/// Project wide formatter settings read from a `.sqlgenerate.toml`,
+/// any setting left out keeps the `FormatOptions` default.
+///
+/// ```toml
+/// indent = 4
+/// width = 100
+/// keyword-case = "upper"
+/// dialect = "mssql"
+/// quoting = "preserve"
+/// layout = "river"
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub indent: Option<Indent>,
+    /// The maximum line width
+    pub width: Option<usize>,
+    pub keyword_case: Option<Case>,
+    /// The dialect to write
+    pub dialect: Option<Target>,
+    pub quoting: Option<Quoting>,
+    pub layout: Option<Layout>,
+}
+
+/// Either a number of spaces or the literal text
+/// to write for each indentation level
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Indent {
+    Spaces(usize),
+    Text(String),
+}
+
+impl Config {
+    pub fn parse(s: &str) -> Res<Self> {
+        Ok(toml::from_str(s)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Res<Self> {
+        let path = path.as_ref();
+        let s = std::fs::read_to_string(path)?;
+        Self::parse(&s).map_err(|e| format!("{}: {}", path.display(), e).into())
+    }
+
+    /// The closest `.sqlgenerate.toml` in the directory of
+    /// `input` or any of its parents
+    pub fn find(input: impl AsRef<Path>) -> Option<PathBuf> {
+        let input = input.as_ref();
+        let start = if input.is_dir() {
+            input
+        } else {
+            input.parent()?
+        };
+        start.ancestors()
+            .map(|dir| dir.join(CONFIG_FILE))
+            .find(|path| path.is_file())
+    }
+
+    /// Load the configuration that applies to `input`,
+    /// the default configuration if there is none
+    pub fn discover(input: impl AsRef<Path>) -> Res<Self> {
+        match Self::find(input) {
+            Some(path) => Self::load(path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn format_options(&self) -> FormatOptions {
+        let mut options = FormatOptions::new();
+        match &self.indent {
+            Some(Indent::Spaces(n)) => options.indent = " ".repeat(*n).into(),
+            Some(Indent::Text(text)) => options.indent = text.clone().into(),
+            None => (),
+        }
+        options.max_width = self.width;
+        if let Some(case) = self.keyword_case {
+            options.keyword_case = case;
+        }
+        if let Some(quoting) = self.quoting {
+            options.quoting = quoting;
+        }
+        if let Some(layout) = self.layout {
+            options.layout = layout;
+        }
+        options
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn discover_from_parent() {
+        let root = std::env::temp_dir().join(format!("sqlgenerate-config-{}", std::process::id()));
+        let nested = root.join("queries").join("reports");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(root.join(CONFIG_FILE), "indent = 2
+width = 80
+keyword-case = \"lower\"
+dialect = \"postgres\"
+quoting = \"always\"
+").unwrap();
+        let config = Config::discover(nested.join("daily.sql")).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(config.dialect, Some(Target::Postgres));
+        let options = config.format_options();
+        assert_eq!(options.indent, "  ");
+        assert_eq!(options.max_width, Some(80));
+        assert_eq!(options.keyword_case, Case::Lower);
+        assert_eq!(options.quoting, Quoting::Always);
+        assert!(Config::parse("indent = 2\ncolour = true").is_err());
+    }
+}
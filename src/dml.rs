@@ -0,0 +1,331 @@
+//! T-SQL's `OUTPUT` clause, which returns the rows an `INSERT`,
+//! `UPDATE`, `DELETE` or `MERGE` changed through the `inserted`
+//! and `deleted` pseudo-tables. sqlparser 0.5 cannot read it, or
+//! the `FROM` of an `UPDATE`, so the three DML statements are
+//! parsed here, PostgreSQL gets the same rows back with `RETURNING`.
+use crate::{
+    error::unsupported,
+    generic::write_from,
+    tsql::{expect_word, parse_word, TsqlStatement},
+    write_clause, write_condition, write_separated_idents, Res, SqlWriter,
+};
+use sqlparser::{
+    ast::*,
+    parser::{Parser, ParserError},
+    tokenizer::Token,
+};
+
+/// `OUTPUT inserted.column, deleted.column, ... [INTO target [(column, ...)]]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputClause {
+    pub items: Vec<SelectItem>,
+    /// Where the rows go instead of the client, a table
+    /// or a table variable
+    pub into: Option<OutputInto>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputInto {
+    pub target: ObjectName,
+    pub columns: Vec<Ident>,
+}
+
+/// An `OUTPUT` clause if one follows
+pub(crate) fn parse_output(parser: &mut Parser) -> Result<Option<OutputClause>, ParserError> {
+    if !parse_word(parser, "OUTPUT") {
+        return Ok(None);
+    }
+    let mut items = vec![parse_output_item(parser)?];
+    while parser.consume_token(&Token::Comma) {
+        items.push(parse_output_item(parser)?);
+    }
+    let into = if parse_word(parser, "INTO") {
+        let target = parser.parse_object_name()?;
+        let columns = parse_columns(parser)?;
+        Some(OutputInto { target, columns })
+    } else {
+        None
+    };
+    Ok(Some(OutputClause { items, into }))
+}
+
+/// sqlparser would read the `INTO` or `VALUES` that can follow
+/// the clause as an alias, so only `AS` or a word that is
+/// not a keyword starts one here
+fn parse_output_item(parser: &mut Parser) -> Result<SelectItem, ParserError> {
+    let expr = match parser.parse_expr()? {
+        Expr::Wildcard => return Ok(SelectItem::Wildcard),
+        Expr::QualifiedWildcard(parts) => return Ok(SelectItem::QualifiedWildcard(ObjectName(parts))),
+        expr => expr,
+    };
+    let alias = match parser.peek_token() {
+        Some(Token::Word(w)) if w.keyword == "AS" => {
+            parser.next_token();
+            Some(parser.parse_identifier()?)
+        },
+        Some(Token::Word(w)) if w.keyword.is_empty() => Some(parser.parse_identifier()?),
+        _ => None,
+    };
+    Ok(match alias {
+        Some(alias) => SelectItem::ExprWithAlias { expr, alias },
+        None => SelectItem::UnnamedExpr(expr),
+    })
+}
+
+/// The rest of an `INSERT` after the keyword, `INTO` is optional in T-SQL
+pub(crate) fn parse_insert(parser: &mut Parser) -> Result<TsqlStatement, ParserError> {
+    parse_word(parser, "INTO");
+    let table_name = parser.parse_object_name()?;
+    let columns = parse_columns(parser)?;
+    let output = parse_output(parser)?;
+    let source = Box::new(parser.parse_query()?);
+    Ok(with_output(Statement::Insert { table_name, columns, source }, output))
+}
+
+/// The rest of an `UPDATE` after the keyword
+pub(crate) fn parse_update(parser: &mut Parser) -> Result<TsqlStatement, ParserError> {
+    let table_name = parser.parse_object_name()?;
+    expect_word(parser, "SET")?;
+    let mut assignments = Vec::new();
+    loop {
+        let id = parser.parse_identifier()?;
+        parser.expect_token(&Token::Eq)?;
+        let value = parser.parse_expr()?;
+        assignments.push(Assignment { id, value });
+        if !parser.consume_token(&Token::Comma) {
+            break;
+        }
+    }
+    let output = parse_output(parser)?;
+    let from = if parse_word(parser, "FROM") {
+        parser.parse_comma_separated(Parser::parse_table_and_joins)?
+    } else {
+        vec![]
+    };
+    let selection = parse_where(parser)?;
+    let statement = Statement::Update { table_name, assignments, selection };
+    if !from.is_empty() {
+        return Ok(TsqlStatement::UpdateFrom { statement, from, output });
+    }
+    Ok(with_output(statement, output))
+}
+
+/// The rest of a `DELETE` after the keyword, `FROM` is optional in T-SQL
+pub(crate) fn parse_delete(parser: &mut Parser) -> Result<TsqlStatement, ParserError> {
+    parse_word(parser, "FROM");
+    let table_name = parser.parse_object_name()?;
+    let output = parse_output(parser)?;
+    let selection = parse_where(parser)?;
+    Ok(with_output(Statement::Delete { table_name, selection }, output))
+}
+
+fn with_output(statement: Statement, output: Option<OutputClause>) -> TsqlStatement {
+    match output {
+        Some(output) => TsqlStatement::Dml { statement, output },
+        None => TsqlStatement::Statement(statement),
+    }
+}
+
+/// An optional `(column, ...)`
+fn parse_columns(parser: &mut Parser) -> Result<Vec<Ident>, ParserError> {
+    let mut columns = Vec::new();
+    if !parser.consume_token(&Token::LParen) {
+        return Ok(columns);
+    }
+    columns.push(parser.parse_identifier()?);
+    while parser.consume_token(&Token::Comma) {
+        columns.push(parser.parse_identifier()?);
+    }
+    parser.expect_token(&Token::RParen)?;
+    Ok(columns)
+}
+
+fn parse_where(parser: &mut Parser) -> Result<Option<Expr>, ParserError> {
+    if parse_word(parser, "WHERE") {
+        return Ok(Some(parser.parse_expr()?));
+    }
+    Ok(None)
+}
+
+/// Write the `OUTPUT` clause on a line of its own
+pub(crate) fn write_output<S>(w: &mut S, node: &OutputClause) -> Res<()>
+where S: SqlWriter + ?Sized {
+    w.write_new_line()?;
+    w.write_prefix()?;
+    write_clause(w, "OUTPUT ")?;
+    write_select_items(w, &node.items)?;
+    if let Some(into) = &node.into {
+        w.write_keyword(" INTO ")?;
+        w.write_object_name(&into.target)?;
+        if !into.columns.is_empty() {
+            w.write(" (")?;
+            write_separated_idents(w, ", ", &into.columns)?;
+            w.write(")")?;
+        }
+    }
+    Ok(())
+}
+
+/// Write a DML statement with its `OUTPUT` clause where T-SQL
+/// expects it, before the source of an `INSERT` and before
+/// the `WHERE` of an `UPDATE` or `DELETE`
+pub(crate) fn write_output_statement<S>(w: &mut S, statement: &Statement, output: &OutputClause) -> Res<()>
+where S: SqlWriter + ?Sized {
+    match statement {
+        Statement::Insert { table_name, columns, source } => {
+            w.write_keyword("INSERT INTO ")?;
+            w.write_object_name(table_name)?;
+            if !columns.is_empty() {
+                w.write(" (")?;
+                write_separated_idents(w, ", ", columns)?;
+                w.write(")")?;
+            }
+            write_output(w, output)?;
+            w.write_new_line()?;
+            w.write_prefix()?;
+            w.write_query(source)
+        },
+        Statement::Update { .. } => write_update_from(w, statement, &[], Some(output)),
+        Statement::Delete { table_name, selection } => {
+            w.write_keyword("DELETE FROM ")?;
+            w.write_object_name(table_name)?;
+            write_output(w, output)?;
+            write_where(w, selection)
+        },
+        _ => unsupported("only INSERT, UPDATE and DELETE have an OUTPUT clause"),
+    }
+}
+
+/// Write a T-SQL `UPDATE` with its optional `OUTPUT` clause
+/// and the `FROM` clause it takes the new values from
+pub(crate) fn write_update_from<S>(w: &mut S, statement: &Statement, from: &[TableWithJoins], output: Option<&OutputClause>) -> Res<()>
+where S: SqlWriter + ?Sized {
+    let (table_name, assignments, selection) = match statement {
+        Statement::Update { table_name, assignments, selection } => (table_name, assignments, selection),
+        _ => return unsupported("only an UPDATE has a FROM clause"),
+    };
+    w.write_keyword("UPDATE ")?;
+    w.write_object_name(table_name)?;
+    w.write_new_line()?;
+    w.write_prefix()?;
+    write_clause(w, "SET ")?;
+    let mut after_first = false;
+    for assignment in assignments {
+        if after_first {
+            w.write(", ")?;
+        }
+        w.write_assignment(assignment)?;
+        after_first = true;
+    }
+    if let Some(output) = output {
+        write_output(w, output)?;
+    }
+    write_from(w, from)?;
+    write_where(w, selection)
+}
+
+/// PostgreSQL, the statement followed by `RETURNING`. The
+/// columns lose their pseudo-table, which has to be the rows
+/// the statement leaves behind, `inserted` for an `INSERT` or
+/// `UPDATE` and `deleted` for a `DELETE`
+pub(crate) fn write_returning<S>(w: &mut S, statement: &Statement, output: &OutputClause) -> Res<()>
+where S: SqlWriter + ?Sized {
+    if output.into.is_some() {
+        return unsupported("RETURNING cannot write its rows INTO a table");
+    }
+    let table = match statement {
+        Statement::Insert { .. } | Statement::Update { .. } => "inserted",
+        Statement::Delete { .. } => "deleted",
+        _ => return unsupported("only INSERT, UPDATE and DELETE have an OUTPUT clause"),
+    };
+    let items = output.items.iter()
+        .map(|item| returning_item(item, table))
+        .collect::<Res<Vec<_>>>()?;
+    w.write_statement(statement)?;
+    w.write_new_line()?;
+    w.write_prefix()?;
+    write_clause(w, "RETURNING ")?;
+    write_select_items(w, &items)
+}
+
+fn returning_item(item: &SelectItem, table: &str) -> Res<SelectItem> {
+    match item {
+        SelectItem::UnnamedExpr(expr) => Ok(SelectItem::UnnamedExpr(returning_column(expr, table)?)),
+        SelectItem::ExprWithAlias { expr, alias } => Ok(SelectItem::ExprWithAlias {
+            expr: returning_column(expr, table)?,
+            alias: alias.clone(),
+        }),
+        SelectItem::QualifiedWildcard(name) if is_pseudo_table(&name.0, table) => Ok(SelectItem::Wildcard),
+        _ => unsupported(format!("RETURNING can only return the columns of {}", table)),
+    }
+}
+
+fn returning_column(expr: &Expr, table: &str) -> Res<Expr> {
+    match expr {
+        Expr::CompoundIdentifier(parts) if parts.len() == 2 && is_pseudo_table(&parts[..1], table) => {
+            Ok(Expr::Identifier(parts[1].clone()))
+        },
+        _ => unsupported(format!("RETURNING can only return the columns of {}", table)),
+    }
+}
+
+fn is_pseudo_table(name: &[Ident], table: &str) -> bool {
+    name.len() == 1 && name[0].eq_ignore_ascii_case(table)
+}
+
+fn write_where<S>(w: &mut S, selection: &Option<Expr>) -> Res<()>
+where S: SqlWriter + ?Sized {
+    if let Some(selection) = selection {
+        w.write_new_line()?;
+        w.write_prefix()?;
+        write_clause(w, "WHERE ")?;
+        write_condition(w, selection)?;
+    }
+    Ok(())
+}
+
+fn write_select_items<S>(w: &mut S, items: &[SelectItem]) -> Res<()>
+where S: SqlWriter + ?Sized {
+    let mut after_first = false;
+    for item in items {
+        if after_first {
+            w.write(", ")?;
+        }
+        w.write_select_item(item)?;
+        after_first = true;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{MsSqlWriter, PostgresWriter};
+
+    #[test]
+    fn output_and_returning() {
+        let statement = Statement::Delete {
+            table_name: ObjectName(vec!["people".to_string()]),
+            selection: Some(Expr::Identifier("active".to_string())),
+        };
+        let output = OutputClause {
+            items: vec![SelectItem::UnnamedExpr(Expr::CompoundIdentifier(vec!["deleted".to_string(), "id".to_string()]))],
+            into: Some(OutputInto {
+                target: ObjectName(vec!["@ids".to_string()]),
+                columns: vec![],
+            }),
+        };
+        let mut w = MsSqlWriter::new("    ", Vec::new());
+        w.write_dml_output(&statement, &output).unwrap();
+        assert_eq!(String::from_utf8(w.into_inner()).unwrap(), "DELETE FROM people
+OUTPUT deleted.id INTO @ids
+WHERE active");
+        let mut w = PostgresWriter::new("    ", Vec::new());
+        assert!(w.write_dml_output(&statement, &output).is_err());
+        let output = OutputClause { into: None, ..output };
+        w.write_dml_output(&statement, &output).unwrap();
+        assert_eq!(String::from_utf8(w.into_inner()).unwrap(), "DELETE FROM people
+WHERE active
+RETURNING id");
+    }
+}
@@ -0,0 +1,21 @@
+use crate::Res;
+use std::fmt;
+
+/// Returned when the AST uses something the target dialect
+/// has no way to express, as opposed to an I/O error of the
+/// underlying writer. Find it with `downcast_ref` on the
+/// boxed error a writer returns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unsupported(pub String);
+
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Unsupported {}
+
+pub(crate) fn unsupported<T>(message: impl Into<String>) -> Res<T> {
+    Err(Box::new(Unsupported(message.into())))
+}
@@ -0,0 +1,544 @@
+//! T-SQL's `FOR XML` and `FOR JSON`, which shape the rows of a
+//! query into a single document and which sqlparser 0.5 cannot
+//! read. Before parsing the clause becomes a marker call that is
+//! the last column of its `SELECT`, `FOR JSON('PATH', NULL, 'ROOT', 'items')`,
+//! and `ForClause::of_query` reads it back. Only T-SQL writes it.
+//!
+//! The common use of `FOR XML PATH('')`, concatenating a column
+//! with `STUFF` removing the leading separator, is `STRING_AGG`
+//! in SQL Server 2017 and later and in PostgreSQL, `string_agg`
+//! rewrites it for a `FunctionMap`.
+use crate::{
+    error::unsupported,
+    rewrite::Walk,
+    tsql::{is_word, TsqlStatement},
+    write_clause, Res, SqlWriter,
+};
+use sqlparser::{
+    ast::*,
+    parser::ParserError,
+    tokenizer::{Token, Whitespace, Word},
+};
+use std::cell::RefCell;
+
+/// `FOR XML mode [, directive ...]` or `FOR JSON mode [, directive ...]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForClause {
+    pub format: ForFormat,
+    /// The mode, e.g. `PATH('')`, followed by the other
+    /// directives, e.g. `ROOT('items')` or `TYPE`
+    pub directives: Vec<ForDirective>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForFormat {
+    Xml,
+    Json,
+}
+
+impl ForFormat {
+    fn marker(self) -> &'static str {
+        match self {
+            ForFormat::Xml => "FOR XML",
+            ForFormat::Json => "FOR JSON",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForDirective {
+    /// The upper case name, two words for
+    /// `ELEMENTS XSINIL` or `BINARY BASE64`
+    pub name: String,
+    pub argument: Option<Expr>,
+}
+
+/// The words that can follow a comma in the clause
+const DIRECTIVES: &[&str] = &[
+    "ROOT",
+    "TYPE",
+    "ELEMENTS",
+    "BINARY",
+    "XMLDATA",
+    "XMLSCHEMA",
+    "INCLUDE_NULL_VALUES",
+    "WITHOUT_ARRAY_WRAPPER",
+];
+
+impl ForClause {
+    /// The clause of a query from `parse_tsql`
+    pub fn of_query(query: &Query) -> Option<Self> {
+        match &query.body {
+            SetExpr::Select(select) => select.projection.last().and_then(Self::from_item),
+            _ => None,
+        }
+    }
+
+    fn from_item(item: &SelectItem) -> Option<Self> {
+        let f = match item {
+            SelectItem::UnnamedExpr(Expr::Function(f)) if f.name.0.len() == 1 => f,
+            _ => return None,
+        };
+        let format = match f.name.0[0].as_str() {
+            "FOR XML" => ForFormat::Xml,
+            "FOR JSON" => ForFormat::Json,
+            _ => return None,
+        };
+        let directives = f.args
+            .chunks(2)
+            .map(|pair| match pair {
+                [Expr::Value(Value::SingleQuotedString(name)), argument] => Some(ForDirective {
+                    name: name.clone(),
+                    argument: match argument {
+                        Expr::Value(Value::Null) => None,
+                        argument => Some(argument.clone()),
+                    },
+                }),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self { format, directives })
+    }
+
+    /// The column `parse_tsql` spells this clause with
+    pub fn to_select_item(&self) -> SelectItem {
+        let args = self.directives.iter()
+            .flat_map(|directive| vec![
+                Expr::Value(Value::SingleQuotedString(directive.name.clone())),
+                directive.argument.clone().unwrap_or(Expr::Value(Value::Null)),
+            ])
+            .collect();
+        SelectItem::UnnamedExpr(Expr::Function(Function {
+            name: ObjectName(vec![self.format.marker().to_string()]),
+            args,
+            over: None,
+            distinct: false,
+        }))
+    }
+}
+
+/// The query without its clause, and the clause
+pub(crate) fn split(query: &Query) -> Option<(Query, ForClause)> {
+    let clause = ForClause::of_query(query)?;
+    let mut query = query.clone();
+    if let SetExpr::Select(select) = &mut query.body {
+        select.projection.pop();
+    }
+    Some((query, clause))
+}
+
+/// Replace each `FOR XML ...` or `FOR JSON ...` with the marker
+/// column at the end of the projection of its `SELECT`
+pub(crate) fn mark_for(tokens: Vec<Token>) -> Vec<Token> {
+    let mut inserts: Vec<(usize, Vec<Token>)> = Vec::new();
+    let mut removed = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        match for_clause(&tokens, i) {
+            Some((marker, end)) => {
+                if let Some(at) = owner(&tokens, i).map(|select| insert_at(&tokens, select, i)) {
+                    inserts.push((at, marker));
+                    removed.push(i..end);
+                }
+                i = end;
+            },
+            None => i += 1,
+        }
+    }
+    let mut marked = Vec::with_capacity(tokens.len());
+    for (i, token) in tokens.into_iter().enumerate() {
+        for (_, marker) in inserts.iter().filter(|(at, _)| *at == i) {
+            marked.extend(marker.iter().cloned());
+        }
+        if !removed.iter().any(|range| range.contains(&i)) {
+            marked.push(token);
+        }
+    }
+    marked
+}
+
+/// The marker for the clause at `start` and the index after it
+fn for_clause(tokens: &[Token], start: usize) -> Option<(Vec<Token>, usize)> {
+    if !is_word(tokens.get(start).cloned(), "FOR") {
+        return None;
+    }
+    let format_at = skip_whitespace(tokens, start + 1);
+    let format = if is_word(tokens.get(format_at).cloned(), "XML") {
+        ForFormat::Xml
+    } else if is_word(tokens.get(format_at).cloned(), "JSON") {
+        ForFormat::Json
+    } else {
+        return None;
+    };
+    let mut marker = vec![
+        Token::Comma,
+        Token::Whitespace(Whitespace::Space),
+        word(format.marker(), ""),
+        Token::LParen,
+    ];
+    let mut i = skip_whitespace(tokens, format_at + 1);
+    let mut first = true;
+    loop {
+        let mut name = match tokens.get(i) {
+            Some(Token::Word(w)) if w.quote_style.is_none() => w.value.to_uppercase(),
+            _ => return None,
+        };
+        i = skip_whitespace(tokens, i + 1);
+        let second = match name.as_str() {
+            "ELEMENTS" => &["XSINIL", "ABSENT"][..],
+            "BINARY" => &["BASE64"][..],
+            _ => &[][..],
+        };
+        if let Some(second) = second.iter().find(|s| is_word(tokens.get(i).cloned(), s)) {
+            name = format!("{} {}", name, second);
+            i = skip_whitespace(tokens, i + 1);
+        }
+        if !first {
+            marker.push(Token::Comma);
+        }
+        first = false;
+        marker.push(Token::SingleQuotedString(name));
+        marker.push(Token::Comma);
+        let mut end = i;
+        if tokens.get(i) == Some(&Token::LParen) {
+            let close = closing_paren(tokens, i)?;
+            marker.extend_from_slice(&tokens[i + 1..close]);
+            end = close + 1;
+            i = skip_whitespace(tokens, end);
+        } else {
+            marker.push(word("NULL", "NULL"));
+        }
+        let next = skip_whitespace(tokens, i + 1);
+        let more = tokens.get(i) == Some(&Token::Comma)
+            && DIRECTIVES.iter().any(|d| is_word(tokens.get(next).cloned(), d));
+        if !more {
+            marker.push(Token::RParen);
+            marker.push(Token::Whitespace(Whitespace::Space));
+            return Some((marker, end));
+        }
+        i = next;
+    }
+}
+
+/// The `SELECT` of the query a clause at `at` ends
+fn owner(tokens: &[Token], at: usize) -> Option<usize> {
+    let mut depth = 0;
+    for i in (0..at).rev() {
+        match &tokens[i] {
+            Token::RParen => depth += 1,
+            Token::LParen if depth == 0 => return None,
+            Token::LParen => depth -= 1,
+            token if depth == 0 && is_word(Some(token.clone()), "SELECT") => return Some(i),
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Where the marker goes, before the `FROM` of the `SELECT`
+/// or before the clause itself when there is none
+fn insert_at(tokens: &[Token], select: usize, clause: usize) -> usize {
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().take(clause).skip(select + 1) {
+        match token {
+            Token::LParen => depth += 1,
+            Token::RParen => depth -= 1,
+            token if depth == 0 && is_word(Some(token.clone()), "FROM") => return i,
+            _ => (),
+        }
+    }
+    clause
+}
+
+fn closing_paren(tokens: &[Token], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().skip(open) {
+        match token {
+            Token::LParen => depth += 1,
+            Token::RParen if depth == 1 => return Some(i),
+            Token::RParen => depth -= 1,
+            _ => (),
+        }
+    }
+    None
+}
+
+fn skip_whitespace(tokens: &[Token], mut i: usize) -> usize {
+    while let Some(Token::Whitespace(_)) = tokens.get(i) {
+        i += 1;
+    }
+    i
+}
+
+fn word(value: &str, keyword: &str) -> Token {
+    Token::Word(Word {
+        value: value.to_string(),
+        quote_style: None,
+        keyword: keyword.to_string(),
+    })
+}
+
+/// Check that every clause `mark_for` left ends a query
+/// rather than one side of a set operation
+pub(crate) fn check_for_clauses(stmts: &mut [TsqlStatement]) -> Result<(), ParserError> {
+    let pass = ForCheck { error: RefCell::new(None) };
+    for stmt in stmts {
+        pass.tsql_statement(stmt);
+    }
+    match pass.error.into_inner() {
+        Some(error) => Err(ParserError::ParserError(error)),
+        None => Ok(()),
+    }
+}
+
+struct ForCheck {
+    error: RefCell<Option<String>>,
+}
+
+impl Walk for ForCheck {
+    fn enter_query(&self, q: &mut Query) {
+        if let SetExpr::SetOperation { left, right, .. } = &q.body {
+            if ends_with_clause(left) || ends_with_clause(right) {
+                self.error.borrow_mut().get_or_insert_with(|| {
+                    "FOR XML and FOR JSON are only supported on a SELECT that is not part of a UNION, EXCEPT or INTERSECT"
+                        .to_string()
+                });
+            }
+        }
+    }
+}
+
+fn ends_with_clause(body: &SetExpr) -> bool {
+    match body {
+        SetExpr::Select(select) => select.projection.last().and_then(ForClause::from_item).is_some(),
+        SetExpr::SetOperation { left, right, .. } => ends_with_clause(left) || ends_with_clause(right),
+        _ => false,
+    }
+}
+
+/// T-SQL, the clause on a line of its own
+pub(crate) fn write_for_clause<S>(w: &mut S, node: &ForClause) -> Res<()>
+where S: SqlWriter + ?Sized {
+    write_clause(w, node.format.marker())?;
+    let mut after_first = false;
+    for directive in &node.directives {
+        w.write(if after_first { ", " } else { " " })?;
+        w.write_keyword(&directive.name)?;
+        if let Some(argument) = &directive.argument {
+            w.write("(")?;
+            w.write_expr(argument)?;
+            w.write(")")?;
+        }
+        after_first = true;
+    }
+    Ok(())
+}
+
+pub(crate) fn unsupported_for_clause<T>() -> Res<T> {
+    unsupported("FOR XML and FOR JSON are only written for T-SQL")
+}
+
+/// `STUFF((SELECT sep + expr FROM ... FOR XML PATH('')), 1, n, '')` as
+/// `(SELECT STRING_AGG(expr, sep) FROM ...)`, any other call of `STUFF`
+/// is kept. The `ORDER BY` of the subquery becomes the order of the
+/// rows `STRING_AGG` concatenates, see `aggregate_order`
+pub(crate) fn string_agg(f: &Function) -> Res<Expr> {
+    Ok(stuffed_query(f).unwrap_or_else(|| Expr::Function(f.clone())))
+}
+
+/// The name of the marker call that is the last argument of an
+/// aggregate sorting its rows, sqlparser 0.5 has no place for that
+/// `ORDER BY`: `STRING_AGG(name, ',', "ORDER BY"() OVER (ORDER BY name))`
+const ORDER_MARKER: &str = "ORDER BY";
+
+/// The arguments of an aggregate before its `ORDER BY` marker,
+/// and the window that holds the order
+pub(crate) fn aggregate_order(f: &Function) -> Option<(&[Expr], &WindowSpec)> {
+    match f.args.split_last() {
+        Some((Expr::Function(Function { name, over: Some(order), .. }), args))
+            if name.0.len() == 1 && name.0[0] == ORDER_MARKER =>
+        {
+            Some((args, order))
+        },
+        _ => None,
+    }
+}
+
+fn stuffed_query(f: &Function) -> Option<Expr> {
+    let (query, start, length, replacement) = match f.args.as_slice() {
+        [Expr::Subquery(query), start, length, replacement] => (query, start, length, replacement),
+        _ => return None,
+    };
+    let (mut query, clause) = split(query)?;
+    let path = ForClause {
+        format: ForFormat::Xml,
+        directives: vec![ForDirective {
+            name: "PATH".to_string(),
+            argument: Some(Expr::Value(Value::SingleQuotedString(String::new()))),
+        }],
+    };
+    if clause != path || query.limit.is_some() || query.fetch.is_some() {
+        return None;
+    }
+    let order_by = std::mem::take(&mut query.order_by);
+    let select = match &mut query.body {
+        SetExpr::Select(select) if select.projection.len() == 1 => select,
+        _ => return None,
+    };
+    let (mut parts, with_concat) = match &select.projection[0] {
+        SelectItem::UnnamedExpr(expr) => concatenated(expr),
+        _ => return None,
+    };
+    let separator = match parts.first() {
+        Some(Expr::Value(Value::SingleQuotedString(s))) | Some(Expr::Value(Value::NationalStringLiteral(s))) => s.clone(),
+        _ => return None,
+    };
+    let number = |n: usize| Expr::Value(Value::Number(n.to_string()));
+    let empty = Expr::Value(Value::SingleQuotedString(String::new()));
+    if parts.len() < 2 || *start != number(1) || *length != number(separator.chars().count()) || *replacement != empty {
+        return None;
+    }
+    let separator = parts.remove(0);
+    let value = match parts.len() {
+        1 => parts.remove(0),
+        _ if with_concat => Expr::Function(Function {
+            name: ObjectName(vec!["CONCAT".to_string()]),
+            args: parts,
+            over: None,
+            distinct: false,
+        }),
+        _ => parts.into_iter()
+            .reduce(|left, right| Expr::BinaryOp {
+                left: Box::new(left),
+                op: BinaryOperator::Plus,
+                right: Box::new(right),
+            })?,
+    };
+    let mut args = vec![value, separator];
+    if !order_by.is_empty() {
+        args.push(Expr::Function(Function {
+            name: ObjectName(vec![ORDER_MARKER.to_string()]),
+            args: vec![],
+            over: Some(WindowSpec {
+                partition_by: vec![],
+                order_by,
+                window_frame: None,
+            }),
+            distinct: false,
+        }));
+    }
+    select.projection = vec![SelectItem::UnnamedExpr(Expr::Function(Function {
+        name: ObjectName(vec!["STRING_AGG".to_string()]),
+        args,
+        over: None,
+        distinct: false,
+    }))];
+    Some(Expr::Subquery(Box::new(query)))
+}
+
+/// The parts of a `+` chain or of a `CONCAT`, which is how
+/// the chain reaches a dialect without string `+`, and
+/// whether it was a `CONCAT`
+fn concatenated(expr: &Expr) -> (Vec<Expr>, bool) {
+    match expr {
+        Expr::BinaryOp { left, op: BinaryOperator::Plus, right } => {
+            let (mut parts, _) = concatenated(left);
+            parts.push((**right).clone());
+            (parts, false)
+        },
+        Expr::Function(f) if f.name.0.len() == 1 && f.name.0[0].eq_ignore_ascii_case("CONCAT") => (f.args.clone(), true),
+        _ => (vec![expr.clone()], false),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{MsSqlWriter, PostgresWriter};
+
+    fn query(projection: Vec<SelectItem>) -> Query {
+        Query {
+            ctes: vec![],
+            body: SetExpr::Select(Box::new(Select {
+                distinct: false,
+                projection,
+                from: vec![TableWithJoins {
+                    relation: TableFactor::Table {
+                        name: ObjectName(vec!["people".to_string()]),
+                        alias: None,
+                        args: vec![],
+                        with_hints: vec![],
+                    },
+                    joins: vec![],
+                }],
+                selection: None,
+                group_by: vec![],
+                having: None,
+            })),
+            order_by: vec![],
+            limit: None,
+            offset: None,
+            fetch: None,
+        }
+    }
+
+    fn string(s: &str) -> Expr {
+        Expr::Value(Value::SingleQuotedString(s.to_string()))
+    }
+
+    #[test]
+    fn clause_as_last_column() {
+        let clause = ForClause {
+            format: ForFormat::Json,
+            directives: vec![
+                ForDirective { name: "PATH".to_string(), argument: None },
+                ForDirective { name: "ROOT".to_string(), argument: Some(string("items")) },
+            ],
+        };
+        let name = SelectItem::UnnamedExpr(Expr::Identifier("name".to_string()));
+        let query = query(vec![name, clause.to_select_item()]);
+        assert_eq!(ForClause::of_query(&query), Some(clause));
+        let mut w = MsSqlWriter::new("    ", Vec::new());
+        w.write_query(&query).unwrap();
+        assert_eq!(String::from_utf8(w.into_inner()).unwrap(), "SELECT name
+FROM people
+FOR JSON PATH, ROOT('items')");
+        let mut w = PostgresWriter::new("    ", Vec::new());
+        assert!(w.write_query(&query).is_err());
+    }
+
+    #[test]
+    fn stuff_to_string_agg() {
+        let path = ForClause {
+            format: ForFormat::Xml,
+            directives: vec![ForDirective { name: "PATH".to_string(), argument: Some(string("")) }],
+        };
+        let concat = Expr::BinaryOp {
+            left: Box::new(string(", ")),
+            op: BinaryOperator::Plus,
+            right: Box::new(Expr::Identifier("name".to_string())),
+        };
+        let number = |n: &str| Expr::Value(Value::Number(n.to_string()));
+        let stuff = Function {
+            name: ObjectName(vec!["STUFF".to_string()]),
+            args: vec![
+                Expr::Subquery(Box::new(query(vec![SelectItem::UnnamedExpr(concat), path.to_select_item()]))),
+                number("1"),
+                number("2"),
+                string(""),
+            ],
+            over: None,
+            distinct: false,
+        };
+        let agg = Expr::Function(Function {
+            name: ObjectName(vec!["STRING_AGG".to_string()]),
+            args: vec![Expr::Identifier("name".to_string()), string(", ")],
+            over: None,
+            distinct: false,
+        });
+        assert_eq!(string_agg(&stuff).unwrap(), Expr::Subquery(Box::new(query(vec![SelectItem::UnnamedExpr(agg)]))));
+
+        let other = Function { args: vec![string("abc"), number("1"), number("2"), string("")], ..stuff };
+        assert_eq!(string_agg(&other).unwrap(), Expr::Function(other));
+    }
+}
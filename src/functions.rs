@@ -0,0 +1,243 @@
+use crate::{error::unsupported, for_clause, Res, Target};
+use std::collections::HashMap;
+use sqlparser::ast::*;
+
+/// A custom translation for a function call, receives the
+/// call as it was parsed and returns the expression to write
+pub type FunctionRewrite = Box<dyn Fn(&Function) -> Res<Expr>>;
+
+enum Mapping {
+    Rename(ObjectName),
+    Rewrite(FunctionRewrite),
+}
+
+/// A registry of function translations consulted by
+/// `SqlWriter::write_function`.
+///
+/// Names are matched case insensitively against the full
+/// (dot separated) name of the call, so UDFs can be registered
+/// with their schema, e.g. `dbo.fn_tax`.
+#[derive(Default)]
+pub struct FunctionMap {
+    entries: HashMap<String, Mapping>,
+}
+
+impl FunctionMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The built-in translations for functions other
+    /// dialects spell differently than `target`
+    pub fn for_target(target: Target) -> Self {
+        let mut map = Self::new();
+        match target {
+            Target::MsSql => {
+                map.rename("NOW", "GETDATE")
+                    .rename("LENGTH", "LEN")
+                    .rename("CHAR_LENGTH", "LEN")
+                    .rename("IFNULL", "ISNULL")
+                    .rename("NVL", "ISNULL")
+                    .rename("CEIL", "CEILING")
+                    .rename("RANDOM", "RAND")
+                    .rename("SUBSTR", "SUBSTRING")
+                    .rename("UUID", "NEWID")
+                    .rename("GEN_RANDOM_UUID", "NEWID")
+                    .rename("LCASE", "LOWER")
+                    .rename("UCASE", "UPPER")
+                    .rewrite("STRPOS", |f| {
+                        let args = expect_args(f, 2)?;
+                        Ok(call("CHARINDEX", vec![args[1].clone(), args[0].clone()]))
+                    });
+            },
+            Target::Postgres => {
+                map.rename("GETDATE", "NOW")
+                    .rename("SYSDATETIME", "NOW")
+                    .rename("LEN", "LENGTH")
+                    .rename("IFNULL", "COALESCE")
+                    .rename("NVL", "COALESCE")
+                    .rename("CEILING", "CEIL")
+                    .rename("RAND", "RANDOM")
+                    .rename("NEWID", "GEN_RANDOM_UUID")
+                    .rename("UUID", "GEN_RANDOM_UUID")
+                    .rename("LCASE", "LOWER")
+                    .rename("UCASE", "UPPER")
+                    .rewrite("ISNULL", isnull)
+                    .rewrite("STUFF", for_clause::string_agg)
+                    .rewrite("CHARINDEX", |f| {
+                        let args = expect_args(f, 2)?;
+                        Ok(call("STRPOS", vec![args[1].clone(), args[0].clone()]))
+                    })
+                    .rewrite("DATEADD", |f| {
+                        let args = expect_args(f, 3)?;
+                        let interval = Expr::Value(Value::Interval {
+                            value: "1".to_string(),
+                            leading_field: date_part(&args[0])?,
+                            leading_precision: None,
+                            last_field: None,
+                            fractional_seconds_precision: None,
+                        });
+                        Ok(Expr::Nested(Box::new(Expr::BinaryOp {
+                            left: Box::new(args[2].clone()),
+                            op: BinaryOperator::Plus,
+                            right: Box::new(Expr::BinaryOp {
+                                left: Box::new(args[1].clone()),
+                                op: BinaryOperator::Multiply,
+                                right: Box::new(interval),
+                            }),
+                        })))
+                    });
+            },
+            Target::MySql => {
+                map.rename("GETDATE", "NOW")
+                    .rename("SYSDATETIME", "NOW")
+                    .rename("LEN", "CHAR_LENGTH")
+                    .rename("NVL", "IFNULL")
+                    .rename("RANDOM", "RAND")
+                    .rename("NEWID", "UUID")
+                    .rename("GEN_RANDOM_UUID", "UUID")
+                    .rename("CHARINDEX", "LOCATE")
+                    .rewrite("ISNULL", |f| {
+                        if f.args.len() == 2 {
+                            return Ok(rename(f, "IFNULL"));
+                        }
+                        Ok(call("ISNULL", f.args.clone()))
+                    })
+                    .rewrite("STRPOS", |f| {
+                        let args = expect_args(f, 2)?;
+                        Ok(call("LOCATE", vec![args[1].clone(), args[0].clone()]))
+                    })
+                    .rewrite("DATEADD", |f| {
+                        let args = expect_args(f, 3)?;
+                        let value = match &args[1] {
+                            Expr::Value(Value::Number(n)) => n.clone(),
+                            _ => return unsupported("DATEADD can only be translated for MySQL with a literal number"),
+                        };
+                        let interval = Expr::Value(Value::Interval {
+                            value,
+                            leading_field: date_part(&args[0])?,
+                            leading_precision: None,
+                            last_field: None,
+                            fractional_seconds_precision: None,
+                        });
+                        Ok(call("DATE_ADD", vec![args[2].clone(), interval]))
+                    });
+            },
+            Target::Snowflake | Target::BigQuery | Target::Oracle => {
+                map.rename("LEN", "LENGTH")
+                    .rename("IFNULL", "COALESCE")
+                    .rewrite("ISNULL", isnull)
+                    .rewrite("GETDATE", |_| Ok(Expr::Identifier("CURRENT_TIMESTAMP".to_string())));
+            },
+        }
+        map
+    }
+
+    /// Write calls to `from` as calls to `to`
+    pub fn rename(&mut self, from: &str, to: &str) -> &mut Self {
+        let to = ObjectName(to.split('.').map(String::from).collect());
+        self.entries.insert(from.to_uppercase(), Mapping::Rename(to));
+        self
+    }
+
+    /// Replace calls to `from` with the expression returned by `f`
+    pub fn rewrite<F>(&mut self, from: &str, f: F) -> &mut Self
+    where F: Fn(&Function) -> Res<Expr> + 'static {
+        self.entries.insert(from.to_uppercase(), Mapping::Rewrite(Box::new(f)));
+        self
+    }
+
+    /// Write the `STUFF` and `FOR XML PATH('')` idiom for joining
+    /// strings as `STRING_AGG`, which SQL Server has from 2017 on
+    pub fn string_agg(&mut self) -> &mut Self {
+        self.rewrite("STUFF", for_clause::string_agg)
+    }
+
+    /// The expression to write in place of this call, `None`
+    /// when the call should be written as is
+    pub fn translate(&self, node: &Function) -> Res<Option<Expr>> {
+        let key = node.name.0.join(".").to_uppercase();
+        match self.entries.get(&key) {
+            Some(Mapping::Rename(name)) => {
+                let mut f = node.clone();
+                f.name = name.clone();
+                Ok(Some(Expr::Function(f)))
+            },
+            Some(Mapping::Rewrite(rewrite)) => rewrite(node).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// `ISNULL(a, b)` is T-SQL's `COALESCE`, `ISNULL(a)` is MySQL's `a IS NULL`
+fn isnull(f: &Function) -> Res<Expr> {
+    if f.args.len() == 1 {
+        return Ok(Expr::Nested(Box::new(Expr::IsNull(Box::new(f.args[0].clone())))));
+    }
+    Ok(rename(f, "COALESCE"))
+}
+
+fn expect_args(f: &Function, count: usize) -> Res<&[Expr]> {
+    if f.args.len() != count {
+        return unsupported(format!("{} expects {} arguments, found {}", f.name, count, f.args.len()));
+    }
+    Ok(&f.args)
+}
+
+/// The DATEADD/DATEDIFF date part as an interval field
+fn date_part(arg: &Expr) -> Res<DateTimeField> {
+    let name = match arg {
+        Expr::Identifier(name) => name.to_lowercase(),
+        _ => return unsupported("expected a date part"),
+    };
+    let field = match name.as_str() {
+        "year" | "yy" | "yyyy" => DateTimeField::Year,
+        "month" | "mm" | "m" => DateTimeField::Month,
+        "day" | "dd" | "d" => DateTimeField::Day,
+        "hour" | "hh" => DateTimeField::Hour,
+        "minute" | "mi" | "n" => DateTimeField::Minute,
+        "second" | "ss" | "s" => DateTimeField::Second,
+        _ => return unsupported(format!("unsupported date part {}", name)),
+    };
+    Ok(field)
+}
+
+fn rename(f: &Function, name: &str) -> Expr {
+    let mut f = f.clone();
+    f.name = ObjectName(vec![name.to_string()]);
+    Expr::Function(f)
+}
+
+fn call(name: &str, args: Vec<Expr>) -> Expr {
+    Expr::Function(Function {
+        name: ObjectName(vec![name.to_string()]),
+        args,
+        over: None,
+        distinct: false,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn function(name: &str, args: Vec<Expr>) -> Function {
+        Function {
+            name: ObjectName(name.split('.').map(String::from).collect()),
+            args,
+            over: None,
+            distinct: false,
+        }
+    }
+
+    #[test]
+    fn built_in_and_custom() {
+        let mut map = FunctionMap::for_target(Target::Postgres);
+        map.rename("dbo.fn_tax", "finance.tax");
+        let now = map.translate(&function("getdate", vec![])).unwrap();
+        assert_eq!(now, Some(Expr::Function(function("NOW", vec![]))));
+        let tax = map.translate(&function("DBO.FN_TAX", vec![Expr::Identifier("total".to_string())])).unwrap();
+        assert_eq!(tax, Some(Expr::Function(function("finance.tax", vec![Expr::Identifier("total".to_string())]))));
+        assert_eq!(map.translate(&function("lower", vec![])).unwrap(), None);
+    }
+}
@@ -0,0 +1,387 @@
+//! The generic spellings behind the default `SqlWriter`
+//! methods, kept as free functions so a dialect writer can
+//! fall back to them from its own override
+use crate::{error::unsupported, for_clause::{self, ForClause}, pivot, start_list_line, write_bare_name, write_clause, write_condition, write_separated_expr, write_wrapped, SqlWriter, Res};
+use sqlparser::ast::*;
+
+pub(crate) fn write_window_frame<S>(w: &mut S, node: &WindowFrame) -> Res<()>
+where S: SqlWriter + ?Sized {
+    w.write_window_frame_units(&node.units)?;
+    w.write(" ")?;
+    if let Some(end) = &node.end_bound {
+        w.write_keyword("BETWEEN ")?;
+        w.write_window_frame_bound(&node.start_bound)?;
+        w.write_keyword(" AND ")?;
+        w.write_window_frame_bound(end)
+    } else {
+        w.write_window_frame_bound(&node.start_bound)
+    }
+}
+
+pub(crate) fn write_function<S>(w: &mut S, node: &Function) -> Res<()>
+where S: SqlWriter + ?Sized {
+    if let Some((args, order)) = for_clause::aggregate_order(node) {
+        return w.write_ordered_aggregate(node, args, order);
+    }
+    write_bare_name(w, &node.name)?;
+    let open = if node.distinct { "(DISTINCT " } else { "(" };
+    write_wrapped(w, open, &node.args, ")", |w, arg| w.write_expr(arg))?;
+    if let Some(over) = &node.over {
+        w.write_keyword(" OVER (")?;
+        w.write_window_spec(over)?;
+        w.write(")")?;
+    }
+    Ok(())
+}
+
+/// `STRING_AGG(expr, sep ORDER BY ...)` as in PostgreSQL and BigQuery
+pub(crate) fn write_ordered_aggregate<S>(w: &mut S, node: &Function, args: &[Expr], order: &WindowSpec) -> Res<()>
+where S: SqlWriter + ?Sized {
+    write_bare_name(w, &node.name)?;
+    w.write_keyword(if node.distinct { "(DISTINCT " } else { "(" })?;
+    write_separated_expr(w, ", ", args)?;
+    w.write(" ")?;
+    w.write_window_spec(order)?;
+    w.write(")")
+}
+
+pub(crate) fn write_query<S>(w: &mut S, node: &Query) -> Res<()>
+where S: SqlWriter + ?Sized {
+    if ForClause::of_query(node).is_some() {
+        return for_clause::unsupported_for_clause();
+    }
+    write_ctes(w, &node.ctes)?;
+    w.write_set_expr(&node.body)?;
+    write_order_by(w, &node.order_by)?;
+    w.write_limit(node)
+}
+
+pub(crate) fn write_ctes<S>(w: &mut S, ctes: &[Cte]) -> Res<()>
+where S: SqlWriter + ?Sized {
+    if ctes.is_empty() {
+        return Ok(());
+    }
+    write_clause(w, "WITH ")?;
+    let mut after_first = false;
+    for cte in ctes {
+        if after_first {
+            start_list_line(w, true)?;
+        }
+        w.write_cte(cte)?;
+        after_first = true;
+    }
+    w.write_new_line()?;
+    w.write_prefix()
+}
+
+pub(crate) fn write_order_by<S>(w: &mut S, order_by: &[OrderByExpr]) -> Res<()>
+where S: SqlWriter + ?Sized {
+    if order_by.is_empty() {
+        return Ok(());
+    }
+    w.write_new_line()?;
+    w.write_prefix()?;
+    write_clause(w, "ORDER BY ")?;
+    let mut after_first = false;
+    for expr in order_by {
+        if after_first {
+            w.write(", ")?;
+        }
+        w.write_order_by_expr(expr)?;
+        after_first = true;
+    }
+    Ok(())
+}
+
+pub(crate) fn write_select<S>(w: &mut S, node: &Select) -> Res<()>
+where S: SqlWriter + ?Sized {
+    let open = if node.distinct { "SELECT DISTINCT " } else { "SELECT " };
+    write_wrapped(w, open, &node.projection, "", |w, item| w.write_select_item(item))?;
+    write_select_clauses(w, node)
+}
+
+/// T-SQL, a `SELECT` limited by `TOP (n) [PERCENT] [WITH TIES]`
+pub(crate) fn write_select_top<S>(w: &mut S, node: &Select, top: &Fetch) -> Res<()>
+where S: SqlWriter + ?Sized {
+    w.write_keyword(if node.distinct { "SELECT DISTINCT TOP (" } else { "SELECT TOP (" })?;
+    match &top.quantity {
+        Some(quantity) => w.write_expr(quantity)?,
+        None => w.write("1")?,
+    }
+    w.write(")")?;
+    if top.percent {
+        w.write_keyword(" PERCENT")?;
+    }
+    if top.with_ties {
+        w.write_keyword(" WITH TIES")?;
+    }
+    write_wrapped(w, " ", &node.projection, "", |w, item| w.write_select_item(item))?;
+    write_select_clauses(w, node)
+}
+
+/// The `FROM` clause on a line of its own, if there are tables
+pub(crate) fn write_from<S>(w: &mut S, from: &[TableWithJoins]) -> Res<()>
+where S: SqlWriter + ?Sized {
+    if from.is_empty() {
+        return Ok(());
+    }
+    w.write_new_line()?;
+    w.write_prefix()?;
+    write_clause(w, "FROM ")?;
+    let mut after_first = false;
+    for table in from {
+        if after_first {
+            w.write(", ")?;
+        }
+        w.write_table_with_joins(table)?;
+        after_first = true;
+    }
+    Ok(())
+}
+
+/// Everything in a `SELECT` after its projection
+fn write_select_clauses<S>(w: &mut S, node: &Select) -> Res<()>
+where S: SqlWriter + ?Sized {
+    write_from(w, &node.from)?;
+    if let Some(wh) = &node.selection {
+        w.write_new_line()?;
+        w.write_prefix()?;
+        write_clause(w, "WHERE ")?;
+        write_condition(w, wh)?;
+    }
+    if !node.group_by.is_empty() {
+        w.write_new_line()?;
+        w.write_prefix()?;
+        write_clause(w, "GROUP BY ")?;
+        write_separated_expr(w, ", ", &node.group_by)?;
+    }
+    if let Some(having) = &node.having {
+        w.write_new_line()?;
+        w.write_prefix()?;
+        write_clause(w, "HAVING ")?;
+        write_condition(w, having)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn write_join<S>(w: &mut S, node: &Join) -> Res<()>
+where S: SqlWriter + ?Sized {
+    if pivot::is_pivot(node) {
+        return pivot::unsupported_pivot();
+    }
+    w.write_join_operator(&node.join_operator)?;
+    w.write_table_factor(&node.relation)?;
+    let constraint = match &node.join_operator {
+        JoinOperator::Inner(con)
+        | JoinOperator::LeftOuter(con)
+        | JoinOperator::RightOuter(con)
+        | JoinOperator::FullOuter(con) => con,
+        _ => return Ok(()),
+    };
+    if let JoinConstraint::Natural = constraint {
+        return Ok(());
+    }
+    w.write_new_line()?;
+    w.indent();
+    w.write_prefix()?;
+    w.write_join_constraint(constraint)?;
+    w.dedent();
+    Ok(())
+}
+
+/// `CROSS APPLY` and `OUTER APPLY` as the `LATERAL` joins of
+/// PostgreSQL, MySQL 8 and Snowflake, an outer join still needs
+/// a condition so it joins `ON TRUE`. `lateral_functions` is false
+/// where a function in `FROM` sees the tables before it anyway
+/// (PostgreSQL), which sqlparser can also read back
+pub(crate) fn write_lateral_join<S>(w: &mut S, node: &Join, lateral_functions: bool) -> Res<()>
+where S: SqlWriter + ?Sized {
+    let outer = match node.join_operator {
+        _ if pivot::is_pivot(node) => return pivot::unsupported_pivot(),
+        JoinOperator::CrossApply => false,
+        JoinOperator::OuterApply => true,
+        _ => return write_join(w, node),
+    };
+    w.write_keyword(if outer { "LEFT OUTER JOIN " } else { "CROSS JOIN " })?;
+    match &node.relation {
+        TableFactor::Derived { lateral: false, subquery, alias } => w.write_table_factor(&TableFactor::Derived {
+            lateral: true,
+            subquery: subquery.clone(),
+            alias: alias.clone(),
+        })?,
+        TableFactor::Table { args, .. } if lateral_functions && !args.is_empty() => {
+            w.write_keyword("LATERAL ")?;
+            w.write_table_factor(&node.relation)?;
+        },
+        relation => w.write_table_factor(relation)?,
+    }
+    if outer {
+        w.write_new_line()?;
+        w.indent();
+        w.write_prefix()?;
+        w.write_keyword("ON TRUE")?;
+        w.dedent();
+    }
+    Ok(())
+}
+
+pub(crate) fn write_set_expr<S>(w: &mut S, node: &SetExpr) -> Res<()>
+where S: SqlWriter + ?Sized {
+    match node {
+        SetExpr::Select(s) => w.write_select(s),
+        SetExpr::Query(q) => {
+            w.write("(")?;
+            w.write_query(q)?;
+            w.write(")")
+        },
+        SetExpr::SetOperation {
+            op,
+            all,
+            left,
+            right,
+        } => {
+            w.write_set_expr(left)?;
+            w.write_new_line()?;
+            w.write_prefix()?;
+            w.write_set_operator(op)?;
+            if *all {
+                w.write_keyword(" ALL")?;
+            }
+            w.write_new_line()?;
+            w.write_prefix()?;
+            w.write_set_expr(right)
+        },
+        SetExpr::Values(values) => w.write_values(values),
+    }
+}
+
+pub(crate) fn write_set_operator<S>(w: &mut S, node: &SetOperator) -> Res<()>
+where S: SqlWriter + ?Sized {
+    match node {
+        SetOperator::Union => w.write_keyword("UNION"),
+        SetOperator::Except => w.write_keyword("EXCEPT"),
+        SetOperator::Intersect => w.write_keyword("INTERSECT"),
+    }
+}
+
+pub(crate) fn write_data_type<S>(w: &mut S, node: &DataType) -> Res<()>
+where S: SqlWriter + ?Sized {
+    match node {
+        DataType::Char(size) => write_sized(w, "CHAR", size),
+        DataType::Varchar(size) => write_sized(w, "VARCHAR", size),
+        DataType::Uuid => w.write_keyword("UUID"),
+        DataType::Clob(size) => write_sized(w, "CLOB", &Some(*size)),
+        DataType::Binary(size) => write_sized(w, "BINARY", &Some(*size)),
+        DataType::Varbinary(size) => write_sized(w, "VARBINARY", &Some(*size)),
+        DataType::Blob(size) => write_sized(w, "BLOB", &Some(*size)),
+        DataType::Decimal(precision, scale) => {
+            w.write_keyword("NUMERIC")?;
+            if let Some(precision) = precision {
+                w.write(&format!("({}", precision))?;
+                if let Some(scale) = scale {
+                    w.write(&format!(", {}", scale))?;
+                }
+                w.write(")")?;
+            }
+            Ok(())
+        },
+        DataType::Float(size) => write_sized(w, "FLOAT", size),
+        DataType::SmallInt => w.write_keyword("SMALLINT"),
+        DataType::Int => w.write_keyword("INT"),
+        DataType::BigInt => w.write_keyword("BIGINT"),
+        DataType::Real => w.write_keyword("REAL"),
+        DataType::Double => w.write_keyword("DOUBLE PRECISION"),
+        DataType::Boolean => w.write_keyword("BOOLEAN"),
+        DataType::Date => w.write_keyword("DATE"),
+        DataType::Time => w.write_keyword("TIME"),
+        DataType::Timestamp => w.write_keyword("TIMESTAMP"),
+        DataType::Interval => w.write_keyword("INTERVAL"),
+        DataType::Regclass => w.write_keyword("REGCLASS"),
+        DataType::Text => w.write_keyword("TEXT"),
+        DataType::Bytea => w.write_keyword("BYTEA"),
+        DataType::Custom(name) => write_bare_name(w, name),
+        DataType::Array(inner) => {
+            w.write_data_type(inner)?;
+            w.write("[]")
+        },
+    }
+}
+
+pub(crate) fn write_value<S>(w: &mut S, node: &Value) -> Res<()>
+where S: SqlWriter + ?Sized {
+    let s = match node {
+        Value::Boolean(b) => b.to_string(),
+        Value::HexStringLiteral(ref s) => format!("X'{}'", s),
+        Value::NationalStringLiteral(ref s) => format!("N{}", quote_string(s)),
+        Value::Number(ref s) => s.to_string(),
+        Value::SingleQuotedString(ref s)
+        | Value::Date(ref s)
+        | Value::Time(ref s)
+        | Value::Timestamp(ref s) => quote_string(s),
+        Value::Null => "NULL".to_string(),
+        Value::Interval {
+            ref value,
+            ref leading_field,
+            ref leading_precision,
+            ref last_field,
+            ref fractional_seconds_precision,
+        } => {
+            let mut interval = format!("INTERVAL {} {}", quote_string(value), leading_field);
+            if let Some(ref lp) = leading_precision {
+                interval.push_str(&format!("({}) ", lp))
+            }
+            if let Some(ref lf) = last_field {
+                interval.push_str(&format!("TO {}", lf));
+                if let Some(frac) = fractional_seconds_precision {
+                    interval.push_str(&format!("({})", frac))
+                }
+            }
+
+            interval
+        },
+    };
+    w.write(&s)
+}
+
+/// A string literal, with the quotes inside it doubled
+pub(crate) fn quote_string(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+fn write_sized<S>(w: &mut S, name: &str, size: &Option<u64>) -> Res<()>
+where S: SqlWriter + ?Sized {
+    w.write_keyword(name)?;
+    if let Some(size) = size {
+        w.write(&format!("({})", size))?;
+    }
+    Ok(())
+}
+
+pub(crate) fn write_fetch<S>(w: &mut S, node: &Fetch) -> Res<()>
+where S: SqlWriter + ?Sized {
+    write_clause(w, "FETCH FIRST ")?;
+    if let Some(quantity) = &node.quantity {
+        w.write_expr(quantity)?;
+        if node.percent {
+            w.write_keyword(" PERCENT")?;
+        }
+        w.write(" ")?;
+    }
+    if node.with_ties {
+        w.write_keyword("ROWS WITH TIES")
+    } else {
+        w.write_keyword("ROWS ONLY")
+    }
+}
+
+/// The row count of a `FETCH FIRST` clause for dialects that
+/// only know `LIMIT`, `FETCH FIRST ROWS ONLY` means a single row
+pub(crate) fn fetch_as_limit(dialect: &str, fetch: &Fetch) -> Res<Expr> {
+    if fetch.percent {
+        return unsupported(format!("{} does not support FETCH ... PERCENT", dialect));
+    }
+    if fetch.with_ties {
+        return unsupported(format!("{} does not support FETCH ... WITH TIES", dialect));
+    }
+    Ok(fetch.quantity.clone().unwrap_or_else(|| Expr::Value(Value::Number("1".to_string()))))
+}
@@ -1,50 +1,1518 @@
-use sqlparser::ast::*;
+use sqlparser::{
+    ast::*,
+    dialect::{keywords::ALL_KEYWORDS, Dialect, GenericDialect, MsSqlDialect, MySqlDialect, PostgreSqlDialect},
+};
 
 type Res<T> = Result<T, Box<dyn std::error::Error>>;
+mod bigquery;
+mod batch;
+mod comments;
+mod config;
+mod dml;
+mod error;
+mod for_clause;
+mod functions;
+mod generic;
+mod merge;
 mod mssql;
+mod mysql;
+mod options;
+mod oracle;
+mod output;
+mod pivot;
+mod postgres;
+mod rewrite;
+mod snowflake;
+mod top;
+mod tsql;
+pub use batch::{parse_batches, split_batches, Batch, BatchError, BatchErrors, Go, ParsedBatch};
+pub use bigquery::BigQueryWriter;
+pub use comments::Comments;
+pub use config::{Config, Indent, CONFIG_FILE};
+pub use dml::{OutputClause, OutputInto};
+pub use error::Unsupported;
+pub use for_clause::{ForClause, ForDirective, ForFormat};
+use error::unsupported;
+pub use functions::{FunctionMap, FunctionRewrite};
+pub use merge::{Merge, MergeAction, MergeClause, MergeMatch};
 pub use mssql::MsSqlWriter;
+pub use mysql::MySqlWriter;
+pub use options::{Case, CommaPlacement, FormatOptions, Layout, Quoting};
+pub use pivot::{Pivot, PivotOperator};
+pub use oracle::OracleWriter;
+pub use postgres::PostgresWriter;
+pub use snowflake::SnowflakeWriter;
+pub use tsql::{
+    parse_tsql, Declaration, DeclarationKind, FunctionReturns, Parameter, ThrowError, TriggerEvent, TriggerTiming,
+    TsqlStatement,
+};
 
+/// The dialects `transpile` can write
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Target {
+    MsSql,
+    Snowflake,
+    BigQuery,
+    Oracle,
+    Postgres,
+    MySql,
+}
+
+impl Target {
+    /// The sqlparser dialect that reads SQL written for this target
+    pub fn dialect(self) -> Box<dyn Dialect> {
+        match self {
+            Target::MsSql => Box::new(MsSqlDialect {}),
+            Target::Postgres => Box::new(PostgreSqlDialect {}),
+            Target::BigQuery | Target::MySql => Box::new(BacktickDialect {}),
+            Target::Snowflake | Target::Oracle => Box::new(GenericDialect {}),
+        }
+    }
+}
+
+/// MySQL and BigQuery quote identifiers with backticks, which
+/// the `MySqlDialect` of sqlparser 0.5 does not read yet
+#[derive(Debug)]
+struct BacktickDialect {}
+
+impl Dialect for BacktickDialect {
+    fn is_delimited_identifier_start(&self, ch: char) -> bool {
+        ch == '`' || ch == '"'
+    }
+
+    fn is_identifier_start(&self, ch: char) -> bool {
+        MySqlDialect {}.is_identifier_start(ch)
+    }
+
+    fn is_identifier_part(&self, ch: char) -> bool {
+        MySqlDialect {}.is_identifier_part(ch)
+    }
+}
+
+impl Target {
+    /// The target a sqlparser dialect was made for, if it is one
+    /// of them. sqlparser dialects carry no name of their own so
+    /// they are told apart by their `Debug` output
+    fn of_dialect(dialect: &dyn Dialect) -> Option<Target> {
+        match format!("{:?}", dialect).as_str() {
+            "MsSqlDialect" => Some(Target::MsSql),
+            "PostgreSqlDialect" => Some(Target::Postgres),
+            "MySqlDialect" | "BacktickDialect" => Some(Target::MySql),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for Target {
+    type Err = String;
+
+    /// The lower case names used in `.sqlgenerate.toml`
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "mssql" => Ok(Target::MsSql),
+            "snowflake" => Ok(Target::Snowflake),
+            "bigquery" => Ok(Target::BigQuery),
+            "oracle" => Ok(Target::Oracle),
+            "postgres" => Ok(Target::Postgres),
+            "mysql" => Ok(Target::MySql),
+            _ => Err(format!("unknown dialect {}, expected mssql, snowflake, bigquery, oracle, postgres or mysql", s)),
+        }
+    }
+}
+
+/// Parse `sql` with the `from` dialect and write it back out
+/// for the `to` dialect, rewriting the constructs that the
+/// two spell differently along the way.
+///
+/// Multiple statements are separated by `;` and a new line.
+pub fn transpile(sql: &str, from: &dyn Dialect, to: Target) -> Res<String> {
+    transpile_with_functions(sql, from, to, FunctionMap::for_target(to))
+}
+
+/// `transpile` with a custom set of function translations,
+/// start from `FunctionMap::for_target` to keep the built-ins
+pub fn transpile_with_functions(sql: &str, from: &dyn Dialect, to: Target, functions: FunctionMap) -> Res<String> {
+    Ok(write_transpiled(sql, from, to, functions, FormatOptions::new())?.0)
+}
+
+/// `transpile` with the provided layout, e.g. the
+/// `Config::format_options` of a project
+pub fn transpile_with_options(sql: &str, from: &dyn Dialect, to: Target, options: FormatOptions) -> Res<String> {
+    Ok(transpile_with_warnings(sql, from, to, options)?.0)
+}
+
+/// `transpile_with_options` that also returns the warnings of the
+/// writer, about what will behave differently on the target, such
+/// as Oracle reading an empty string as NULL
+pub fn transpile_with_warnings(sql: &str, from: &dyn Dialect, to: Target, options: FormatOptions) -> Res<(String, Vec<String>)> {
+    write_transpiled(sql, from, to, FunctionMap::for_target(to), options)
+}
+
+/// Parse `sql` written for `dialect` and write it back out for
+/// the same dialect in the layout of `options`. Unlike `transpile`
+/// nothing is rewritten, so the statements keep their meaning.
+pub fn format(sql: &str, dialect: Target, options: FormatOptions) -> Res<String> {
+    let batches = parse_batches(sql, &*dialect.dialect())?;
+    Ok(write_batches(sql, &batches, dialect, None, options, false)?.0)
+}
+
+fn write_transpiled(sql: &str, from: &dyn Dialect, to: Target, functions: FunctionMap, options: FormatOptions) -> Res<(String, Vec<String>)> {
+    let mut batches = parse_batches(sql, from)?;
+    let source = Target::of_dialect(from);
+    for stmt in batches.iter_mut().flat_map(|b| b.statements.iter_mut()) {
+        rewrite::rewrite_statement(stmt, source, to);
+    }
+    write_batches(sql, &batches, to, Some(functions), options, source != Some(to))
+}
+
+/// Write the parsed `batches` of `sql` with the writer of `to`,
+/// returning the text and the warnings of the writer
+fn write_batches(sql: &str, batches: &[ParsedBatch], to: Target, functions: Option<FunctionMap>, options: FormatOptions, translate_types: bool) -> Res<(String, Vec<String>)> {
+    macro_rules! write_with {
+        ($writer:ident) => {{
+            let mut w = $writer::new("    ", Vec::new())
+                .options(options)
+                .translate_types(translate_types)
+                .comments(Comments::extract(sql));
+            if let Some(functions) = functions {
+                w = w.functions(functions);
+            }
+            write_statements(&mut w, batches, to)?;
+            let warnings = w.warnings().to_vec();
+            (w.into_inner(), warnings)
+        }};
+    }
+    let (buf, warnings) = match to {
+        Target::MsSql => write_with!(MsSqlWriter),
+        Target::Snowflake => write_with!(SnowflakeWriter),
+        Target::BigQuery => write_with!(BigQueryWriter),
+        Target::Oracle => write_with!(OracleWriter),
+        Target::Postgres => write_with!(PostgresWriter),
+        Target::MySql => write_with!(MySqlWriter),
+    };
+    Ok((String::from_utf8(buf)?, warnings))
+}
+
+/// Statements are separated by `;`, the `GO` lines between
+/// batches are kept for T-SQL and dropped for everything else.
+/// A T-SQL `MERGE` is written with its own `;`.
+fn write_statements<S>(w: &mut S, batches: &[ParsedBatch], to: Target) -> Res<()>
+where S: SqlWriter {
+    let mut after_first = false;
+    let mut after_go = false;
+    let mut after_merge = false;
+    for batch in batches {
+        for stmt in &batch.statements {
+            if after_first {
+                if !after_go && !after_merge {
+                    w.write(";")?;
+                }
+                w.write_new_line()?;
+                for _ in 0..w.options().blank_lines {
+                    w.write_new_line()?;
+                }
+            }
+            w.write_tsql_statement(stmt)?;
+            after_first = true;
+            after_go = false;
+            after_merge = to == Target::MsSql && matches!(stmt, TsqlStatement::Merge(_));
+        }
+        let go = match batch.go {
+            Some(go) => go,
+            None => continue,
+        };
+        if to != Target::MsSql {
+            if go.count.map(|count| count > 1).unwrap_or(false) {
+                return unsupported("only T-SQL can repeat a batch with GO <count>");
+            }
+            continue;
+        }
+        if after_first {
+            w.write_new_line()?;
+        }
+        w.write_keyword("GO")?;
+        if let Some(count) = go.count {
+            w.write(&format!(" {}", count))?;
+        }
+        after_first = true;
+        after_go = true;
+    }
+    w.flush_comments()
+}
+
+/// A type that can write a sqlparser AST back out as SQL text.
+///
+/// Only the output primitives at the top of this trait are
+/// required, every `write_*` method has a default that produces
+/// generic SQL so a dialect writer only needs to override the
+/// nodes its dialect spells differently.
 pub trait SqlWriter {
-    fn write_assignment(&mut self, node: &Assignment) -> Res<()>;
-    fn write_column_def(&mut self, node: &ColumnDef) -> Res<()>;
-    fn write_column_options_def(&mut self, node: &ColumnOptionDef) -> Res<()>;
-    fn write_cte(&mut self, node: &Cte) -> Res<()>;
-    fn write_fetch(&mut self, node: &Fetch) -> Res<()>;
-    fn write_function(&mut self, node: &Function) -> Res<()>;
-    fn write_join(&mut self, node: &Join) -> Res<()>;
-    fn write_object_name(&mut self, node: &ObjectName) -> Res<()>;
-    fn write_order_by_expr(&mut self, node: &OrderByExpr) -> Res<()>;
-    fn write_query(&mut self, node: &Query) -> Res<()>;
-    fn write_select(&mut self, node: &Select) -> Res<()>;	
-    fn write_sql_option(&mut self, node: &SqlOption) -> Res<()>;
-    fn write_table_alias(&mut self, node: &TableAlias) -> Res<()>;
-    fn write_table_with_joins(&mut self, node: &TableWithJoins) -> Res<()>;
-    fn write_values(&mut self, node: &Values) -> Res<()>;
-    fn write_window_frame(&mut self, node: &WindowFrame) -> Res<()>;
-    fn write_window_spec(&mut self, node: &WindowSpec) -> Res<()>;
-    fn write_alter_table_operation(&mut self, node: &AlterTableOperation) -> Res<()>;
-    fn write_binary_operator(&mut self, node: &BinaryOperator) -> Res<()>;
-    fn write_column_option(&mut self, node: &ColumnOption) -> Res<()>;
-    fn write_data_type(&mut self, node: &DataType) -> Res<()>;
-    fn write_date_time_field(&mut self, node: &DateTimeField) -> Res<()>;
-    fn write_expr(&mut self, node: &Expr) -> Res<()>;
-    fn write_file_format(&mut self, node: &FileFormat) -> Res<()>;
-    fn write_join_constraint(&mut self, node: &JoinConstraint) -> Res<()>;
-    fn write_join_operator(&mut self, node: &JoinOperator) -> Res<()>;
-    fn write_object_type(&mut self, node: &ObjectType) -> Res<()>;
-    fn write_select_item(&mut self, node: &SelectItem) -> Res<()>;
-    fn write_set_expr(&mut self, node: &SetExpr) -> Res<()>;
-    fn write_set_operator(&mut self, node: &SetOperator) -> Res<()>;
-    fn write_set_variable_value(&mut self, node: &SetVariableValue) -> Res<()>;
-    fn write_show_statement_filter(&mut self, node: &ShowStatementFilter) -> Res<()>;
-    fn write_statement(&mut self, node: &Statement) -> Res<()>;
-    fn write_table_constraint(&mut self, node: &TableConstraint) -> Res<()>;
-    fn write_table_factor(&mut self, node: &TableFactor) -> Res<()>;
-    fn write_transaction_access_mode(&mut self, node: &TransactionAccessMode) -> Res<()>;
-    fn write_transaction_isolation_level(&mut self, node: &TransactionIsolationLevel) -> Res<()>;
-    fn write_transaction_mode(&mut self, node: &TransactionMode) -> Res<()>;
-    fn write_unary_operator(&mut self, node: &UnaryOperator) -> Res<()>;
-    fn write_value(&mut self, node: &Value) -> Res<()>;
-    fn write_window_frame_bound(&mut self, node: &WindowFrameBound) -> Res<()>;
-    fn write_window_frame_units(&mut self, node: &WindowFrameUnits) -> Res<()>;
-}
\ No newline at end of file
+    /// Write the provided text to the output as is
+    fn write(&mut self, s: &str) -> Res<()>;
+    /// Start a new line in the output
+    fn write_new_line(&mut self) -> Res<()>;
+    /// Write the indentation for the current level
+    fn write_prefix(&mut self) -> Res<()>;
+    /// Increase the indentation level by one
+    fn indent(&mut self);
+    /// Decrease the indentation level by one
+    fn dedent(&mut self);
+    /// The characters left on the current line before the
+    /// maximum width, `None` when the output is never wrapped
+    fn remaining_width(&self) -> Option<usize> {
+        None
+    }
+    /// Stop producing output, everything written until the
+    /// matching `end_measure` only counts towards its width
+    fn start_measure(&mut self) {}
+    /// The width written since the last `start_measure`,
+    /// `None` if that included a new line
+    fn end_measure(&mut self) -> Option<usize> {
+        None
+    }
+    /// Write any comments of the source that were not
+    /// written next to the words they were attached to
+    fn flush_comments(&mut self) -> Res<()> {
+        Ok(())
+    }
+
+    /// The layout choices to apply while writing
+    fn options(&self) -> &FormatOptions {
+        &options::DEFAULT_OPTIONS
+    }
+    /// The function translations `write_function` should apply
+    fn function_map(&self) -> Option<&FunctionMap> {
+        None
+    }
+    /// Write a keyword in the configured keyword case
+    fn write_keyword(&mut self, s: &str) -> Res<()> {
+        let case = self.options().keyword_case;
+        self.write(&case.apply(s))
+    }
+    /// Write a single identifier, dialects that quote or fold
+    /// identifiers differently should override this
+    fn write_ident(&mut self, id: &str) -> Res<()> {
+        self.write_unquoted_ident(id)
+    }
+    /// Write an identifier that was not quoted in the
+    /// source in the configured identifier case
+    fn write_unquoted_ident(&mut self, id: &str) -> Res<()> {
+        let case = self.options().identifier_case;
+        let quoting = self.options().quoting;
+        let id = case.apply(id);
+        if quoting == Quoting::Always && can_quote(&id) {
+            return self.write_ident(&format!("\"{}\"", id));
+        }
+        self.write(&id)
+    }
+    /// Write a `CAST` expression
+    fn write_cast(&mut self, expr: &Expr, data_type: &DataType) -> Res<()> {
+        self.write_keyword("CAST(")?;
+        self.write_expr(expr)?;
+        self.write_keyword(" AS ")?;
+        self.write_data_type(data_type)?;
+        self.write(")")
+    }
+    /// Write the alias following a table or derived table
+    fn write_table_factor_alias(&mut self, node: &TableAlias) -> Res<()> {
+        if self.options().table_alias_as {
+            self.write_keyword(" AS ")?;
+        } else {
+            self.write(" ")?;
+        }
+        self.write_table_alias(node)
+    }
+    /// Write the LIMIT, OFFSET and FETCH portions of a query
+    fn write_limit(&mut self, node: &Query) -> Res<()> {
+        if let Some(limit) = &node.limit {
+            self.write_new_line()?;
+            self.write_prefix()?;
+            write_clause(self, "LIMIT ")?;
+            self.write_expr(limit)?;
+        }
+        if let Some(offset) = &node.offset {
+            self.write_new_line()?;
+            self.write_prefix()?;
+            write_clause(self, "OFFSET ")?;
+            self.write_expr(offset)?;
+            self.write_keyword(" ROWS")?;
+        }
+        if let Some(fetch) = &node.fetch {
+            self.write_new_line()?;
+            self.write_prefix()?;
+            self.write_fetch(fetch)?;
+        }
+        Ok(())
+    }
+
+    fn write_assignment(&mut self, node: &Assignment) -> Res<()> {
+        self.write_ident(&node.id)?;
+        self.write(" = ")?;
+        self.write_expr(&node.value)
+    }
+    fn write_column_def(&mut self, node: &ColumnDef) -> Res<()> {
+        self.write_ident(&node.name)?;
+        self.write(" ")?;
+        self.write_data_type(&node.data_type)?;
+        if let Some(a) = &node.collation {
+            self.write_keyword(" COLLATE ")?;
+            self.write_object_name(a)?;
+        }
+        for opt in &node.options {
+            self.write(" ")?;
+            self.write_column_options_def(opt)?;
+        }
+        Ok(())
+    }
+    fn write_column_options_def(&mut self, node: &ColumnOptionDef) -> Res<()> {
+        if let Some(name) = &node.name {
+            self.write_keyword("CONSTRAINT ")?;
+            self.write_ident(name)?;
+            self.write(" ")?;
+        }
+        self.write_column_option(&node.option)
+    }
+    fn write_cte(&mut self, node: &Cte) -> Res<()> {
+        self.write_table_alias(&node.alias)?;
+        self.write_keyword(" AS (")?;
+        self.indent();
+        self.write_new_line()?;
+        self.write_prefix()?;
+        self.write_query(&node.query)?;
+        self.dedent();
+        self.write_new_line()?;
+        self.write_prefix()?;
+        self.write(")")
+    }
+    fn write_fetch(&mut self, node: &Fetch) -> Res<()> {
+        generic::write_fetch(self, node)
+    }
+    fn write_function(&mut self, node: &Function) -> Res<()> {
+        let translated = match self.function_map() {
+            Some(map) => map.translate(node)?,
+            None => None,
+        };
+        match translated {
+            Some(Expr::Function(f)) => generic::write_function(self, &f),
+            Some(expr) => self.write_expr(&expr),
+            None => generic::write_function(self, node),
+        }
+    }
+    fn write_join(&mut self, node: &Join) -> Res<()> {
+        generic::write_join(self, node)
+    }
+    fn write_object_name(&mut self, node: &ObjectName) -> Res<()> {
+        write_separated_idents(self, ".", &node.0)
+    }
+    fn write_order_by_expr(&mut self, node: &OrderByExpr) -> Res<()> {
+        self.write_expr(&node.expr)?;
+        match node.asc {
+            Some(true) => self.write_keyword(" ASC"),
+            Some(false) => self.write_keyword(" DESC"),
+            None => Ok(()),
+        }
+    }
+    fn write_ordered_aggregate(&mut self, node: &Function, args: &[Expr], order: &WindowSpec) -> Res<()> {
+        generic::write_ordered_aggregate(self, node, args, order)
+    }
+    fn write_query(&mut self, node: &Query) -> Res<()> {
+        generic::write_query(self, node)
+    }
+    fn write_select(&mut self, node: &Select) -> Res<()> {
+        generic::write_select(self, node)
+    }
+    fn write_sql_option(&mut self, node: &SqlOption) -> Res<()> {
+        self.write_ident(&node.name)?;
+        self.write(" = ")?;
+        self.write_value(&node.value)
+    }
+    fn write_table_alias(&mut self, node: &TableAlias) -> Res<()> {
+        self.write_ident(&node.name)?;
+        if !node.columns.is_empty() {
+            self.write(" (")?;
+            write_separated_idents(self, ", ", &node.columns)?;
+            self.write(")")?;
+        }
+        Ok(())
+    }
+    fn write_table_with_joins(&mut self, node: &TableWithJoins) -> Res<()> {
+        self.write_table_factor(&node.relation)?;
+        for join in &node.joins {
+            self.write_new_line()?;
+            self.write_prefix()?;
+            self.write_join(join)?;
+        }
+        Ok(())
+    }
+    fn write_values(&mut self, node: &Values) -> Res<()> {
+        self.write_keyword("VALUES ")?;
+        let mut after_first = false;
+        for row in &node.0 {
+            if after_first {
+                self.write(", ")?;
+            }
+            self.write("(")?;
+            write_separated_expr(self, ", ", row)?;
+            self.write(")")?;
+            after_first = true;
+        }
+        Ok(())
+    }
+    fn write_window_frame(&mut self, node: &WindowFrame) -> Res<()> {
+        generic::write_window_frame(self, node)
+    }
+    fn write_window_spec(&mut self, node: &WindowSpec) -> Res<()> {
+        let mut after_first = false;
+        if !node.partition_by.is_empty() {
+            self.write_keyword("PARTITION BY ")?;
+            write_separated_expr(self, ", ", &node.partition_by)?;
+            after_first = true;
+        }
+        if !node.order_by.is_empty() {
+            if after_first {
+                self.write(" ")?;
+            }
+            self.write_keyword("ORDER BY ")?;
+            let mut past_first = false;
+            for expr in &node.order_by {
+                if past_first {
+                    self.write(", ")?;
+                }
+                self.write_order_by_expr(expr)?;
+                past_first = true;
+            }
+            after_first = true;
+        }
+        if let Some(frame) = &node.window_frame {
+            if after_first {
+                self.write(" ")?;
+            }
+            self.write_window_frame(frame)?;
+        }
+        Ok(())
+    }
+    fn write_alter_table_operation(&mut self, node: &AlterTableOperation) -> Res<()> {
+        match node {
+            AlterTableOperation::AddConstraint(constraint) => {
+                self.write_keyword("ADD ")?;
+                self.write_table_constraint(constraint)
+            },
+            AlterTableOperation::DropConstraint { name } => {
+                self.write_keyword("DROP CONSTRAINT ")?;
+                self.write_ident(name)
+            },
+        }
+    }
+    fn write_binary_operator(&mut self, node: &BinaryOperator) -> Res<()> {
+        let s = match node {
+            BinaryOperator::And => "AND",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Eq => "=",
+            BinaryOperator::Gt => ">",
+            BinaryOperator::GtEq => ">=",
+            BinaryOperator::Like => "LIKE",
+            BinaryOperator::Lt => "<",
+            BinaryOperator::LtEq => "<=",
+            BinaryOperator::Minus => "-",
+            BinaryOperator::Modulus => "%",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::NotEq => "!=",
+            BinaryOperator::NotLike => "NOT LIKE",
+            BinaryOperator::Or => "OR",
+            BinaryOperator::Plus => "+",
+        };
+        self.write(s)
+    }
+    fn write_column_option(&mut self, node: &ColumnOption) -> Res<()> {
+        match &node {
+            ColumnOption::Null => self.write_keyword("NULL"),
+            ColumnOption::NotNull => self.write_keyword("NOT NULL"),
+            ColumnOption::Default(expr) => {
+                self.write_keyword("DEFAULT ")?;
+                self.write_expr(expr)
+            },
+            ColumnOption::Unique { is_primary } => {
+                if *is_primary {
+                    self.write_keyword("PRIMARY KEY")
+                } else {
+                    self.write_keyword("UNIQUE")
+                }
+            },
+            ColumnOption::ForeignKey {
+                foreign_table,
+                referred_columns,
+            } => {
+                self.write_keyword("REFERENCES ")?;
+                self.write_object_name(foreign_table)?;
+                self.write(" (")?;
+                write_separated_idents(self, ", ", referred_columns)?;
+                self.write(")")
+            }
+            ColumnOption::Check(expr) => {
+                self.write_keyword("CHECK (")?;
+                self.write_expr(expr)?;
+                self.write(")")
+            }
+        }
+    }
+    fn write_data_type(&mut self, node: &DataType) -> Res<()> {
+        generic::write_data_type(self, node)
+    }
+    fn write_date_time_field(&mut self, node: &DateTimeField) -> Res<()> {
+        let s = match node {
+            DateTimeField::Year => "YEAR",
+            DateTimeField::Month => "MONTH",
+            DateTimeField::Day => "DAY",
+            DateTimeField::Hour => "HOUR",
+            DateTimeField::Minute => "MINUTE",
+            DateTimeField::Second => "SECOND",
+        };
+        self.write(s)
+    }
+    fn write_expr(&mut self, node: &Expr) -> Res<()> {
+        match node {
+            Expr::Identifier(ref id) => self.write_ident(id),
+            Expr::Wildcard => self.write("*"),
+            Expr::QualifiedWildcard(ref idents) => {
+                write_separated_idents(self, ".", idents)?;
+                self.write(".*")
+            },
+            Expr::CompoundIdentifier(ref idents) => {
+                write_separated_idents(self, ".", idents)
+            },
+            Expr::IsNull(ref expr) => {
+                self.write_expr(expr)?;
+                self.write_keyword(" IS NULL")
+            },
+            Expr::IsNotNull(ref expr) => {
+                self.write_expr(expr)?;
+                self.write_keyword(" IS NOT NULL")
+            },
+            Expr::InList { expr, list, negated } => {
+                self.write_expr(expr)?;
+                if *negated {
+                    self.write_keyword(" NOT")?;
+                }
+                write_wrapped(self, " IN (", list, ")", |w, e| w.write_expr(e))
+            },
+            Expr::InSubquery { expr, subquery, negated } => {
+                self.write_expr(expr)?;
+                if *negated {
+                    self.write_keyword(" NOT")?;
+                }
+                self.write_keyword(" IN (")?;
+                self.write_query(subquery)?;
+                self.write(")")
+            },
+            Expr::Between {
+                expr,
+                negated,
+                low,
+                high,
+            } => {
+                self.write_expr(expr)?;
+                if *negated {
+                    self.write_keyword(" NOT")?;
+                }
+                self.write_keyword(" BETWEEN ")?;
+                self.write_expr(low)?;
+                self.write_keyword(" AND ")?;
+                self.write_expr(high)
+            },
+            Expr::BinaryOp { op: op @ BinaryOperator::And, .. }
+            | Expr::BinaryOp { op: op @ BinaryOperator::Or, .. } => {
+                write_boolean_chain(self, node, op)
+            },
+            Expr::BinaryOp { left, op, right } => {
+                let spaced = self.options().operator_spacing
+                    || !is_symbolic_operator(op)
+                    || matches!(**right, Expr::UnaryOp { .. });
+                self.write_expr(left)?;
+                if spaced {
+                    self.write(" ")?;
+                }
+                self.write_binary_operator(op)?;
+                if spaced {
+                    self.write(" ")?;
+                }
+                self.write_expr(right)
+            },
+            Expr::UnaryOp { op, expr } => {
+                self.write_unary_operator(op)?;
+                self.write(" ")?;
+                self.write_expr(expr)
+            },
+            Expr::Cast { expr, data_type } => {
+                self.write_cast(expr, data_type)
+            },
+            Expr::Extract { field, expr } => {
+                self.write_keyword("EXTRACT(")?;
+                self.write_date_time_field(field)?;
+                self.write_keyword(" FROM ")?;
+                self.write_expr(expr)?;
+                self.write(")")
+            },
+            Expr::Collate { expr, collation } => {
+                self.write_expr(expr)?;
+                self.write_keyword(" COLLATE ")?;
+                self.write_object_name(collation)
+            },
+            Expr::Nested(expr) => {
+                self.write("(")?;
+                self.write_expr(expr)?;
+                self.write(")")
+            },
+            Expr::Value(ref val) => {
+                if self.options().strip_literals && is_strippable(val) {
+                    return self.write("?");
+                }
+                self.write_value(val)
+            },
+            Expr::Function(ref f) => {
+                self.write_function(f)
+            },
+            Expr::Case {
+                operand,
+                conditions,
+                results,
+                else_result,
+            } => {
+                self.write_keyword("CASE")?;
+                if let Some(operand) = operand {
+                    self.write(" ")?;
+                    self.write_expr(operand)?;
+                }
+                for (condition, result) in conditions.iter().zip(results.iter()) {
+                    self.write_keyword(" WHEN ")?;
+                    self.write_expr(condition)?;
+                    self.write_keyword(" THEN ")?;
+                    self.write_expr(result)?;
+                }
+                if let Some(else_result) = else_result {
+                    self.write_keyword(" ELSE ")?;
+                    self.write_expr(else_result)?;
+                }
+                self.write_keyword(" END")
+            },
+            Expr::Exists(ref query) => {
+                self.write_keyword("EXISTS (")?;
+                self.write_query(query)?;
+                self.write(")")
+            },
+            Expr::Subquery(ref query) => {
+                self.write("(")?;
+                self.write_query(query)?;
+                self.write(")")
+            },
+        }
+    }
+    fn write_file_format(&mut self, node: &FileFormat) -> Res<()> {
+        let s = match node {
+            FileFormat::TEXTFILE => "TEXTFILE",
+            FileFormat::SEQUENCEFILE => "SEQUENCEFILE",
+            FileFormat::ORC => "ORC",
+            FileFormat::PARQUET => "PARQUET",
+            FileFormat::AVRO => "AVRO",
+            FileFormat::RCFILE => "RCFILE",
+            FileFormat::JSONFILE => "JSONFILE",
+        };
+        self.write(s)
+    }
+    fn write_join_constraint(&mut self, node: &JoinConstraint) -> Res<()> {
+        match node {
+            JoinConstraint::On(expr) => {
+                self.write_keyword("ON ")?;
+                self.write_expr(expr)
+            },
+            JoinConstraint::Using(idents) => {
+                self.write_keyword("USING (")?;
+                write_separated_idents(self, ", ", idents)?;
+                self.write(")")
+            },
+            JoinConstraint::Natural => Ok(()),
+        }
+    }
+    fn write_join_operator(&mut self, node: &JoinOperator) -> Res<()> {
+        let constraint = match node {
+            JoinOperator::Inner(con)
+            | JoinOperator::LeftOuter(con)
+            | JoinOperator::RightOuter(con)
+            | JoinOperator::FullOuter(con) => Some(con),
+            _ => None,
+        };
+        if let Some(JoinConstraint::Natural) = constraint {
+            self.write_keyword("NATURAL ")?;
+        }
+        match node {
+            JoinOperator::Inner(_) => self.write_keyword("INNER JOIN "),
+            JoinOperator::LeftOuter(_) => self.write_keyword("LEFT OUTER JOIN "),
+            JoinOperator::FullOuter(_) => self.write_keyword("FULL OUTER JOIN "),
+            JoinOperator::RightOuter(_) => self.write_keyword("RIGHT OUTER JOIN "),
+            JoinOperator::CrossJoin => self.write_keyword("CROSS JOIN "),
+            JoinOperator::CrossApply => self.write_keyword("CROSS APPLY "),
+            JoinOperator::OuterApply => self.write_keyword("OUTER APPLY "),
+        }
+    }
+    fn write_object_type(&mut self, node: &ObjectType) -> Res<()> {
+        match node {
+            ObjectType::Table => self.write_keyword("TABLE"),
+            ObjectType::View => self.write_keyword("VIEW"),
+        }
+    }
+    fn write_select_item(&mut self, node: &SelectItem) -> Res<()> {
+        match node {
+            SelectItem::UnnamedExpr(ref expr) => self.write_expr(expr)?,
+            SelectItem::ExprWithAlias { ref expr, ref alias } => {
+                self.write_expr(expr)?;
+                self.write_keyword(" AS ")?;
+                self.write_ident(alias)?;
+            },
+            SelectItem::QualifiedWildcard(ref name) => {
+                self.write_object_name(name)?;
+                self.write(".*")?;
+            },
+            SelectItem::Wildcard => self.write("*")?,
+        }
+        Ok(())
+    }
+    fn write_set_expr(&mut self, node: &SetExpr) -> Res<()> {
+        generic::write_set_expr(self, node)
+    }
+    fn write_set_operator(&mut self, node: &SetOperator) -> Res<()> {
+        generic::write_set_operator(self, node)
+    }
+    fn write_set_variable_value(&mut self, node: &SetVariableValue) -> Res<()> {
+        match node {
+            SetVariableValue::Ident(id) => self.write_ident(id),
+            SetVariableValue::Literal(val) => self.write_value(val),
+        }
+    }
+    fn write_show_statement_filter(&mut self, node: &ShowStatementFilter) -> Res<()> {
+        match node {
+            ShowStatementFilter::Like(pattern) => {
+                self.write_keyword("LIKE ")?;
+                self.write_value(&Value::SingleQuotedString(pattern.clone()))
+            },
+            ShowStatementFilter::Where(expr) => {
+                self.write_keyword("WHERE ")?;
+                self.write_expr(expr)
+            },
+        }
+    }
+    fn write_statement(&mut self, node: &Statement) -> Res<()> {
+        match node {
+            Statement::Query(q) => self.write_query(q),
+            Statement::Insert {
+                table_name,
+                columns,
+                source,
+            } => {
+                self.write_keyword("INSERT INTO ")?;
+                self.write_object_name(table_name)?;
+                if !columns.is_empty() {
+                    self.write(" (")?;
+                    write_separated_idents(self, ", ", columns)?;
+                    self.write(")")?;
+                }
+                self.write_new_line()?;
+                self.write_prefix()?;
+                self.write_query(source)
+            },
+            Statement::Update {
+                table_name,
+                assignments,
+                selection,
+            } => {
+                self.write_keyword("UPDATE ")?;
+                self.write_object_name(table_name)?;
+                self.write_new_line()?;
+                self.write_prefix()?;
+                write_clause(self, "SET ")?;
+                let mut after_first = false;
+                for assignment in assignments {
+                    if after_first {
+                        self.write(", ")?;
+                    }
+                    self.write_assignment(assignment)?;
+                    after_first = true;
+                }
+                if let Some(wh) = selection {
+                    self.write_new_line()?;
+                    self.write_prefix()?;
+                    write_clause(self, "WHERE ")?;
+                    write_condition(self, wh)?;
+                }
+                Ok(())
+            },
+            Statement::Delete {
+                table_name,
+                selection,
+            } => {
+                self.write_keyword("DELETE FROM ")?;
+                self.write_object_name(table_name)?;
+                if let Some(wh) = selection {
+                    self.write_new_line()?;
+                    self.write_prefix()?;
+                    write_clause(self, "WHERE ")?;
+                    write_condition(self, wh)?;
+                }
+                Ok(())
+            },
+            Statement::CreateView {
+                name,
+                columns,
+                query,
+                materialized,
+                with_options,
+            } => {
+                self.write_keyword("CREATE ")?;
+                if *materialized {
+                    self.write_keyword("MATERIALIZED ")?;
+                }
+                self.write_keyword("VIEW ")?;
+                self.write_object_name(name)?;
+                if !columns.is_empty() {
+                    self.write(" (")?;
+                    write_separated_idents(self, ", ", columns)?;
+                    self.write(")")?;
+                }
+                write_with_options(self, with_options)?;
+                self.write_keyword(" AS")?;
+                self.write_new_line()?;
+                self.write_prefix()?;
+                self.write_query(query)
+            },
+            Statement::CreateTable {
+                name,
+                columns,
+                constraints,
+                with_options,
+                external,
+                file_format,
+                location,
+            } => {
+                self.write_keyword("CREATE ")?;
+                if *external {
+                    self.write_keyword("EXTERNAL ")?;
+                }
+                self.write_keyword("TABLE ")?;
+                self.write_object_name(name)?;
+                write_table_elements(self, columns, constraints)?;
+                if *external {
+                    if let Some(file_format) = file_format {
+                        self.write_keyword(" STORED AS ")?;
+                        self.write_file_format(file_format)?;
+                    }
+                    if let Some(location) = location {
+                        self.write_keyword(" LOCATION ")?;
+                        self.write_value(&Value::SingleQuotedString(location.clone()))?;
+                    }
+                }
+                write_with_options(self, with_options)
+            },
+            Statement::AlterTable { name, operation } => {
+                self.write_keyword("ALTER TABLE ")?;
+                self.write_object_name(name)?;
+                self.write(" ")?;
+                self.write_alter_table_operation(operation)
+            },
+            Statement::Drop {
+                object_type,
+                if_exists,
+                names,
+                cascade,
+            } => {
+                self.write_keyword("DROP ")?;
+                self.write_object_type(object_type)?;
+                if *if_exists {
+                    self.write_keyword(" IF EXISTS")?;
+                }
+                self.write(" ")?;
+                let mut after_first = false;
+                for name in names {
+                    if after_first {
+                        self.write(", ")?;
+                    }
+                    self.write_object_name(name)?;
+                    after_first = true;
+                }
+                if *cascade {
+                    self.write_keyword(" CASCADE")?;
+                }
+                Ok(())
+            },
+            Statement::SetVariable {
+                local,
+                variable,
+                value,
+            } => {
+                self.write_keyword("SET ")?;
+                if *local {
+                    self.write_keyword("LOCAL ")?;
+                }
+                self.write_ident(variable)?;
+                self.write(" = ")?;
+                self.write_set_variable_value(value)
+            },
+            Statement::ShowVariable { variable } => {
+                self.write_keyword("SHOW ")?;
+                self.write_ident(variable)
+            },
+            Statement::ShowColumns {
+                extended,
+                full,
+                table_name,
+                filter,
+            } => {
+                self.write_keyword("SHOW ")?;
+                if *extended {
+                    self.write_keyword("EXTENDED ")?;
+                }
+                if *full {
+                    self.write_keyword("FULL ")?;
+                }
+                self.write_keyword("COLUMNS FROM ")?;
+                self.write_object_name(table_name)?;
+                if let Some(filter) = filter {
+                    self.write(" ")?;
+                    self.write_show_statement_filter(filter)?;
+                }
+                Ok(())
+            },
+            Statement::StartTransaction { modes } => {
+                self.write_keyword("START TRANSACTION")?;
+                write_transaction_modes(self, modes)
+            },
+            Statement::SetTransaction { modes } => {
+                self.write_keyword("SET TRANSACTION")?;
+                write_transaction_modes(self, modes)
+            },
+            Statement::Commit { chain } => {
+                self.write_keyword("COMMIT")?;
+                if *chain {
+                    self.write_keyword(" AND CHAIN")?;
+                }
+                Ok(())
+            },
+            Statement::Rollback { chain } => {
+                self.write_keyword("ROLLBACK")?;
+                if *chain {
+                    self.write_keyword(" AND CHAIN")?;
+                }
+                Ok(())
+            },
+            other => {
+                let debug = format!("{:?}", other);
+                let name = debug.split(|c: char| !c.is_alphanumeric()).next().unwrap_or_default();
+                unsupported(format!("{} is not supported", name.to_uppercase()))
+            },
+        }
+    }
+    /// Write a statement of a T-SQL batch, only T-SQL has the
+    /// procedural statements so other dialects refuse them
+    fn write_tsql_statement(&mut self, node: &TsqlStatement) -> Res<()> {
+        match node {
+            TsqlStatement::Statement(stmt) => self.write_statement(stmt),
+            TsqlStatement::Dml { statement, output } => self.write_dml_output(statement, output),
+            TsqlStatement::Merge(merge) => self.write_merge(merge),
+            TsqlStatement::UpdateFrom { .. } => unsupported("only T-SQL can UPDATE from a join"),
+            TsqlStatement::Declare(_) | TsqlStatement::SetVariable { .. } | TsqlStatement::SelectVariables { .. } => {
+                unsupported("only T-SQL has local variables")
+            },
+            TsqlStatement::CreateProcedure { .. }
+            | TsqlStatement::CreateFunction { .. }
+            | TsqlStatement::CreateTrigger { .. } => {
+                unsupported("procedures, functions and triggers can only be written as T-SQL")
+            },
+            _ => unsupported("only T-SQL has procedural control flow"),
+        }
+    }
+    /// Write an `INSERT`, `UPDATE` or `DELETE` that returns
+    /// the rows it changed
+    fn write_dml_output(&mut self, _statement: &Statement, _output: &OutputClause) -> Res<()> {
+        unsupported("only T-SQL and PostgreSQL can return the rows a statement changes")
+    }
+    /// Write a `MERGE`, dialects without one may be able to
+    /// write the same upsert another way
+    fn write_merge(&mut self, _node: &Merge) -> Res<()> {
+        unsupported("MERGE can only be written for T-SQL, PostgreSQL and MySQL")
+    }
+    fn write_table_constraint(&mut self, node: &TableConstraint) -> Res<()> {
+        let name = match node {
+            TableConstraint::Unique { name, .. }
+            | TableConstraint::ForeignKey { name, .. }
+            | TableConstraint::Check { name, .. } => name,
+        };
+        if let Some(name) = name {
+            self.write_keyword("CONSTRAINT ")?;
+            self.write_ident(name)?;
+            self.write(" ")?;
+        }
+        match node {
+            TableConstraint::Unique { columns, is_primary, .. } => {
+                if *is_primary {
+                    self.write_keyword("PRIMARY KEY (")?;
+                } else {
+                    self.write_keyword("UNIQUE (")?;
+                }
+                write_separated_idents(self, ", ", columns)?;
+                self.write(")")
+            },
+            TableConstraint::ForeignKey {
+                columns,
+                foreign_table,
+                referred_columns,
+                ..
+            } => {
+                self.write_keyword("FOREIGN KEY (")?;
+                write_separated_idents(self, ", ", columns)?;
+                self.write_keyword(") REFERENCES ")?;
+                self.write_object_name(foreign_table)?;
+                self.write(" (")?;
+                write_separated_idents(self, ", ", referred_columns)?;
+                self.write(")")
+            },
+            TableConstraint::Check { expr, .. } => {
+                self.write_keyword("CHECK (")?;
+                self.write_expr(expr)?;
+                self.write(")")
+            },
+        }
+    }
+    fn write_table_factor(&mut self, node: &TableFactor) -> Res<()> {
+        match node {
+            TableFactor::Table {
+                ref alias,
+                ref args,
+                ref name,
+                ref with_hints,
+            } => {
+                if args.is_empty() {
+                    self.write_object_name(name)?;
+                } else {
+                    // a table-valued function, named like any other function
+                    write_bare_name(self, name)?;
+                    self.write("(")?;
+                    write_separated_expr(self, ", ", args)?;
+                    self.write(")")?;
+                }
+                if let Some(ref a) = alias {
+                    self.write_table_factor_alias(a)?;
+                }
+                if !with_hints.is_empty() {
+                    self.write_keyword(" WITH (")?;
+                    write_separated_expr(self, ", ", with_hints)?;
+                    self.write(")")?;
+                }
+                Ok(())
+            },
+            TableFactor::Derived {
+                lateral,
+                subquery,
+                alias,
+            } => {
+                if *lateral {
+                    self.write_keyword("LATERAL ")?;
+                }
+                self.write("(")?;
+                self.indent();
+                self.write_new_line()?;
+                self.write_prefix()?;
+                self.write_query(subquery)?;
+                self.dedent();
+                self.write_new_line()?;
+                self.write_prefix()?;
+                self.write(")")?;
+                if let Some(a) = alias {
+                    self.write_table_factor_alias(a)?;
+                }
+                Ok(())
+            },
+            TableFactor::NestedJoin(table) => {
+                self.write("(")?;
+                self.write_table_with_joins(table)?;
+                self.write(")")
+            },
+        }
+    }
+    fn write_transaction_access_mode(&mut self, node: &TransactionAccessMode) -> Res<()> {
+        match node {
+            TransactionAccessMode::ReadOnly => self.write_keyword("READ ONLY"),
+            TransactionAccessMode::ReadWrite => self.write_keyword("READ WRITE"),
+        }
+    }
+    fn write_transaction_isolation_level(&mut self, node: &TransactionIsolationLevel) -> Res<()> {
+        let s = match node {
+            TransactionIsolationLevel::ReadUncommitted => "READ UNCOMMITTED",
+            TransactionIsolationLevel::ReadCommitted => "READ COMMITTED",
+            TransactionIsolationLevel::RepeatableRead => "REPEATABLE READ",
+            TransactionIsolationLevel::Serializable => "SERIALIZABLE",
+        };
+        self.write(s)
+    }
+    fn write_transaction_mode(&mut self, node: &TransactionMode) -> Res<()> {
+        match node {
+            TransactionMode::AccessMode(mode) => self.write_transaction_access_mode(mode),
+            TransactionMode::IsolationLevel(level) => {
+                self.write_keyword("ISOLATION LEVEL ")?;
+                self.write_transaction_isolation_level(level)
+            },
+        }
+    }
+    fn write_unary_operator(&mut self, node: &UnaryOperator) -> Res<()> {
+        let s = match node {
+            UnaryOperator::Minus => "-",
+            UnaryOperator::Not => "NOT",
+            UnaryOperator::Plus => "+"
+        };
+        self.write(s)
+    }
+    fn write_value(&mut self, node: &Value) -> Res<()> {
+        generic::write_value(self, node)
+    }
+    fn write_window_frame_bound(&mut self, node: &WindowFrameBound) -> Res<()> {
+        match node {
+            WindowFrameBound::CurrentRow => self.write_keyword("CURRENT ROW"),
+            WindowFrameBound::Preceding(None) => self.write_keyword("UNBOUNDED PRECEDING"),
+            WindowFrameBound::Following(None) => self.write_keyword("UNBOUNDED FOLLOWING"),
+            WindowFrameBound::Preceding(Some(n)) => self.write_keyword(&format!("{} PRECEDING", n)),
+            WindowFrameBound::Following(Some(n)) => self.write_keyword(&format!("{} FOLLOWING", n)),
+        }
+    }
+    fn write_window_frame_units(&mut self, node: &WindowFrameUnits) -> Res<()> {
+        match node {
+            WindowFrameUnits::Rows => self.write_keyword("ROWS"),
+            WindowFrameUnits::Range => self.write_keyword("RANGE"),
+            WindowFrameUnits::Groups => self.write_keyword("GROUPS"),
+        }
+    }
+}
+
+pub(crate) fn write_separated_idents<S>(w: &mut S, sep: &str, idents: &[Ident]) -> Res<()>
+where S: SqlWriter + ?Sized {
+    let mut after_first = false;
+    for id in idents {
+        if after_first {
+            w.write(sep)?;
+        }
+        w.write_ident(id)?;
+        after_first = true;
+    }
+    Ok(())
+}
+
+pub(crate) fn write_separated_expr<S>(w: &mut S, sep: &str, exprs: &[Expr]) -> Res<()>
+where S: SqlWriter + ?Sized {
+    let mut after_first = false;
+    for expr in exprs {
+        if after_first {
+            w.write(sep)?;
+        }
+        w.write_expr(expr)?;
+        after_first = true;
+    }
+    Ok(())
+}
+
+/// Runs `f` without producing any output and reports
+/// whether everything it wrote fits on the current line
+fn fits<S, F>(w: &mut S, f: F) -> Res<bool>
+where S: SqlWriter + ?Sized,
+      F: FnOnce(&mut S) -> Res<()> {
+    let remaining = match w.remaining_width() {
+        Some(remaining) => remaining,
+        None => return Ok(true),
+    };
+    w.start_measure();
+    let res = f(w);
+    let width = w.end_measure();
+    res?;
+    Ok(width.map(|width| width <= remaining).unwrap_or(false))
+}
+
+/// Writes `items` separated by `, ` between `open` and `close`
+/// when that fits in the maximum width, otherwise each item
+/// is written on its own indented line
+pub(crate) fn write_wrapped<S, T, F>(w: &mut S, open: &str, items: &[T], close: &str, mut f: F) -> Res<()>
+where S: SqlWriter + ?Sized,
+      F: FnMut(&mut S, &T) -> Res<()> {
+    let write_flat = |w: &mut S, f: &mut F| -> Res<()> {
+        w.write_keyword(open)?;
+        let mut after_first = false;
+        for item in items {
+            if after_first {
+                w.write(", ")?;
+            }
+            f(w, item)?;
+            after_first = true;
+        }
+        w.write(close)
+    };
+    if items.is_empty() || fits(w, |w| write_flat(w, &mut f))? {
+        return write_flat(w, &mut f);
+    }
+    if close.is_empty() && w.options().layout == Layout::River {
+        return write_river_list(w, open, items, f);
+    }
+    w.write_keyword(open.trim_end())?;
+    w.indent();
+    let mut after_first = false;
+    for item in items {
+        start_list_line(w, after_first)?;
+        f(w, item)?;
+        after_first = true;
+    }
+    w.dedent();
+    if !close.is_empty() {
+        w.write_new_line()?;
+        w.write_prefix()?;
+    }
+    w.write(close)
+}
+
+/// The column clause keywords end at in `Layout::River`,
+/// the width of `SELECT`
+const RIVER: usize = 6;
+
+/// Writes a keyword that starts a clause, in `Layout::River` its
+/// first word is right aligned so the clause bodies line up
+pub(crate) fn write_clause<S>(w: &mut S, keyword: &str) -> Res<()>
+where S: SqlWriter + ?Sized {
+    if w.options().layout == Layout::River {
+        let first = keyword.split_whitespace().next().unwrap_or_default();
+        let pad = RIVER.saturating_sub(first.chars().count());
+        w.write(&" ".repeat(pad))?;
+    }
+    w.write_keyword(keyword)
+}
+
+/// A clause list in `Layout::River` keeps its first item next
+/// to the keyword and lines the rest up on the far side of the river
+fn write_river_list<S, T, F>(w: &mut S, open: &str, items: &[T], mut f: F) -> Res<()>
+where S: SqlWriter + ?Sized,
+      F: FnMut(&mut S, &T) -> Res<()> {
+    w.write_keyword(open)?;
+    let leading = w.options().commas == CommaPlacement::Leading;
+    let mut after_first = false;
+    for item in items {
+        if after_first {
+            if !leading {
+                w.write(",")?;
+            }
+            w.write_new_line()?;
+            w.write_prefix()?;
+            if leading {
+                w.write(&" ".repeat(RIVER - 1))?;
+                w.write(", ")?;
+            } else {
+                w.write(&" ".repeat(RIVER + 1))?;
+            }
+        }
+        f(w, item)?;
+        after_first = true;
+    }
+    Ok(())
+}
+
+/// Writes the condition of a `WHERE` or `HAVING`, in
+/// `Layout::River` every top level `AND`/`OR` starts a
+/// new line aligned with the clause keywords
+pub(crate) fn write_condition<S>(w: &mut S, node: &Expr) -> Res<()>
+where S: SqlWriter + ?Sized {
+    let op = match node {
+        Expr::BinaryOp { op, .. } if w.options().layout == Layout::River
+            && (*op == BinaryOperator::And || *op == BinaryOperator::Or) => op,
+        _ => return w.write_expr(node),
+    };
+    let mut operands = Vec::new();
+    collect_operands(node, op, &mut operands);
+    w.write_expr(operands[0])?;
+    let keyword = if *op == BinaryOperator::And { "AND " } else { "OR " };
+    for operand in &operands[1..] {
+        w.write_new_line()?;
+        w.write_prefix()?;
+        write_clause(w, keyword)?;
+        w.write_expr(operand)?;
+    }
+    Ok(())
+}
+
+/// Starts the line for the next item of a list written one
+/// item per line, with the comma placed as configured
+pub(crate) fn start_list_line<S>(w: &mut S, after_first: bool) -> Res<()>
+where S: SqlWriter + ?Sized {
+    let leading = w.options().commas == CommaPlacement::Leading;
+    if after_first && !leading {
+        w.write(",")?;
+    }
+    w.write_new_line()?;
+    w.write_prefix()?;
+    if after_first && leading {
+        w.write(", ")?;
+    }
+    Ok(())
+}
+
+fn is_symbolic_operator(op: &BinaryOperator) -> bool {
+    !matches!(
+        op,
+        BinaryOperator::And
+        | BinaryOperator::Or
+        | BinaryOperator::Like
+        | BinaryOperator::NotLike
+    )
+}
+
+/// Writes a chain of `AND` or `OR` operations on one line when
+/// it fits in the maximum width, otherwise every operator
+/// starts a new indented line
+fn write_boolean_chain<S>(w: &mut S, node: &Expr, op: &BinaryOperator) -> Res<()>
+where S: SqlWriter + ?Sized {
+    let mut operands = Vec::new();
+    collect_operands(node, op, &mut operands);
+    let write_flat = |w: &mut S| -> Res<()> {
+        let mut after_first = false;
+        for operand in &operands {
+            if after_first {
+                w.write(" ")?;
+                w.write_binary_operator(op)?;
+                w.write(" ")?;
+            }
+            w.write_expr(operand)?;
+            after_first = true;
+        }
+        Ok(())
+    };
+    if fits(w, write_flat)? {
+        return write_flat(w);
+    }
+    w.write_expr(operands[0])?;
+    w.indent();
+    for operand in &operands[1..] {
+        w.write_new_line()?;
+        w.write_prefix()?;
+        w.write_binary_operator(op)?;
+        w.write(" ")?;
+        w.write_expr(operand)?;
+    }
+    w.dedent();
+    Ok(())
+}
+
+fn collect_operands<'a>(node: &'a Expr, op: &BinaryOperator, operands: &mut Vec<&'a Expr>) {
+    match node {
+        Expr::BinaryOp { left, op: inner, right } if inner == op => {
+            collect_operands(left, op, operands);
+            collect_operands(right, op, operands);
+        },
+        _ => operands.push(node),
+    }
+}
+
+/// Writes the ` (...)` of a `CREATE TABLE` with one
+/// column or constraint per line
+pub(crate) fn write_table_elements<S>(w: &mut S, columns: &[ColumnDef], constraints: &[TableConstraint]) -> Res<()>
+where S: SqlWriter + ?Sized {
+    w.write(" (")?;
+    w.indent();
+    let mut after_first = false;
+    for column in columns {
+        start_list_line(w, after_first)?;
+        w.write_column_def(column)?;
+        after_first = true;
+    }
+    for constraint in constraints {
+        start_list_line(w, after_first)?;
+        w.write_table_constraint(constraint)?;
+        after_first = true;
+    }
+    w.dedent();
+    w.write_new_line()?;
+    w.write_prefix()?;
+    w.write(")")
+}
+
+fn write_with_options<S>(w: &mut S, options: &[SqlOption]) -> Res<()>
+where S: SqlWriter + ?Sized {
+    if options.is_empty() {
+        return Ok(());
+    }
+    w.write_keyword(" WITH (")?;
+    let mut after_first = false;
+    for opt in options {
+        if after_first {
+            w.write(", ")?;
+        }
+        w.write_sql_option(opt)?;
+        after_first = true;
+    }
+    w.write(")")
+}
+
+fn write_transaction_modes<S>(w: &mut S, modes: &[TransactionMode]) -> Res<()>
+where S: SqlWriter + ?Sized {
+    let mut after_first = false;
+    for mode in modes {
+        if after_first {
+            w.write(",")?;
+        }
+        w.write(" ")?;
+        w.write_transaction_mode(mode)?;
+        after_first = true;
+    }
+    Ok(())
+}
+
+/// Writes the name of a function or type, which keeps
+/// the quotes it was written with but never gains any
+pub(crate) fn write_bare_name<S>(w: &mut S, name: &ObjectName) -> Res<()>
+where S: SqlWriter + ?Sized {
+    let case = w.options().identifier_case;
+    let mut after_first = false;
+    for part in &name.0 {
+        if after_first {
+            w.write(".")?;
+        }
+        if unquote_ident(part).is_some() {
+            w.write_ident(part)?;
+        } else {
+            w.write(&case.apply(part))?;
+        }
+        after_first = true;
+    }
+    Ok(())
+}
+
+/// Literals that `FormatOptions::strip_literals` replaces,
+/// `NULL` and booleans carry no data so they are kept
+fn is_strippable(val: &Value) -> bool {
+    !matches!(val, Value::Null | Value::Boolean(_))
+}
+
+/// Keywords (`CURRENT_TIMESTAMP`) and names that are
+/// not plain words (`@variable`) must stay unquoted
+fn can_quote(id: &str) -> bool {
+    let starts_with_word = id.chars().next()
+        .map(|c| c.is_alphabetic() || c == '_')
+        .unwrap_or(false);
+    starts_with_word && !ALL_KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(id))
+}
+
+/// Split a quoted identifier into its quote character
+/// and the text between the quotes, returns `None` for
+/// identifiers that were not quoted in the source
+pub(crate) fn unquote_ident(id: &str) -> Option<(char, &str)> {
+    let mut chars = id.chars();
+    let open = chars.next()?;
+    let close = match open {
+        '"' => '"',
+        '`' => '`',
+        '[' => ']',
+        _ => return None,
+    };
+    if id.len() < 2 || !id.ends_with(close) {
+        return None;
+    }
+    Some((open, &id[1..id.len() - 1]))
+}
@@ -0,0 +1,488 @@
+//! `MERGE`, which sqlparser 0.5 cannot read. T-SQL writes it
+//! as is, PostgreSQL and MySQL have no `MERGE` so the common
+//! upsert shape, update the matched rows and insert the rest,
+//! is written as an `INSERT` with `ON CONFLICT` or
+//! `ON DUPLICATE KEY UPDATE` instead.
+use crate::{
+    error::unsupported,
+    dml::{parse_output, write_output, OutputClause},
+    tsql::{expect_word, parse_word},
+    write_clause, write_condition, write_separated_expr, write_separated_idents, Res, SqlWriter,
+};
+use sqlparser::{
+    ast::*,
+    parser::{Parser, ParserError},
+    tokenizer::Token,
+};
+
+/// `MERGE INTO target USING source ON condition WHEN ... [OUTPUT ...]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Merge {
+    pub target: TableFactor,
+    pub source: TableFactor,
+    pub on: Expr,
+    pub clauses: Vec<MergeClause>,
+    pub output: Option<OutputClause>,
+}
+
+/// `WHEN [NOT] MATCHED [BY SOURCE] [AND condition] THEN action`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeClause {
+    pub kind: MergeMatch,
+    pub condition: Option<Expr>,
+    pub action: MergeAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeMatch {
+    Matched,
+    /// `NOT MATCHED [BY TARGET]`, a source row without a target row
+    NotMatched,
+    /// `NOT MATCHED BY SOURCE`, a target row without a source row
+    NotMatchedBySource,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeAction {
+    /// `UPDATE SET column = expr, ...`
+    Update(Vec<Assignment>),
+    Delete,
+    /// `INSERT [(column, ...)] VALUES (expr, ...)`
+    Insert {
+        columns: Vec<Ident>,
+        values: Vec<Expr>,
+    },
+}
+
+/// The rest of a `MERGE` after the keyword
+pub(crate) fn parse_merge(parser: &mut Parser) -> Result<Merge, ParserError> {
+    parse_word(parser, "INTO");
+    let target = parser.parse_table_factor()?;
+    expect_word(parser, "USING")?;
+    let source = parser.parse_table_factor()?;
+    expect_word(parser, "ON")?;
+    let on = parser.parse_expr()?;
+    let mut clauses = Vec::new();
+    while parse_word(parser, "WHEN") {
+        clauses.push(parse_clause(parser)?);
+    }
+    if clauses.is_empty() {
+        return Err(ParserError::ParserError(format!("Expected WHEN, found {:?}", parser.peek_token())));
+    }
+    let output = parse_output(parser)?;
+    Ok(Merge { target, source, on, clauses, output })
+}
+
+fn parse_clause(parser: &mut Parser) -> Result<MergeClause, ParserError> {
+    let kind = if parse_word(parser, "NOT") {
+        expect_word(parser, "MATCHED")?;
+        if parse_word(parser, "BY") {
+            if parse_word(parser, "SOURCE") {
+                MergeMatch::NotMatchedBySource
+            } else {
+                expect_word(parser, "TARGET")?;
+                MergeMatch::NotMatched
+            }
+        } else {
+            MergeMatch::NotMatched
+        }
+    } else {
+        expect_word(parser, "MATCHED")?;
+        MergeMatch::Matched
+    };
+    let condition = if parse_word(parser, "AND") {
+        Some(parser.parse_expr()?)
+    } else {
+        None
+    };
+    expect_word(parser, "THEN")?;
+    let action = if parse_word(parser, "UPDATE") {
+        expect_word(parser, "SET")?;
+        let mut assignments = Vec::new();
+        loop {
+            let id = parser.parse_identifier()?;
+            parser.expect_token(&Token::Eq)?;
+            let value = parser.parse_expr()?;
+            assignments.push(Assignment { id, value });
+            if !parser.consume_token(&Token::Comma) {
+                break;
+            }
+        }
+        MergeAction::Update(assignments)
+    } else if parse_word(parser, "DELETE") {
+        MergeAction::Delete
+    } else {
+        expect_word(parser, "INSERT")?;
+        let mut columns = Vec::new();
+        if parser.consume_token(&Token::LParen) {
+            columns.push(parser.parse_identifier()?);
+            while parser.consume_token(&Token::Comma) {
+                columns.push(parser.parse_identifier()?);
+            }
+            parser.expect_token(&Token::RParen)?;
+        }
+        expect_word(parser, "VALUES")?;
+        parser.expect_token(&Token::LParen)?;
+        let mut values = vec![parser.parse_expr()?];
+        while parser.consume_token(&Token::Comma) {
+            values.push(parser.parse_expr()?);
+        }
+        parser.expect_token(&Token::RParen)?;
+        MergeAction::Insert { columns, values }
+    };
+    Ok(MergeClause { kind, condition, action })
+}
+
+/// Write `MERGE` the way T-SQL spells it
+pub(crate) fn write_merge<S>(w: &mut S, node: &Merge) -> Res<()>
+where S: SqlWriter + ?Sized {
+    w.write_keyword("MERGE INTO ")?;
+    w.write_table_factor(&node.target)?;
+    w.write_new_line()?;
+    w.write_prefix()?;
+    write_clause(w, "USING ")?;
+    w.write_table_factor(&node.source)?;
+    w.write_new_line()?;
+    w.write_prefix()?;
+    write_clause(w, "ON ")?;
+    write_condition(w, &node.on)?;
+    for clause in &node.clauses {
+        w.write_new_line()?;
+        w.write_prefix()?;
+        w.write_keyword(match clause.kind {
+            MergeMatch::Matched => "WHEN MATCHED",
+            MergeMatch::NotMatched => "WHEN NOT MATCHED",
+            MergeMatch::NotMatchedBySource => "WHEN NOT MATCHED BY SOURCE",
+        })?;
+        if let Some(condition) = &clause.condition {
+            w.write_keyword(" AND ")?;
+            w.write_expr(condition)?;
+        }
+        w.write_keyword(" THEN")?;
+        w.indent();
+        w.write_new_line()?;
+        w.write_prefix()?;
+        match &clause.action {
+            MergeAction::Update(assignments) => {
+                w.write_keyword("UPDATE SET ")?;
+                write_assignments(w, assignments)?;
+            },
+            MergeAction::Delete => w.write_keyword("DELETE")?,
+            MergeAction::Insert { columns, values } => {
+                w.write_keyword("INSERT")?;
+                if !columns.is_empty() {
+                    w.write(" (")?;
+                    write_separated_idents(w, ", ", columns)?;
+                    w.write(")")?;
+                }
+                w.write_new_line()?;
+                w.write_prefix()?;
+                w.write_keyword("VALUES (")?;
+                write_separated_expr(w, ", ", values)?;
+                w.write(")")?;
+            },
+        }
+        w.dedent();
+    }
+    if let Some(output) = &node.output {
+        write_output(w, output)?;
+    }
+    // T-SQL requires the `;`
+    w.write(";")
+}
+
+/// A `MERGE` taken apart as an insert that updates
+/// the rows it conflicts with
+struct Upsert<'a> {
+    insert: Statement,
+    /// The target columns the `ON` condition matches on
+    conflict: Vec<Ident>,
+    updates: Vec<(&'a Ident, UpdateValue<'a>)>,
+}
+
+enum UpdateValue<'a> {
+    /// The value the insert would have written to this column
+    Inserted(&'a Ident),
+    /// An expression that does not read either table
+    Expr(&'a Expr),
+}
+
+/// Only the upsert shape translates: an optional
+/// `WHEN MATCHED THEN UPDATE` and a `WHEN NOT MATCHED THEN INSERT`,
+/// matching target columns to source columns with `=`
+fn upsert<'a>(node: &'a Merge, dialect: &str) -> Res<Upsert<'a>> {
+    if node.output.is_some() {
+        return unsupported(format!("{} cannot return the rows of a MERGE with OUTPUT", dialect));
+    }
+    let (table, target) = match &node.target {
+        TableFactor::Table { name, alias, .. } => (name, alias_or_name(name, alias)),
+        _ => return unsupported("the target of a MERGE has to be a table"),
+    };
+    let source = match &node.source {
+        TableFactor::Table { name, alias, .. } => alias_or_name(name, alias),
+        TableFactor::Derived { alias: Some(alias), .. } => alias.name.clone(),
+        _ => return unsupported("the source of a MERGE has to be a table or an aliased subquery"),
+    };
+    let mut insert = None;
+    let mut update = None;
+    for clause in &node.clauses {
+        if clause.condition.is_some() {
+            return unsupported(format!("{} has no conditional WHEN clauses for an upsert", dialect));
+        }
+        match (clause.kind, &clause.action) {
+            (MergeMatch::NotMatched, MergeAction::Insert { columns, values }) if insert.is_none() => {
+                insert = Some((columns, values));
+            },
+            (MergeMatch::Matched, MergeAction::Update(assignments)) if update.is_none() => {
+                update = Some(assignments);
+            },
+            _ => return unsupported(format!("{} can only write a MERGE that updates matches and inserts the rest", dialect)),
+        }
+    }
+    let (columns, values) = match insert {
+        Some(insert) => insert,
+        None => return unsupported(format!("{} can only write a MERGE that inserts the rows it does not match", dialect)),
+    };
+    if columns.is_empty() {
+        return unsupported("a MERGE has to name the columns it inserts");
+    }
+    let mut conflict = Vec::new();
+    collect_conflict(&node.on, &target, &source, &mut conflict)?;
+    let mut updates = Vec::new();
+    for assignment in update.into_iter().flatten() {
+        let value = match values.iter().position(|v| *v == assignment.value) {
+            Some(i) => UpdateValue::Inserted(&columns[i]),
+            None if !mentions(&assignment.value, &target) && !mentions(&assignment.value, &source) => {
+                UpdateValue::Expr(&assignment.value)
+            },
+            None => return unsupported(format!("{} can only update a column to a value the MERGE inserts", dialect)),
+        };
+        updates.push((&assignment.id, value));
+    }
+    let select = Select {
+        distinct: false,
+        projection: values.iter().cloned().map(SelectItem::UnnamedExpr).collect(),
+        from: vec![TableWithJoins {
+            relation: node.source.clone(),
+            joins: vec![],
+        }],
+        selection: None,
+        group_by: vec![],
+        having: None,
+    };
+    let insert = Statement::Insert {
+        table_name: table.clone(),
+        columns: columns.clone(),
+        source: Box::new(Query {
+            ctes: vec![],
+            body: SetExpr::Select(Box::new(select)),
+            order_by: vec![],
+            limit: None,
+            offset: None,
+            fetch: None,
+        }),
+    };
+    Ok(Upsert { insert, conflict, updates })
+}
+
+fn alias_or_name(name: &ObjectName, alias: &Option<TableAlias>) -> Ident {
+    match alias {
+        Some(alias) => alias.name.clone(),
+        None => name.0.last().cloned().unwrap_or_default(),
+    }
+}
+
+/// The target columns of `target.a = source.b AND ...`
+fn collect_conflict(on: &Expr, target: &str, source: &str, columns: &mut Vec<Ident>) -> Res<()> {
+    match on {
+        Expr::Nested(inner) => collect_conflict(inner, target, source, columns),
+        Expr::BinaryOp { left, op: BinaryOperator::And, right } => {
+            collect_conflict(left, target, source, columns)?;
+            collect_conflict(right, target, source, columns)
+        },
+        Expr::BinaryOp { left, op: BinaryOperator::Eq, right } => {
+            match (column_of(left, target), column_of(right, source), column_of(right, target), column_of(left, source)) {
+                (Some(column), Some(_), _, _) | (_, _, Some(column), Some(_)) => {
+                    columns.push(column.clone());
+                    Ok(())
+                },
+                _ => unsupported("the ON condition of an upsert can only compare target columns to source columns"),
+            }
+        },
+        _ => unsupported("the ON condition of an upsert can only compare target columns to source columns"),
+    }
+}
+
+/// The column of `table.column`
+fn column_of<'a>(expr: &'a Expr, table: &str) -> Option<&'a Ident> {
+    match expr {
+        Expr::CompoundIdentifier(parts) if parts.len() == 2 && parts[0] == table => Some(&parts[1]),
+        _ => None,
+    }
+}
+
+/// Whether `expr` might read a column of `table`, anything
+/// this does not look into is assumed to
+fn mentions(expr: &Expr, table: &str) -> bool {
+    match expr {
+        Expr::Identifier(id) => !id.starts_with('@'),
+        Expr::CompoundIdentifier(parts) => parts.len() != 2 || parts[0] == table,
+        Expr::Value(_) => false,
+        Expr::Nested(inner)
+        | Expr::IsNull(inner)
+        | Expr::IsNotNull(inner)
+        | Expr::UnaryOp { expr: inner, .. }
+        | Expr::Cast { expr: inner, .. } => mentions(inner, table),
+        Expr::BinaryOp { left, right, .. } => mentions(left, table) || mentions(right, table),
+        Expr::Function(f) => f.over.is_some() || f.args.iter().any(|arg| mentions(arg, table)),
+        _ => true,
+    }
+}
+
+/// PostgreSQL, `INSERT ... SELECT ... ON CONFLICT (columns) DO UPDATE SET ...`
+pub(crate) fn write_on_conflict<S>(w: &mut S, node: &Merge) -> Res<()>
+where S: SqlWriter + ?Sized {
+    let upsert = upsert(node, "PostgreSQL")?;
+    w.write_statement(&upsert.insert)?;
+    w.write_new_line()?;
+    w.write_prefix()?;
+    w.write_keyword("ON CONFLICT (")?;
+    write_separated_idents(w, ", ", &upsert.conflict)?;
+    if upsert.updates.is_empty() {
+        return w.write_keyword(") DO NOTHING");
+    }
+    w.write_keyword(") DO UPDATE SET ")?;
+    let mut after_first = false;
+    for (column, value) in &upsert.updates {
+        if after_first {
+            w.write(", ")?;
+        }
+        w.write_ident(column)?;
+        w.write(" = ")?;
+        match value {
+            UpdateValue::Inserted(inserted) => {
+                w.write_keyword("EXCLUDED.")?;
+                w.write_ident(inserted)?;
+            },
+            UpdateValue::Expr(expr) => w.write_expr(expr)?,
+        }
+        after_first = true;
+    }
+    Ok(())
+}
+
+/// MySQL, `INSERT ... SELECT ... ON DUPLICATE KEY UPDATE ...`,
+/// the unique keys of the table decide what conflicts so the
+/// `ON` condition is not written
+pub(crate) fn write_on_duplicate_key<S>(w: &mut S, node: &Merge) -> Res<()>
+where S: SqlWriter + ?Sized {
+    let upsert = upsert(node, "MySQL")?;
+    w.write_statement(&upsert.insert)?;
+    w.write_new_line()?;
+    w.write_prefix()?;
+    w.write_keyword("ON DUPLICATE KEY UPDATE ")?;
+    if upsert.updates.is_empty() {
+        // inserting only the new rows is an update that changes nothing
+        let column = &upsert.conflict[0];
+        w.write_ident(column)?;
+        w.write(" = ")?;
+        return w.write_ident(column);
+    }
+    let mut after_first = false;
+    for (column, value) in &upsert.updates {
+        if after_first {
+            w.write(", ")?;
+        }
+        w.write_ident(column)?;
+        w.write(" = ")?;
+        match value {
+            UpdateValue::Inserted(inserted) => {
+                w.write_keyword("VALUES(")?;
+                w.write_ident(inserted)?;
+                w.write(")")?;
+            },
+            UpdateValue::Expr(expr) => w.write_expr(expr)?,
+        }
+        after_first = true;
+    }
+    Ok(())
+}
+
+fn write_assignments<S>(w: &mut S, assignments: &[Assignment]) -> Res<()>
+where S: SqlWriter + ?Sized {
+    let mut after_first = false;
+    for assignment in assignments {
+        if after_first {
+            w.write(", ")?;
+        }
+        w.write_assignment(assignment)?;
+        after_first = true;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{MySqlWriter, PostgresWriter};
+
+    fn column(table: &str, name: &str) -> Expr {
+        Expr::CompoundIdentifier(vec![table.to_string(), name.to_string()])
+    }
+
+    fn table(name: &str, alias: &str) -> TableFactor {
+        TableFactor::Table {
+            name: ObjectName(vec![name.to_string()]),
+            alias: Some(TableAlias {
+                name: alias.to_string(),
+                columns: vec![],
+            }),
+            args: vec![],
+            with_hints: vec![],
+        }
+    }
+
+    #[test]
+    fn upserts() {
+        let merge = Merge {
+            target: table("people", "t"),
+            source: table("staging", "s"),
+            on: Expr::BinaryOp {
+                left: Box::new(column("t", "id")),
+                op: BinaryOperator::Eq,
+                right: Box::new(column("s", "id")),
+            },
+            clauses: vec![
+                MergeClause {
+                    kind: MergeMatch::Matched,
+                    condition: None,
+                    action: MergeAction::Update(vec![Assignment {
+                        id: "name".to_string(),
+                        value: column("s", "name"),
+                    }]),
+                },
+                MergeClause {
+                    kind: MergeMatch::NotMatched,
+                    condition: None,
+                    action: MergeAction::Insert {
+                        columns: vec!["id".to_string(), "name".to_string()],
+                        values: vec![column("s", "id"), column("s", "name")],
+                    },
+                },
+            ],
+            output: None,
+        };
+        let mut w = PostgresWriter::new("    ", Vec::new());
+        w.write_merge(&merge).unwrap();
+        assert_eq!(String::from_utf8(w.into_inner()).unwrap(), "INSERT INTO people (id, name)
+SELECT s.id, s.name
+FROM staging AS s
+ON CONFLICT (id) DO UPDATE SET name = EXCLUDED.name");
+        let mut w = MySqlWriter::new("    ", Vec::new());
+        w.write_merge(&merge).unwrap();
+        assert_eq!(String::from_utf8(w.into_inner()).unwrap(), "INSERT INTO people (id, name)
+SELECT s.id, s.name
+FROM staging AS s
+ON DUPLICATE KEY UPDATE name = VALUES(name)");
+    }
+}
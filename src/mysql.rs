@@ -0,0 +1,149 @@
+use crate::{error::unsupported, generic, merge, output::{self, Output}, pivot, write_clause, unquote_ident, write_bare_name, Merge, SqlWriter, Res};
+use std::io::Write;
+use sqlparser::ast::*;
+
+/// Writes SQL for MySQL 8
+pub struct MySqlWriter<W> {
+    out: Output<W>,
+}
+
+impl<W> MySqlWriter<W>
+where W: Write {
+    pub fn new(prefix: &'static str, writer: W) -> Self {
+        Self {
+            out: Output::new(prefix, writer),
+        }
+    }
+
+    output::writer_builders!();
+}
+
+impl<W> SqlWriter for MySqlWriter<W>
+where W: Write {
+    output::output_primitives!();
+
+    fn write_ident(&mut self, id: &str) -> Res<()> {
+        match unquote_ident(id) {
+            Some((_, inner)) => self.write(&format!("`{}`", inner)),
+            None => self.write_unquoted_ident(id),
+        }
+    }
+
+    /// MySQL only accepts an OFFSET after a LIMIT, the largest
+    /// unsigned 64 bit value stands in for "every row"
+    fn write_limit(&mut self, node: &Query) -> Res<()> {
+        let limit = match (&node.limit, &node.fetch) {
+            (Some(_), Some(_)) => {
+                return unsupported("MySQL does not allow LIMIT and FETCH in the same query")
+            },
+            (Some(limit), None) => Some(limit.clone()),
+            (None, Some(fetch)) => Some(generic::fetch_as_limit("MySQL", fetch)?),
+            (None, None) => None,
+        };
+        if limit.is_none() && node.offset.is_none() {
+            return Ok(());
+        }
+        self.write_new_line()?;
+        self.write_prefix()?;
+        write_clause(self, "LIMIT ")?;
+        match &limit {
+            Some(limit) => self.write_expr(limit)?,
+            None => self.write("18446744073709551615")?,
+        }
+        if let Some(offset) = &node.offset {
+            self.write_new_line()?;
+            self.write_prefix()?;
+            write_clause(self, "OFFSET ")?;
+            self.write_expr(offset)?;
+        }
+        Ok(())
+    }
+
+    /// `CAST` in MySQL only accepts a handful of target types
+    fn write_cast(&mut self, expr: &Expr, data_type: &DataType) -> Res<()> {
+        self.write_keyword("CAST(")?;
+        self.write_expr(expr)?;
+        self.write_keyword(" AS ")?;
+        match data_type {
+            DataType::SmallInt | DataType::Int | DataType::BigInt => self.write_keyword("SIGNED")?,
+            DataType::Char(_) | DataType::Varchar(_) | DataType::Text | DataType::Clob(_) | DataType::Uuid => {
+                self.write_keyword("CHAR")?
+            },
+            DataType::Real | DataType::Double => self.write_keyword("DOUBLE")?,
+            DataType::Timestamp => self.write_keyword("DATETIME")?,
+            DataType::Decimal(..)
+            | DataType::Float(_)
+            | DataType::Date
+            | DataType::Time
+            | DataType::Binary(_) => self.write_data_type(data_type)?,
+            _ => return unsupported(format!("MySQL cannot CAST to {:?}", data_type)),
+        }
+        self.write(")")
+    }
+
+    fn write_merge(&mut self, node: &Merge) -> Res<()> {
+        merge::write_on_duplicate_key(self, node)
+    }
+
+    fn write_join(&mut self, node: &Join) -> Res<()> {
+        match (&node.join_operator, &node.relation) {
+            (JoinOperator::CrossApply, TableFactor::Table { args, .. })
+            | (JoinOperator::OuterApply, TableFactor::Table { args, .. })
+                if !args.is_empty() && !pivot::is_pivot(node) =>
+            {
+                unsupported("MySQL has no table-valued functions to join LATERAL")
+            },
+            _ => generic::write_lateral_join(self, node, true),
+        }
+    }
+
+    fn write_data_type(&mut self, node: &DataType) -> Res<()> {
+        if !self.out.translate_types {
+            return generic::write_data_type(self, node);
+        }
+        match node {
+            DataType::Varchar(size) => self.write_keyword(&format!("VARCHAR({})", size.unwrap_or(255))),
+            DataType::Uuid => self.write_keyword("CHAR(36)"),
+            DataType::Clob(_) => self.write_keyword("LONGTEXT"),
+            DataType::Bytea => self.write_keyword("LONGBLOB"),
+            DataType::Decimal(precision, scale) => {
+                self.write_keyword("DECIMAL")?;
+                if let Some(precision) = precision {
+                    self.write(&format!("({}", precision))?;
+                    if let Some(scale) = scale {
+                        self.write(&format!(", {}", scale))?;
+                    }
+                    self.write(")")?;
+                }
+                Ok(())
+            },
+            DataType::Double => self.write_keyword("DOUBLE"),
+            DataType::Timestamp => self.write_keyword("DATETIME"),
+            DataType::Interval | DataType::Regclass | DataType::Array(_) => {
+                unsupported(format!("MySQL has no {:?} data type", node))
+            },
+            DataType::Custom(name) => {
+                let last = name.0.last().map(|n| n.to_uppercase()).unwrap_or_default();
+                match last.as_str() {
+                    "NVARCHAR" => self.write_keyword("VARCHAR(255)"),
+                    "NTEXT" => self.write_keyword("LONGTEXT"),
+                    "BIT" => self.write_keyword("BOOLEAN"),
+                    "DATETIME2" => self.write_keyword("DATETIME(6)"),
+                    "SMALLDATETIME" => self.write_keyword("DATETIME"),
+                    "UNIQUEIDENTIFIER" => self.write_keyword("CHAR(36)"),
+                    "MONEY" => self.write_keyword("DECIMAL(19, 4)"),
+                    _ => write_bare_name(self, name),
+                }
+            },
+            _ => generic::write_data_type(self, node),
+        }
+    }
+
+    fn write_value(&mut self, node: &Value) -> Res<()> {
+        match node {
+            Value::Boolean(true) => self.write_keyword("TRUE"),
+            Value::Boolean(false) => self.write_keyword("FALSE"),
+            _ => generic::write_value(self, node),
+        }
+    }
+}
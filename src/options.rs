@@ -0,0 +1,310 @@
+use serde::Deserialize;
+use std::borrow::Cow;
+
+/// How the letters of keywords or identifiers are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Case {
+    Upper,
+    Lower,
+    /// Leave the text as the writer produced it, sqlparser
+    /// does not keep the case keywords were parsed with so
+    /// keywords are still written in upper case
+    Preserve,
+}
+
+impl Case {
+    pub(crate) fn apply(self, s: &str) -> Cow<'_, str> {
+        match self {
+            Case::Upper => s.to_uppercase().into(),
+            Case::Lower => s.to_lowercase().into(),
+            Case::Preserve => s.into(),
+        }
+    }
+}
+
+/// Where the comma goes when a list is written one item per line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommaPlacement {
+    /// `a,` at the end of the line
+    Trailing,
+    /// `, b` at the start of the next line
+    Leading,
+}
+
+/// Which identifiers are written with quotes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Quoting {
+    /// Quote the identifiers that were quoted in the source
+    Preserve,
+    /// Quote every identifier that is not a keyword, the name
+    /// is quoted as written (after `identifier_case`) so it
+    /// becomes case sensitive in dialects that fold names
+    Always,
+}
+
+/// How clauses are laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// Every clause keyword starts at the indentation
+    Left,
+    /// Clause keywords are right aligned so their bodies
+    /// start in the same column (`SELECT`, `  FROM`, ` WHERE`)
+    River,
+}
+
+/// The layout choices shared by every dialect writer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// The text written once per indentation level
+    pub indent: Cow<'static, str>,
+    pub keyword_case: Case,
+    /// Only applied to identifiers that were not quoted
+    pub identifier_case: Case,
+    pub quoting: Quoting,
+    pub commas: CommaPlacement,
+    pub layout: Layout,
+    /// Write `AS` between a table and its alias
+    pub table_alias_as: bool,
+    /// Surround symbolic operators like `=` and `+` with spaces
+    pub operator_spacing: bool,
+    /// Break long lists and boolean chains onto indented
+    /// lines once they would run past this many characters
+    pub max_width: Option<usize>,
+    /// The number of empty lines written between statements
+    pub blank_lines: usize,
+    /// Write everything on one line with only the
+    /// spaces needed to keep words apart, and no comments
+    pub minify: bool,
+    /// Replace number and string literals with `?`
+    pub strip_literals: bool,
+}
+
+impl FormatOptions {
+    pub const fn new() -> Self {
+        Self {
+            indent: Cow::Borrowed("    "),
+            keyword_case: Case::Upper,
+            identifier_case: Case::Preserve,
+            quoting: Quoting::Preserve,
+            commas: CommaPlacement::Trailing,
+            layout: Layout::Left,
+            table_alias_as: true,
+            operator_spacing: true,
+            max_width: None,
+            blank_lines: 0,
+            minify: false,
+            strip_literals: false,
+        }
+    }
+}
+
+impl FormatOptions {
+    /// The shortest output, for logging or
+    /// embedding queries in string constants
+    pub fn minified(strip_literals: bool) -> Self {
+        Self {
+            minify: true,
+            strip_literals,
+            ..Self::new()
+        }
+    }
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub(crate) static DEFAULT_OPTIONS: FormatOptions = FormatOptions::new();
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{PostgresWriter, SqlWriter};
+    use sqlparser::ast::*;
+
+    #[test]
+    fn keyword_case_and_commas() {
+        let s = Select {
+            distinct: false,
+            projection: vec![
+                SelectItem::UnnamedExpr(Expr::Identifier("FirstName".to_string())),
+                SelectItem::UnnamedExpr(Expr::Identifier("\"LastName\"".to_string())),
+            ],
+            from: vec![TableWithJoins {
+                relation: TableFactor::Table {
+                    name: ObjectName(vec!["People".to_string()]),
+                    alias: Some(TableAlias {
+                        name: "p".to_string(),
+                        columns: vec![],
+                    }),
+                    args: vec![],
+                    with_hints: vec![],
+                },
+                joins: vec![],
+            }],
+            selection: Some(Expr::BinaryOp {
+                left: Box::new(Expr::Identifier("age".to_string())),
+                op: BinaryOperator::GtEq,
+                right: Box::new(Expr::Value(Value::Number("18".to_string()))),
+            }),
+            group_by: vec![],
+            having: None,
+        };
+        let options = FormatOptions {
+            indent: "  ".into(),
+            keyword_case: Case::Lower,
+            identifier_case: Case::Lower,
+            quoting: Quoting::Preserve,
+            commas: CommaPlacement::Leading,
+            layout: Layout::Left,
+            table_alias_as: false,
+            operator_spacing: false,
+            max_width: Some(20),
+            blank_lines: 0,
+            minify: false,
+            strip_literals: false,
+        };
+        let mut w = PostgresWriter::new("    ", Vec::new()).options(options);
+        w.write_select(&s).unwrap();
+        let out = String::from_utf8(w.into_inner()).unwrap();
+        assert_eq!(out, "select
+  firstname
+  , \"LastName\"
+from people p
+where age>=18")
+    }
+
+    #[test]
+    fn sized_types_follow_keyword_case() {
+        let options = FormatOptions {
+            keyword_case: Case::Lower,
+            ..FormatOptions::new()
+        };
+        let mut w = PostgresWriter::new("    ", Vec::new()).options(options);
+        w.write_data_type(&DataType::Varchar(Some(10))).unwrap();
+        w.write(" ").unwrap();
+        w.write_data_type(&DataType::Char(None)).unwrap();
+        assert_eq!(String::from_utf8(w.into_inner()).unwrap(), "varchar(10) char");
+    }
+
+    #[test]
+    fn always_quote() {
+        let projection = vec![
+            SelectItem::UnnamedExpr(Expr::Identifier("name".to_string())),
+            SelectItem::UnnamedExpr(Expr::Identifier("CURRENT_TIMESTAMP".to_string())),
+            SelectItem::UnnamedExpr(Expr::Function(Function {
+                name: ObjectName(vec!["COUNT".to_string()]),
+                args: vec![Expr::Identifier("id".to_string())],
+                over: None,
+                distinct: false,
+            })),
+        ];
+        let s = Select {
+            distinct: false,
+            projection,
+            from: vec![],
+            selection: None,
+            group_by: vec![],
+            having: None,
+        };
+        let options = FormatOptions {
+            quoting: Quoting::Always,
+            ..FormatOptions::new()
+        };
+        let mut w = crate::MsSqlWriter::new("    ", Vec::new()).options(options);
+        w.write_select(&s).unwrap();
+        let out = String::from_utf8(w.into_inner()).unwrap();
+        assert_eq!(out, "SELECT [name], CURRENT_TIMESTAMP, COUNT([id])")
+    }
+
+    #[test]
+    fn minify() {
+        let eq = |left: &str, op: BinaryOperator, right: Value| Expr::BinaryOp {
+            left: Box::new(Expr::Identifier(left.to_string())),
+            op,
+            right: Box::new(Expr::Value(right)),
+        };
+        let s = Select {
+            distinct: false,
+            projection: vec![
+                SelectItem::UnnamedExpr(Expr::Identifier("id".to_string())),
+                SelectItem::UnnamedExpr(Expr::Identifier("[full name]".to_string())),
+            ],
+            from: vec![TableWithJoins {
+                relation: TableFactor::Table {
+                    name: ObjectName(vec!["people".to_string()]),
+                    alias: None,
+                    args: vec![],
+                    with_hints: vec![],
+                },
+                joins: vec![],
+            }],
+            selection: Some(Expr::BinaryOp {
+                left: Box::new(eq("city", BinaryOperator::Eq, Value::SingleQuotedString("New  York".to_string()))),
+                op: BinaryOperator::And,
+                right: Box::new(eq("age", BinaryOperator::GtEq, Value::Number("18".to_string()))),
+            }),
+            group_by: vec![],
+            having: None,
+        };
+        let mut w = crate::MsSqlWriter::new("    ", Vec::new()).options(FormatOptions::minified(false));
+        w.write_select(&s).unwrap();
+        let out = String::from_utf8(w.into_inner()).unwrap();
+        assert_eq!(out, "SELECT id,[full name] FROM people WHERE city='New  York' AND age>=18");
+        let mut w = crate::MsSqlWriter::new("    ", Vec::new()).options(FormatOptions::minified(true));
+        w.write_select(&s).unwrap();
+        let out = String::from_utf8(w.into_inner()).unwrap();
+        assert_eq!(out, "SELECT id,[full name] FROM people WHERE city=? AND age>=?");
+    }
+
+    #[test]
+    fn river() {
+        let cmp = |left: &str, op: BinaryOperator, right: Value| Expr::BinaryOp {
+            left: Box::new(Expr::Identifier(left.to_string())),
+            op,
+            right: Box::new(Expr::Value(right)),
+        };
+        let s = Select {
+            distinct: false,
+            projection: ["id", "first_name", "last_name"].iter()
+                .map(|name| SelectItem::UnnamedExpr(Expr::Identifier(name.to_string())))
+                .collect(),
+            from: vec![TableWithJoins {
+                relation: TableFactor::Table {
+                    name: ObjectName(vec!["people".to_string()]),
+                    alias: None,
+                    args: vec![],
+                    with_hints: vec![],
+                },
+                joins: vec![],
+            }],
+            selection: Some(Expr::BinaryOp {
+                left: Box::new(cmp("age", BinaryOperator::GtEq, Value::Number("18".to_string()))),
+                op: BinaryOperator::And,
+                right: Box::new(cmp("city", BinaryOperator::Eq, Value::SingleQuotedString("Oslo".to_string()))),
+            }),
+            group_by: vec![],
+            having: None,
+        };
+        let options = FormatOptions {
+            layout: Layout::River,
+            max_width: Some(25),
+            ..FormatOptions::new()
+        };
+        let mut w = crate::MsSqlWriter::new("    ", Vec::new()).options(options);
+        w.write_select(&s).unwrap();
+        let out = String::from_utf8(w.into_inner()).unwrap();
+        assert_eq!(out, "SELECT id,
+       first_name,
+       last_name
+  FROM people
+ WHERE age >= 18
+   AND city = 'Oslo'")
+    }
+}
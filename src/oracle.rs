@@ -0,0 +1,321 @@
+use crate::{error::unsupported, generic, output::{self, Output}, write_clause, unquote_ident, write_bare_name, SqlWriter, Res};
+use std::io::Write;
+use sqlparser::ast::*;
+
+/// Writes SQL for Oracle.
+///
+/// Row limits are written as `OFFSET n ROWS FETCH FIRST n ROWS ONLY`
+/// by default, `rownum_paging` wraps the query in `ROWNUM` filters
+/// instead for databases older than 12c. Oracle stores `''` as NULL
+/// so every empty string literal written is recorded in `warnings`.
+pub struct OracleWriter<W> {
+    out: Output<W>,
+    rownum_paging: bool,
+}
+
+impl<W> OracleWriter<W>
+where W: Write {
+    pub fn new(prefix: &'static str, writer: W) -> Self {
+        Self {
+            out: Output::new(prefix, writer),
+            rownum_paging: false,
+        }
+    }
+
+    /// Page with `ROWNUM` for Oracle 11g and earlier,
+    /// which have no `FETCH FIRST` clause
+    pub fn rownum_paging(mut self, rownum: bool) -> Self {
+        self.rownum_paging = rownum;
+        self
+    }
+
+    output::writer_builders!();
+}
+
+impl<W> SqlWriter for OracleWriter<W>
+where W: Write {
+    output::output_primitives!();
+
+    fn write_ident(&mut self, id: &str) -> Res<()> {
+        match unquote_ident(id) {
+            Some((_, inner)) => self.write(&format!("\"{}\"", inner)),
+            None => self.write_unquoted_ident(id),
+        }
+    }
+
+    fn write_table_factor_alias(&mut self, node: &TableAlias) -> Res<()> {
+        if !node.columns.is_empty() {
+            return unsupported("Oracle does not support column lists in table aliases");
+        }
+        self.write(" ")?;
+        self.write_ident(&node.name)
+    }
+
+    fn write_select(&mut self, node: &Select) -> Res<()> {
+        if !node.from.is_empty() {
+            return generic::write_select(self, node);
+        }
+        let mut with_dual = node.clone();
+        with_dual.from.push(TableWithJoins {
+            relation: TableFactor::Table {
+                name: ObjectName(vec!["DUAL".to_string()]),
+                alias: None,
+                args: vec![],
+                with_hints: vec![],
+            },
+            joins: vec![],
+        });
+        generic::write_select(self, &with_dual)
+    }
+
+    fn write_query(&mut self, node: &Query) -> Res<()> {
+        if !self.rownum_paging
+            || (node.limit.is_none() && node.offset.is_none() && node.fetch.is_none()) {
+            return generic::write_query(self, node);
+        }
+        let limit = match (&node.limit, &node.fetch) {
+            (Some(_), Some(_)) => {
+                return unsupported("LIMIT and FETCH cannot be combined in the same query")
+            },
+            (Some(limit), None) => Some(limit.clone()),
+            (None, Some(fetch)) => Some(generic::fetch_as_limit("Oracle 11g", fetch)?),
+            (None, None) => None,
+        };
+        let mut inner = node.clone();
+        inner.limit = None;
+        inner.offset = None;
+        inner.fetch = None;
+        let offset = match &node.offset {
+            Some(offset) => offset,
+            None => {
+                self.write_keyword("SELECT *")?;
+                self.write_new_line()?;
+                self.write_prefix()?;
+                self.write_rownum_source(&inner)?;
+                if let Some(limit) = &limit {
+                    self.write_new_line()?;
+                    self.write_prefix()?;
+                    write_clause(self, "WHERE ROWNUM <= ")?;
+                    self.write_expr(limit)?;
+                }
+                return Ok(());
+            },
+        };
+        self.write_keyword("SELECT *")?;
+        self.write_new_line()?;
+        self.write_prefix()?;
+        self.write_keyword("FROM (")?;
+        self.indent();
+        self.write_new_line()?;
+        self.write_prefix()?;
+        self.write_keyword("SELECT ")?;
+        self.write("paged_.*, ")?;
+        self.write_keyword("ROWNUM AS ")?;
+        self.write("rownum_")?;
+        self.write_new_line()?;
+        self.write_prefix()?;
+        self.write_rownum_source(&inner)?;
+        self.write(" paged_")?;
+        if let Some(limit) = &limit {
+            self.write_new_line()?;
+            self.write_prefix()?;
+            write_clause(self, "WHERE ROWNUM <= ")?;
+            self.write_expr(offset)?;
+            self.write(" + ")?;
+            self.write_expr(limit)?;
+        }
+        self.dedent();
+        self.write_new_line()?;
+        self.write_prefix()?;
+        self.write(")")?;
+        self.write_new_line()?;
+        self.write_prefix()?;
+        write_clause(self, "WHERE ")?;
+        self.write("rownum_ > ")?;
+        self.write_expr(offset)
+    }
+
+    /// Oracle puts OFFSET before FETCH and has no LIMIT
+    fn write_limit(&mut self, node: &Query) -> Res<()> {
+        if let Some(offset) = &node.offset {
+            self.write_new_line()?;
+            self.write_prefix()?;
+            write_clause(self, "OFFSET ")?;
+            self.write_expr(offset)?;
+            self.write_keyword(" ROWS")?;
+        }
+        let fetch = match (&node.limit, &node.fetch) {
+            (Some(_), Some(_)) => {
+                return unsupported("LIMIT and FETCH cannot be combined in the same query")
+            },
+            (Some(limit), None) => Fetch {
+                with_ties: false,
+                percent: false,
+                quantity: Some(limit.clone()),
+            },
+            (None, Some(fetch)) => fetch.clone(),
+            (None, None) => return Ok(()),
+        };
+        self.write_new_line()?;
+        self.write_prefix()?;
+        self.write_fetch(&fetch)
+    }
+
+    fn write_set_operator(&mut self, node: &SetOperator) -> Res<()> {
+        match node {
+            SetOperator::Except => self.write_keyword("MINUS"),
+            _ => generic::write_set_operator(self, node),
+        }
+    }
+
+    fn write_data_type(&mut self, node: &DataType) -> Res<()> {
+        if !self.out.translate_types {
+            return generic::write_data_type(self, node);
+        }
+        match node {
+            DataType::Varchar(size) => {
+                self.write_keyword(&format!("VARCHAR2({})", size.unwrap_or(4000)))
+            },
+            DataType::Text | DataType::Clob(_) => self.write_keyword("CLOB"),
+            DataType::Uuid => self.write_keyword("VARCHAR2(36)"),
+            DataType::Binary(size) | DataType::Varbinary(size) => {
+                self.write_keyword(&format!("RAW({})", size))
+            },
+            DataType::Blob(_) | DataType::Bytea => self.write_keyword("BLOB"),
+            DataType::Decimal(precision, scale) => {
+                self.write_keyword("NUMBER")?;
+                if let Some(precision) = precision {
+                    self.write(&format!("({}", precision))?;
+                    if let Some(scale) = scale {
+                        self.write(&format!(", {}", scale))?;
+                    }
+                    self.write(")")?;
+                }
+                Ok(())
+            },
+            DataType::SmallInt => self.write_keyword("NUMBER(5)"),
+            DataType::Int => self.write_keyword("NUMBER(10)"),
+            DataType::BigInt => self.write_keyword("NUMBER(19)"),
+            DataType::Real => self.write_keyword("BINARY_FLOAT"),
+            DataType::Double => self.write_keyword("BINARY_DOUBLE"),
+            DataType::Boolean => self.write_keyword("NUMBER(1)"),
+            DataType::Time
+            | DataType::Interval
+            | DataType::Regclass
+            | DataType::Array(_) => {
+                unsupported(format!("Oracle has no {:?} data type", node))
+            },
+            DataType::Custom(name) => {
+                let last = name.0.last().map(|n| n.to_uppercase()).unwrap_or_default();
+                match last.as_str() {
+                    "NVARCHAR" => self.write_keyword("NVARCHAR2(2000)"),
+                    "NTEXT" => self.write_keyword("NCLOB"),
+                    "TINYINT" => self.write_keyword("NUMBER(3)"),
+                    "BIT" => self.write_keyword("NUMBER(1)"),
+                    "DATETIME" | "DATETIME2" | "SMALLDATETIME" => self.write_keyword("TIMESTAMP"),
+                    "DATETIMEOFFSET" => self.write_keyword("TIMESTAMP WITH TIME ZONE"),
+                    "UNIQUEIDENTIFIER" => self.write_keyword("VARCHAR2(36)"),
+                    "MONEY" => self.write_keyword("NUMBER(19, 4)"),
+                    _ => write_bare_name(self, name),
+                }
+            },
+            _ => generic::write_data_type(self, node),
+        }
+    }
+
+    fn write_value(&mut self, node: &Value) -> Res<()> {
+        match node {
+            Value::SingleQuotedString(s) | Value::NationalStringLiteral(s) if s.is_empty() => {
+                if !self.out.is_measuring() {
+                    self.out.warnings.push("empty string literal will be treated as NULL by Oracle".to_string());
+                }
+                generic::write_value(self, node)
+            },
+            Value::Boolean(true) => self.write("1"),
+            Value::Boolean(false) => self.write("0"),
+            _ => generic::write_value(self, node),
+        }
+    }
+}
+
+impl<W> OracleWriter<W>
+where W: Write {
+    fn write_rownum_source(&mut self, inner: &Query) -> Res<()> {
+        self.write_keyword("FROM (")?;
+        self.indent();
+        self.write_new_line()?;
+        self.write_prefix()?;
+        generic::write_query(self, inner)?;
+        self.dedent();
+        self.write_new_line()?;
+        self.write_prefix()?;
+        self.write(")")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn select(from: Vec<TableWithJoins>, projection: Vec<SelectItem>) -> Query {
+        Query {
+            ctes: vec![],
+            body: SetExpr::Select(Box::new(Select {
+                distinct: false,
+                projection,
+                from,
+                selection: None,
+                group_by: vec![],
+                having: None,
+            })),
+            order_by: vec![],
+            limit: None,
+            offset: None,
+            fetch: None,
+        }
+    }
+
+    #[test]
+    fn dual_and_empty_strings() {
+        let q = select(vec![], vec![
+            SelectItem::UnnamedExpr(Expr::Value(Value::SingleQuotedString(String::new()))),
+        ]);
+        let mut w = OracleWriter::new("    ", Vec::new());
+        w.write_query(&q).unwrap();
+        assert_eq!(w.warnings().len(), 1);
+        let out = String::from_utf8(w.into_inner()).unwrap();
+        assert_eq!(out, "SELECT ''
+FROM DUAL")
+    }
+
+    #[test]
+    fn rownum_paging() {
+        let mut q = select(vec![TableWithJoins {
+            relation: TableFactor::Table {
+                name: ObjectName(vec!["orders".to_string()]),
+                alias: Some(TableAlias {
+                    name: "o".to_string(),
+                    columns: vec![],
+                }),
+                args: vec![],
+                with_hints: vec![],
+            },
+            joins: vec![],
+        }], vec![SelectItem::UnnamedExpr(Expr::CompoundIdentifier(vec!["o".to_string(), "id".to_string()]))]);
+        q.limit = Some(Expr::Value(Value::Number("5".to_string())));
+        q.offset = Some(Expr::Value(Value::Number("10".to_string())));
+        let mut w = OracleWriter::new("    ", Vec::new()).rownum_paging(true);
+        w.write_query(&q).unwrap();
+        let out = String::from_utf8(w.into_inner()).unwrap();
+        assert_eq!(out, "SELECT *
+FROM (
+    SELECT paged_.*, ROWNUM AS rownum_
+    FROM (
+        SELECT o.id
+        FROM orders o
+    ) paged_
+    WHERE ROWNUM <= 10 + 5
+)
+WHERE rownum_ > 10")
+    }
+}
@@ -0,0 +1,376 @@
+use crate::{comments::{CommentQueue, Placement}, Comments, FormatOptions, FunctionMap, Res};
+use std::io::Write;
+
+/// The builder methods every dialect writer shares, for a
+/// writer that keeps its state in an `out: Output<W>` field
+macro_rules! writer_builders {
+    () => {
+        /// Translate function calls with the provided map
+        pub fn functions(mut self, map: $crate::FunctionMap) -> Self {
+            self.out.functions = Some(map);
+            self
+        }
+
+        /// Map data types onto the ones this dialect prefers,
+        /// on unless the source was written for it already
+        pub fn translate_types(mut self, translate: bool) -> Self {
+            self.out.translate_types = translate;
+            self
+        }
+
+        /// Break long lists and boolean chains onto indented
+        /// lines once they would run past `width` characters
+        pub fn max_width(mut self, width: usize) -> Self {
+            self.out.options.max_width = Some(width);
+            self
+        }
+
+        /// Replace every layout option, including the indent
+        /// prefix passed to `new`
+        pub fn options(mut self, options: $crate::FormatOptions) -> Self {
+            self.out.options = options;
+            self
+        }
+
+        /// Write the comments of the source being regenerated
+        /// next to the words they were attached to
+        pub fn comments(mut self, comments: $crate::Comments) -> Self {
+            self.out.set_comments(comments);
+            self
+        }
+
+        /// Anything written that will behave differently on
+        /// this dialect than on the one the source was written for
+        pub fn warnings(&self) -> &[String] {
+            &self.out.warnings
+        }
+
+        pub fn into_inner(self) -> W {
+            self.out.into_inner()
+        }
+    };
+}
+
+/// The output primitives of `SqlWriter`, all answered
+/// by the `out: Output<W>` field of a dialect writer
+macro_rules! output_primitives {
+    () => {
+        fn write(&mut self, s: &str) -> $crate::Res<()> {
+            self.out.write(s)
+        }
+        fn write_new_line(&mut self) -> $crate::Res<()> {
+            self.out.write_new_line()
+        }
+        fn write_prefix(&mut self) -> $crate::Res<()> {
+            self.out.write_prefix()
+        }
+        fn indent(&mut self) {
+            self.out.indent += 1;
+        }
+        fn dedent(&mut self) {
+            self.out.indent -= 1;
+        }
+        fn remaining_width(&self) -> Option<usize> {
+            self.out.remaining_width()
+        }
+        fn start_measure(&mut self) {
+            self.out.start_measure()
+        }
+        fn end_measure(&mut self) -> Option<usize> {
+            self.out.end_measure()
+        }
+        fn flush_comments(&mut self) -> $crate::Res<()> {
+            self.out.flush_comments()
+        }
+        fn options(&self) -> &$crate::FormatOptions {
+            &self.out.options
+        }
+        fn function_map(&self) -> Option<&$crate::FunctionMap> {
+            self.out.functions.as_ref()
+        }
+    };
+}
+
+pub(crate) use {output_primitives, writer_builders};
+
+/// The output state shared by every dialect writer,
+/// tracks the current indentation level and the
+/// length of the line currently being written
+pub(crate) struct Output<W> {
+    pub indent: usize,
+    pub options: FormatOptions,
+    pub functions: Option<FunctionMap>,
+    /// Map data types onto the ones the dialect prefers, off
+    /// when the source was written for the dialect already
+    pub translate_types: bool,
+    /// Anything written that will behave differently on
+    /// this dialect than on the one the source was written for
+    pub warnings: Vec<String>,
+    pub current_line_len: usize,
+    measures: Vec<Measure>,
+    comments: Option<CommentQueue>,
+    /// Something other than indentation is on the current line
+    line_has_text: bool,
+    /// A line comment was written, the next text needs a new line
+    break_line: bool,
+    minified: Minified,
+    writer: W,
+}
+
+/// An in progress measurement, `start` is the line
+/// length when it began and `broke` is set if a new
+/// line was written before it ended
+struct Measure {
+    start: usize,
+    broke: bool,
+}
+
+/// What `FormatOptions::minify` needs to know about the
+/// text already written to decide if a space is required
+#[derive(Default)]
+struct Minified {
+    last: Option<char>,
+    /// Whitespace was skipped since `last`
+    space: bool,
+    /// The closing quote of the literal or identifier being written
+    quote: Option<char>,
+}
+
+impl<W> Output<W>
+where W: Write {
+    pub fn new(prefix: &'static str, writer: W) -> Self {
+        Self {
+            indent: 0,
+            options: FormatOptions {
+                indent: prefix.into(),
+                ..FormatOptions::new()
+            },
+            functions: None,
+            translate_types: true,
+            warnings: Vec::new(),
+            current_line_len: 0,
+            measures: Vec::new(),
+            comments: None,
+            line_has_text: false,
+            break_line: false,
+            minified: Minified::default(),
+            writer,
+        }
+    }
+
+    pub fn write_new_line(&mut self) -> Res<()> {
+        if self.options.minify {
+            self.minified.space = true;
+            return Ok(());
+        }
+        self.current_line_len = 0;
+        if let Some(measure) = self.measures.last_mut() {
+            measure.broke = true;
+            return Ok(());
+        }
+        self.line_has_text = false;
+        self.break_line = false;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    pub fn write_prefix(&mut self) -> Res<()> {
+        if self.options.minify {
+            return Ok(());
+        }
+        for _ in 0..self.indent {
+            self.current_line_len += self.options.indent.chars().count();
+            if !self.is_measuring() {
+                self.writer.write_all(self.options.indent.as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write(&mut self, s: &str) -> Res<()> {
+        if self.is_measuring() {
+            self.current_line_len += s.chars().count();
+            return Ok(());
+        }
+        let mut s = s;
+        if self.break_line && !s.trim().is_empty() {
+            self.write_new_line()?;
+            self.write_prefix()?;
+            s = s.trim_start();
+        }
+        let pieces = match &mut self.comments {
+            Some(queue) if !self.options.minify => queue.place(s),
+            _ => return self.write_raw(s),
+        };
+        for (text, before, after) in pieces {
+            for comment in before {
+                self.write_comment(comment)?;
+            }
+            let mut text = text.as_str();
+            if self.break_line {
+                // a line comment was written mid chunk, the
+                // blanks that followed it in the chunk go
+                text = text.trim_start();
+                if !text.is_empty() {
+                    self.write_new_line()?;
+                    self.write_prefix()?;
+                }
+            }
+            self.write_raw(text)?;
+            for comment in after {
+                self.write_comment(comment)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Re-emit the comments of `comments` as the words
+    /// they were attached to are written
+    pub fn set_comments(&mut self, comments: Comments) {
+        self.comments = Some(CommentQueue::new(comments));
+    }
+
+    /// Write every comment that has not been written yet
+    pub fn flush_comments(&mut self) -> Res<()> {
+        let remaining = match &mut self.comments {
+            Some(queue) if !self.options.minify => queue.remaining(),
+            _ => return Ok(()),
+        };
+        for comment in remaining {
+            self.write_comment(comment)?;
+        }
+        Ok(())
+    }
+
+    fn write_comment(&mut self, comment: Placement) -> Res<()> {
+        let line_comment = comment.is_line_comment();
+        match comment {
+            Placement::LineBefore(text) => {
+                if self.line_has_text {
+                    self.write_new_line()?;
+                    self.write_prefix()?;
+                }
+                self.write_raw(&text)?;
+                self.write_new_line()?;
+                self.write_prefix()?;
+            },
+            Placement::InlineBefore(text) => {
+                self.write_raw(&text)?;
+                if line_comment {
+                    self.write_new_line()?;
+                    self.write_prefix()?;
+                } else {
+                    self.write_raw(" ")?;
+                }
+            },
+            Placement::InlineAfter(text) => {
+                self.write_raw(" ")?;
+                self.write_raw(&text)?;
+                self.break_line = line_comment;
+            },
+            Placement::LineAfter(text) => {
+                if self.line_has_text {
+                    self.write_new_line()?;
+                    self.write_prefix()?;
+                }
+                self.write_raw(&text)?;
+                self.break_line = true;
+            },
+        }
+        Ok(())
+    }
+
+    fn write_raw(&mut self, s: &str) -> Res<()> {
+        if self.options.minify {
+            return self.write_minified(s);
+        }
+        self.current_line_len += s.chars().count();
+        if !s.trim().is_empty() {
+            self.line_has_text = true;
+        }
+        self.writer.write_all(s.as_bytes())?;
+        Ok(())
+    }
+
+    /// Drop whitespace unless the characters on either
+    /// side of it would otherwise run together
+    fn write_minified(&mut self, s: &str) -> Res<()> {
+        let mut out = String::with_capacity(s.len());
+        for c in s.chars() {
+            if let Some(close) = self.minified.quote {
+                if c == close {
+                    self.minified.quote = None;
+                }
+                out.push(c);
+                self.minified.last = Some(c);
+                continue;
+            }
+            if c.is_whitespace() {
+                self.minified.space = self.minified.last.is_some();
+                continue;
+            }
+            if self.minified.space && self.minified.last.map(|last| needs_space(last, c)).unwrap_or(false) {
+                out.push(' ');
+            }
+            self.minified.quote = match c {
+                '\'' | '"' | '`' => Some(c),
+                '[' => Some(']'),
+                _ => None,
+            };
+            self.minified.space = false;
+            self.minified.last = Some(c);
+            out.push(c);
+        }
+        self.current_line_len += out.chars().count();
+        self.writer.write_all(out.as_bytes())?;
+        Ok(())
+    }
+
+    /// The characters left on the current line before
+    /// `max_width` is reached
+    pub fn remaining_width(&self) -> Option<usize> {
+        if self.options.minify {
+            return None;
+        }
+        self.options.max_width.map(|max| max.saturating_sub(self.current_line_len))
+    }
+
+    /// Stop writing to `writer`, everything written until the
+    /// matching `end_measure` only counts towards its width
+    pub fn start_measure(&mut self) {
+        self.measures.push(Measure {
+            start: self.current_line_len,
+            broke: false,
+        });
+    }
+
+    /// The width written since the last `start_measure`,
+    /// `None` if that included a new line
+    pub fn end_measure(&mut self) -> Option<usize> {
+        let measure = self.measures.pop()?;
+        let end = std::mem::replace(&mut self.current_line_len, measure.start);
+        if measure.broke {
+            None
+        } else {
+            Some(end - measure.start)
+        }
+    }
+
+    pub fn is_measuring(&self) -> bool {
+        !self.measures.is_empty()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+fn needs_space(last: char, next: char) -> bool {
+    let word = |c: char| c.is_alphanumeric() || matches!(c, '_' | '@' | '#' | '$' | '?');
+    let quote = |c: char| matches!(c, '\'' | '"' | '`' | '[' | ']');
+    (word(last) && (word(next) || quote(next)))
+        || (quote(last) && word(next))
+        // `--` and `/*` would start a comment
+        || (last == '-' && next == '-')
+        || (last == '/' && next == '*')
+}
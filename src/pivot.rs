@@ -0,0 +1,573 @@
+//! T-SQL's `PIVOT` and `UNPIVOT` table operators, which sqlparser
+//! 0.5 cannot read. They turn the table source to their left in
+//! the `FROM`, so before parsing each is spelled as a join onto
+//! that source, `CROSS APPLY PIVOT(aggregate, column, value, ...) AS p`,
+//! and `Pivot::from_join` reads the join back.
+//!
+//! T-SQL writes the operator again, the other dialects get the
+//! query it stands for: conditional aggregation for `PIVOT` and
+//! a `UNION ALL` per column for `UNPIVOT`. Both group by the
+//! source's other columns, which have to be known from the
+//! source subquery or the columns the outer query selects.
+use crate::{
+    error::unsupported,
+    rewrite::Walk,
+    tsql::{is_word, TsqlStatement},
+    unquote_ident, write_separated_idents, Res, SqlWriter,
+};
+use sqlparser::{
+    ast::*,
+    parser::ParserError,
+    tokenizer::{Token, Whitespace, Word},
+};
+use std::cell::RefCell;
+
+/// A `PIVOT` or `UNPIVOT` and the alias of the table it produces
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pivot {
+    pub operator: PivotOperator,
+    pub alias: TableAlias,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PivotOperator {
+    /// `PIVOT (aggregate FOR column IN ([value], ...))`, a column
+    /// per value holding the aggregate of the rows with that value
+    Pivot {
+        aggregate: Function,
+        column: Ident,
+        values: Vec<Ident>,
+    },
+    /// `UNPIVOT (value FOR name IN (column, ...))`, a row per column
+    /// holding its name and its value
+    Unpivot {
+        value: Ident,
+        name: Ident,
+        columns: Vec<Ident>,
+    },
+}
+
+impl Pivot {
+    /// The pivot a join from `parse_tsql` stands for
+    pub fn from_join(join: &Join) -> Option<Self> {
+        let (name, args, alias) = match (&join.join_operator, &join.relation) {
+            (JoinOperator::CrossApply, TableFactor::Table { name, args, alias: Some(alias), with_hints })
+                if name.0.len() == 1 && with_hints.is_empty() && args.len() > 2 => (&name.0[0], args, alias),
+            _ => return None,
+        };
+        let idents = |args: &[Expr]| {
+            args.iter()
+                .map(|arg| match arg {
+                    Expr::Identifier(id) => Some(id.clone()),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()
+        };
+        let operator = match (name.as_str(), &args[0]) {
+            ("PIVOT", Expr::Function(aggregate)) => {
+                let mut rest = idents(&args[1..])?;
+                PivotOperator::Pivot {
+                    aggregate: aggregate.clone(),
+                    column: rest.remove(0),
+                    values: rest,
+                }
+            },
+            ("UNPIVOT", _) => {
+                let mut idents = idents(args)?;
+                let columns = idents.split_off(2);
+                let name = idents.pop()?;
+                PivotOperator::Unpivot {
+                    value: idents.pop()?,
+                    name,
+                    columns,
+                }
+            },
+            _ => return None,
+        };
+        Some(Self {
+            operator,
+            alias: alias.clone(),
+        })
+    }
+
+    /// The join `parse_tsql` spells this pivot with
+    pub fn to_join(&self) -> Join {
+        let ident = |id: &Ident| Expr::Identifier(id.clone());
+        let (name, args) = match &self.operator {
+            PivotOperator::Pivot { aggregate, column, values } => {
+                let args = vec![Expr::Function(aggregate.clone()), ident(column)];
+                ("PIVOT", args.into_iter().chain(values.iter().map(ident)).collect())
+            },
+            PivotOperator::Unpivot { value, name, columns } => {
+                let args = vec![ident(value), ident(name)];
+                ("UNPIVOT", args.into_iter().chain(columns.iter().map(ident)).collect())
+            },
+        };
+        Join {
+            relation: TableFactor::Table {
+                name: ObjectName(vec![name.to_string()]),
+                alias: Some(self.alias.clone()),
+                args,
+                with_hints: vec![],
+            },
+            join_operator: JoinOperator::CrossApply,
+        }
+    }
+}
+
+/// Whether a join is a pivot from `parse_tsql`, `PIVOT` and
+/// `UNPIVOT` are reserved so no function has either name
+pub(crate) fn is_pivot(join: &Join) -> bool {
+    match (&join.join_operator, &join.relation) {
+        (JoinOperator::CrossApply, TableFactor::Table { name, args, .. }) => {
+            !args.is_empty() && name.0.len() == 1 && (name.0[0] == "PIVOT" || name.0[0] == "UNPIVOT")
+        },
+        _ => false,
+    }
+}
+
+/// Replace `PIVOT (a FOR b IN (c, ...))` with `CROSS APPLY PIVOT(a, b, c, ...)`
+/// and the same for `UNPIVOT`
+pub(crate) fn mark_pivot(tokens: Vec<Token>) -> Vec<Token> {
+    let mut marked = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        let operator = ["PIVOT", "UNPIVOT"].iter().find(|op| is_word(Some(tokens[i].clone()), op));
+        match operator.and_then(|op| pivot_clause(&tokens, i + 1).map(|clause| (op, clause))) {
+            Some((op, (parts, end))) => {
+                for word in &["CROSS", "APPLY"] {
+                    marked.push(keyword(word));
+                    marked.push(Token::Whitespace(Whitespace::Space));
+                }
+                marked.push(keyword(op));
+                marked.push(Token::LParen);
+                for (n, part) in parts.iter().enumerate() {
+                    if n > 0 {
+                        marked.push(Token::Comma);
+                    }
+                    marked.extend_from_slice(part);
+                }
+                marked.push(Token::RParen);
+                i = end;
+            },
+            None => {
+                marked.push(tokens[i].clone());
+                i += 1;
+            },
+        }
+    }
+    marked
+}
+
+/// The three parts of `(a FOR b IN (c, ...))` starting at `start`
+/// and the index after it
+fn pivot_clause(tokens: &[Token], start: usize) -> Option<([&[Token]; 3], usize)> {
+    let open = skip_whitespace(tokens, start);
+    if tokens.get(open) != Some(&Token::LParen) {
+        return None;
+    }
+    let close = closing_paren(tokens, open)?;
+    let top_level = |word: &str, from: usize| {
+        let mut depth = 0;
+        (from..close).find(|&i| {
+            match &tokens[i] {
+                Token::LParen => depth += 1,
+                Token::RParen => depth -= 1,
+                token => return depth == 0 && is_word(Some(token.clone()), word),
+            }
+            false
+        })
+    };
+    let for_word = top_level("FOR", open + 1)?;
+    let in_word = top_level("IN", for_word + 1)?;
+    let list = skip_whitespace(tokens, in_word + 1);
+    if tokens.get(list) != Some(&Token::LParen) {
+        return None;
+    }
+    let list_close = closing_paren(tokens, list)?;
+    if skip_whitespace(tokens, list_close + 1) != close {
+        return None;
+    }
+    let parts = [&tokens[open + 1..for_word], &tokens[for_word + 1..in_word], &tokens[list + 1..list_close]];
+    Some((parts, close + 1))
+}
+
+fn closing_paren(tokens: &[Token], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().skip(open) {
+        match token {
+            Token::LParen => depth += 1,
+            Token::RParen if depth == 1 => return Some(i),
+            Token::RParen => depth -= 1,
+            _ => (),
+        }
+    }
+    None
+}
+
+fn skip_whitespace(tokens: &[Token], mut i: usize) -> usize {
+    while let Some(Token::Whitespace(_)) = tokens.get(i) {
+        i += 1;
+    }
+    i
+}
+
+fn keyword(word: &str) -> Token {
+    Token::Word(Word {
+        value: word.to_string(),
+        quote_style: None,
+        keyword: word.to_string(),
+    })
+}
+
+/// Check that every pivot `mark_pivot` left reads back as one
+pub(crate) fn check_pivots(stmts: &mut [TsqlStatement]) -> Result<(), ParserError> {
+    let pass = PivotCheck { error: RefCell::new(None) };
+    for stmt in stmts {
+        pass.tsql_statement(stmt);
+    }
+    match pass.error.into_inner() {
+        Some(error) => Err(ParserError::ParserError(error)),
+        None => Ok(()),
+    }
+}
+
+struct PivotCheck {
+    error: RefCell<Option<String>>,
+}
+
+impl Walk for PivotCheck {
+    fn visit_table_with_joins(&self, table: &mut TableWithJoins) {
+        let invalid = table.joins.iter().any(|join| is_pivot(join) && Pivot::from_join(join).is_none());
+        if invalid {
+            self.error.borrow_mut().get_or_insert_with(|| {
+                "Expected PIVOT (aggregate FOR column IN ([value], ...)) AS alias \
+                 or UNPIVOT (value FOR name IN (column, ...)) AS alias".to_string()
+            });
+        }
+    }
+}
+
+/// T-SQL, the operator on a line of its own after its source
+pub(crate) fn write_pivot<S>(w: &mut S, node: &Pivot) -> Res<()>
+where S: SqlWriter + ?Sized {
+    match &node.operator {
+        PivotOperator::Pivot { aggregate, column, values } => {
+            w.write_keyword("PIVOT (")?;
+            w.write_function(aggregate)?;
+            w.write_keyword(" FOR ")?;
+            w.write_ident(column)?;
+            w.write_keyword(" IN (")?;
+            write_separated_idents(w, ", ", values)?;
+        },
+        PivotOperator::Unpivot { value, name, columns } => {
+            w.write_keyword("UNPIVOT (")?;
+            w.write_ident(value)?;
+            w.write_keyword(" FOR ")?;
+            w.write_ident(name)?;
+            w.write_keyword(" IN (")?;
+            write_separated_idents(w, ", ", columns)?;
+        },
+    }
+    w.write("))")?;
+    w.write_table_factor_alias(&node.alias)
+}
+
+pub(crate) fn unsupported_pivot<T>() -> Res<T> {
+    unsupported("PIVOT and UNPIVOT are only written for T-SQL, other dialects need the columns of their source to expand them")
+}
+
+/// Replace every pivot in the `FROM` of a select with the
+/// subquery it stands for, where the columns to group by are known
+pub(crate) fn expand_pivots(select: &mut Select) {
+    for table in &mut select.from {
+        while let Some(i) = table.joins.iter().position(is_pivot) {
+            let pivot = match Pivot::from_join(&table.joins[i]) {
+                Some(pivot) => pivot,
+                None => break,
+            };
+            let source = if i == 0 {
+                table.relation.clone()
+            } else {
+                TableFactor::NestedJoin(Box::new(TableWithJoins {
+                    relation: table.relation.clone(),
+                    joins: table.joins[..i].to_vec(),
+                }))
+            };
+            let body = match expand(&pivot, source, &select.projection) {
+                Some(body) => body,
+                None => break,
+            };
+            table.relation = TableFactor::Derived {
+                lateral: false,
+                subquery: Box::new(query(body)),
+                alias: Some(pivot.alias),
+            };
+            table.joins.drain(..=i);
+        }
+    }
+}
+
+fn expand(pivot: &Pivot, source: TableFactor, projection: &[SelectItem]) -> Option<SetExpr> {
+    match &pivot.operator {
+        PivotOperator::Pivot { aggregate, column, values } => {
+            if aggregate.args.len() != 1 {
+                return None;
+            }
+            let mut pivoted = vec![column.clone()];
+            pivoted.extend(column_name(&aggregate.args[0]));
+            let groups = group_columns(&source, projection, &pivot.alias, &pivoted, values)?;
+            let mut items = group_items(&groups);
+            for value in values {
+                let condition = Expr::BinaryOp {
+                    left: Box::new(Expr::Identifier(column.clone())),
+                    op: BinaryOperator::Eq,
+                    right: Box::new(literal(value)),
+                };
+                let expr = Expr::Function(Function {
+                    args: vec![Expr::Case {
+                        operand: None,
+                        conditions: vec![condition],
+                        results: vec![aggregate.args[0].clone()],
+                        else_result: None,
+                    }],
+                    ..aggregate.clone()
+                });
+                items.push(SelectItem::ExprWithAlias { expr, alias: value.clone() });
+            }
+            Some(select(items, source, None, groups.into_iter().map(Expr::Identifier).collect()))
+        },
+        PivotOperator::Unpivot { value, name, columns } => {
+            let produced = [value.clone(), name.clone()];
+            let groups = group_columns(&source, projection, &pivot.alias, columns, &produced)?;
+            columns.iter()
+                .map(|column| {
+                    let mut items = group_items(&groups);
+                    items.push(SelectItem::ExprWithAlias { expr: literal_string(column), alias: name.clone() });
+                    items.push(SelectItem::ExprWithAlias { expr: Expr::Identifier(column.clone()), alias: value.clone() });
+                    let not_null = Expr::IsNotNull(Box::new(Expr::Identifier(column.clone())));
+                    select(items, source.clone(), Some(not_null), vec![])
+                })
+                .fold(None, |left, right| match left {
+                    None => Some(right),
+                    Some(left) => Some(SetExpr::SetOperation {
+                        op: SetOperator::Union,
+                        all: true,
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    }),
+                })
+        },
+    }
+}
+
+/// The columns of the source a pivot keeps, the ones a source
+/// subquery selects or else the ones the outer query selects
+/// from the pivot, less the columns the pivot consumes and
+/// the ones it produces
+fn group_columns(
+    source: &TableFactor,
+    projection: &[SelectItem],
+    alias: &TableAlias,
+    consumed: &[Ident],
+    produced: &[Ident],
+) -> Option<Vec<Ident>> {
+    let named = |items: &[SelectItem], alias: Option<&TableAlias>| {
+        items.iter()
+            .map(|item| match item {
+                SelectItem::UnnamedExpr(expr) => Some(column_of(expr, alias)),
+                SelectItem::ExprWithAlias { alias: name, .. } if alias.is_none() => Some(Some(name.clone())),
+                SelectItem::ExprWithAlias { expr, .. } => Some(column_of(expr, alias)),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+    };
+    let derived = match source {
+        TableFactor::Derived { subquery, .. } => match &subquery.body {
+            SetExpr::Select(s) => named(&s.projection, None),
+            _ => None,
+        },
+        _ => None,
+    };
+    let (columns, excluded) = match derived {
+        Some(columns) => (columns, consumed),
+        None => (named(projection, Some(alias))?, produced),
+    };
+    let mut groups: Vec<Ident> = Vec::new();
+    for column in columns.into_iter().flatten() {
+        let known = |ids: &[Ident]| ids.iter().any(|id| same_name(id, &column));
+        if !known(excluded) && !known(consumed) && !known(&groups) {
+            groups.push(column);
+        }
+    }
+    Some(groups)
+}
+
+/// The column an expression names, from any table when `alias` is
+/// `None` or else only when it is unqualified or qualified by `alias`
+fn column_of(expr: &Expr, alias: Option<&TableAlias>) -> Option<Ident> {
+    match (expr, alias) {
+        (Expr::Identifier(id), _) => Some(id.clone()),
+        (Expr::CompoundIdentifier(parts), None) => parts.last().cloned(),
+        (Expr::CompoundIdentifier(parts), Some(alias)) if parts.len() == 2 && same_name(&parts[0], &alias.name) => {
+            Some(parts[1].clone())
+        },
+        _ => None,
+    }
+}
+
+fn column_name(expr: &Expr) -> Option<Ident> {
+    column_of(expr, None)
+}
+
+fn same_name(left: &str, right: &str) -> bool {
+    bare(left).eq_ignore_ascii_case(bare(right))
+}
+
+fn bare(id: &str) -> &str {
+    unquote_ident(id).map(|(_, inner)| inner).unwrap_or(id)
+}
+
+/// The value of a pivoted column as the literal it is compared
+/// with, a number when it is all digits
+fn literal(value: &Ident) -> Expr {
+    let text = bare(value);
+    if !text.is_empty() && text.chars().all(|c| c.is_ascii_digit()) {
+        return Expr::Value(Value::Number(text.to_string()));
+    }
+    literal_string(value)
+}
+
+fn literal_string(id: &Ident) -> Expr {
+    Expr::Value(Value::SingleQuotedString(bare(id).to_string()))
+}
+
+fn group_items(groups: &[Ident]) -> Vec<SelectItem> {
+    groups.iter().map(|id| SelectItem::UnnamedExpr(Expr::Identifier(id.clone()))).collect()
+}
+
+fn select(projection: Vec<SelectItem>, source: TableFactor, selection: Option<Expr>, group_by: Vec<Expr>) -> SetExpr {
+    SetExpr::Select(Box::new(Select {
+        distinct: false,
+        projection,
+        from: vec![TableWithJoins {
+            relation: source,
+            joins: vec![],
+        }],
+        selection,
+        group_by,
+        having: None,
+    }))
+}
+
+fn query(body: SetExpr) -> Query {
+    Query {
+        ctes: vec![],
+        body,
+        order_by: vec![],
+        limit: None,
+        offset: None,
+        fetch: None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{MsSqlWriter, PostgresWriter};
+
+    fn ident(s: &str) -> Expr {
+        Expr::Identifier(s.to_string())
+    }
+
+    fn pivot_select(operator: PivotOperator, projection: &[&str]) -> Select {
+        let pivot = Pivot {
+            operator,
+            alias: TableAlias { name: "p".to_string(), columns: vec![] },
+        };
+        Select {
+            distinct: false,
+            projection: projection.iter().map(|s| SelectItem::UnnamedExpr(ident(s))).collect(),
+            from: vec![TableWithJoins {
+                relation: TableFactor::Table {
+                    name: ObjectName(vec!["sales".to_string()]),
+                    alias: None,
+                    args: vec![],
+                    with_hints: vec![],
+                },
+                joins: vec![pivot.to_join()],
+            }],
+            selection: None,
+            group_by: vec![],
+            having: None,
+        }
+    }
+
+    #[test]
+    fn pivot_to_aggregates() {
+        let operator = PivotOperator::Pivot {
+            aggregate: Function {
+                name: ObjectName(vec!["SUM".to_string()]),
+                args: vec![ident("amount")],
+                over: None,
+                distinct: false,
+            },
+            column: "quarter".to_string(),
+            values: vec!["[Q1]".to_string(), "[2]".to_string()],
+        };
+        let mut select = pivot_select(operator, &["region", "[Q1]", "[2]"]);
+        let mut w = MsSqlWriter::new("    ", Vec::new());
+        w.write_select(&select).unwrap();
+        assert_eq!(String::from_utf8(w.into_inner()).unwrap(), "SELECT region, [Q1], [2]
+FROM sales
+PIVOT (SUM(amount) FOR quarter IN ([Q1], [2])) AS p");
+        let mut w = PostgresWriter::new("    ", Vec::new());
+        assert!(w.write_select(&select).is_err());
+        expand_pivots(&mut select);
+        let mut w = PostgresWriter::new("    ", Vec::new());
+        w.write_select(&select).unwrap();
+        assert_eq!(String::from_utf8(w.into_inner()).unwrap(), "SELECT region, \"Q1\", \"2\"
+FROM (
+    SELECT region, SUM(CASE WHEN quarter = 'Q1' THEN amount END) AS \"Q1\", SUM(CASE WHEN quarter = 2 THEN amount END) AS \"2\"
+    FROM sales
+    GROUP BY region
+) AS p");
+    }
+
+    #[test]
+    fn unpivot_to_union() {
+        let operator = PivotOperator::Unpivot {
+            value: "amount".to_string(),
+            name: "quarter".to_string(),
+            columns: vec!["q1".to_string(), "q2".to_string()],
+        };
+        let mut select = pivot_select(operator, &["region", "quarter", "amount"]);
+        let mut w = MsSqlWriter::new("    ", Vec::new());
+        w.write_select(&select).unwrap();
+        assert_eq!(String::from_utf8(w.into_inner()).unwrap(), "SELECT region, quarter, amount
+FROM sales
+UNPIVOT (amount FOR quarter IN (q1, q2)) AS p");
+        expand_pivots(&mut select);
+        let mut w = PostgresWriter::new("    ", Vec::new());
+        w.write_select(&select).unwrap();
+        assert_eq!(String::from_utf8(w.into_inner()).unwrap(), "SELECT region, quarter, amount
+FROM (
+    SELECT region, 'q1' AS quarter, q1 AS amount
+    FROM sales
+    WHERE q1 IS NOT NULL
+    UNION ALL
+    SELECT region, 'q2' AS quarter, q2 AS amount
+    FROM sales
+    WHERE q2 IS NOT NULL
+) AS p");
+        let mut select = pivot_select(PivotOperator::Unpivot {
+            value: "amount".to_string(),
+            name: "quarter".to_string(),
+            columns: vec!["q1".to_string()],
+        }, &[]);
+        select.projection = vec![SelectItem::Wildcard];
+        let before = select.clone();
+        expand_pivots(&mut select);
+        assert_eq!(select, before);
+    }
+}
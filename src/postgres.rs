@@ -0,0 +1,147 @@
+use crate::{dml, error::unsupported, generic, merge, output::{self, Output}, unquote_ident, write_bare_name, Merge, OutputClause, SqlWriter, Res};
+use std::io::Write;
+use sqlparser::ast::*;
+
+/// Writes SQL for PostgreSQL
+pub struct PostgresWriter<W> {
+    out: Output<W>,
+}
+
+impl<W> PostgresWriter<W>
+where W: Write {
+    pub fn new(prefix: &'static str, writer: W) -> Self {
+        Self {
+            out: Output::new(prefix, writer),
+        }
+    }
+
+    output::writer_builders!();
+}
+
+impl<W> SqlWriter for PostgresWriter<W>
+where W: Write {
+    output::output_primitives!();
+
+    fn write_ident(&mut self, id: &str) -> Res<()> {
+        match unquote_ident(id) {
+            Some((_, inner)) => self.write(&format!("\"{}\"", inner)),
+            None => self.write_unquoted_ident(id),
+        }
+    }
+
+    fn write_fetch(&mut self, node: &Fetch) -> Res<()> {
+        if node.percent {
+            return unsupported("PostgreSQL does not support FETCH ... PERCENT");
+        }
+        generic::write_fetch(self, node)
+    }
+
+    fn write_dml_output(&mut self, statement: &Statement, output: &OutputClause) -> Res<()> {
+        dml::write_returning(self, statement, output)
+    }
+
+    fn write_merge(&mut self, node: &Merge) -> Res<()> {
+        merge::write_on_conflict(self, node)
+    }
+
+    fn write_join(&mut self, node: &Join) -> Res<()> {
+        generic::write_lateral_join(self, node, false)
+    }
+
+    fn write_data_type(&mut self, node: &DataType) -> Res<()> {
+        if !self.out.translate_types {
+            return generic::write_data_type(self, node);
+        }
+        match node {
+            DataType::Clob(_) => self.write_keyword("TEXT"),
+            DataType::Binary(_) | DataType::Varbinary(_) | DataType::Blob(_) => self.write_keyword("BYTEA"),
+            DataType::Custom(name) => {
+                let last = name.0.last().map(|n| n.to_uppercase()).unwrap_or_default();
+                match last.as_str() {
+                    "NVARCHAR" => self.write_keyword("VARCHAR"),
+                    "NTEXT" => self.write_keyword("TEXT"),
+                    "TINYINT" => self.write_keyword("SMALLINT"),
+                    "BIT" => self.write_keyword("BOOLEAN"),
+                    "DATETIME" | "DATETIME2" | "SMALLDATETIME" => self.write_keyword("TIMESTAMP"),
+                    "DATETIMEOFFSET" => self.write_keyword("TIMESTAMPTZ"),
+                    "UNIQUEIDENTIFIER" => self.write_keyword("UUID"),
+                    "MONEY" => self.write_keyword("NUMERIC(19, 4)"),
+                    _ => write_bare_name(self, name),
+                }
+            },
+            _ => generic::write_data_type(self, node),
+        }
+    }
+
+    fn write_value(&mut self, node: &Value) -> Res<()> {
+        match node {
+            Value::NationalStringLiteral(s) => self.write(&generic::quote_string(s)),
+            Value::Boolean(true) => self.write_keyword("TRUE"),
+            Value::Boolean(false) => self.write_keyword("FALSE"),
+            _ => generic::write_value(self, node),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::MsSqlWriter;
+
+    #[test]
+    fn apply_as_lateral() {
+        let table = |name: &[&str], args: Vec<Expr>, alias: &str| TableFactor::Table {
+            name: ObjectName(name.iter().map(|s| s.to_string()).collect()),
+            alias: Some(TableAlias { name: alias.to_string(), columns: vec![] }),
+            args,
+            with_hints: vec![],
+        };
+        let subquery = Query {
+            ctes: vec![],
+            body: SetExpr::Select(Box::new(Select {
+                distinct: false,
+                projection: vec![SelectItem::UnnamedExpr(Expr::Identifier("total".to_string()))],
+                from: vec![],
+                selection: None,
+                group_by: vec![],
+                having: None,
+            })),
+            order_by: vec![],
+            limit: None,
+            offset: None,
+            fetch: None,
+        };
+        let from = TableWithJoins {
+            relation: table(&["people"], vec![], "p"),
+            joins: vec![
+                Join {
+                    relation: table(&["dbo", "orders_for"], vec![Expr::CompoundIdentifier(vec!["p".to_string(), "id".to_string()])], "o"),
+                    join_operator: JoinOperator::CrossApply,
+                },
+                Join {
+                    relation: TableFactor::Derived {
+                        lateral: false,
+                        subquery: Box::new(subquery),
+                        alias: Some(TableAlias { name: "last".to_string(), columns: vec![] }),
+                    },
+                    join_operator: JoinOperator::OuterApply,
+                },
+            ],
+        };
+        let mut w = MsSqlWriter::new("    ", Vec::new());
+        w.write_table_with_joins(&from).unwrap();
+        assert_eq!(String::from_utf8(w.into_inner()).unwrap(), "people AS p
+CROSS APPLY dbo.orders_for(p.id) AS o
+OUTER APPLY (
+    SELECT total
+) AS last");
+        let mut w = PostgresWriter::new("    ", Vec::new());
+        w.write_table_with_joins(&from).unwrap();
+        assert_eq!(String::from_utf8(w.into_inner()).unwrap(), "people AS p
+CROSS JOIN dbo.orders_for(p.id) AS o
+LEFT OUTER JOIN LATERAL (
+    SELECT total
+) AS last
+    ON TRUE");
+    }
+}
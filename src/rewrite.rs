@@ -0,0 +1,457 @@
+//! The AST rewrites `transpile` applies before handing
+//! statements to the target writer, these cover constructs
+//! that mean the same thing but are spelled with different
+//! nodes in different dialects
+use crate::{
+    merge::MergeAction,
+    pivot::expand_pivots,
+    tsql::{DeclarationKind, FunctionReturns, TsqlStatement},
+    Target,
+};
+use sqlparser::ast::*;
+
+pub(crate) fn rewrite_statement(stmt: &mut TsqlStatement, source: Option<Target>, target: Target) {
+    let rw = Rewriter { source, target };
+    rw.tsql_statement(stmt)
+}
+
+/// Walks every query and expression of a statement, children
+/// before their parents, calling the hooks along the way
+pub(crate) trait Walk {
+    /// Called for a query before any of its parts
+    fn enter_query(&self, _q: &mut Query) {}
+    /// Called for a query after all of its parts
+    fn leave_query(&self, _q: &mut Query) {}
+    fn visit_select(&self, _s: &mut Select) {}
+    fn visit_table_with_joins(&self, _t: &mut TableWithJoins) {}
+    /// A replacement for an expression whose children were
+    /// already walked
+    fn replace(&self, _expr: &Expr) -> Option<Expr> {
+        None
+    }
+    /// Walk an expression used as a condition rather than a value
+    fn condition(&self, expr: &mut Expr) {
+        self.expr(expr)
+    }
+
+    /// The statements and expressions of a T-SQL statement
+    fn tsql_statement(&self, stmt: &mut TsqlStatement) {
+        match stmt {
+            TsqlStatement::Statement(stmt) | TsqlStatement::Dml { statement: stmt, .. } => self.statement(stmt),
+            TsqlStatement::UpdateFrom { statement, from, .. } => {
+                self.statement(statement);
+                for table in from {
+                    self.table_with_joins(table);
+                }
+            },
+            TsqlStatement::Merge(merge) => {
+                self.table_factor(&mut merge.source);
+                self.condition(&mut merge.on);
+                for clause in &mut merge.clauses {
+                    if let Some(condition) = &mut clause.condition {
+                        self.condition(condition);
+                    }
+                    match &mut clause.action {
+                        MergeAction::Update(assignments) => {
+                            for assignment in assignments {
+                                self.expr(&mut assignment.value);
+                            }
+                        },
+                        MergeAction::Insert { values, .. } => {
+                            for value in values {
+                                self.expr(value);
+                            }
+                        },
+                        MergeAction::Delete => (),
+                    }
+                }
+            },
+            TsqlStatement::Declare(declarations) => {
+                for declaration in declarations {
+                    if let DeclarationKind::Scalar { default: Some(default), .. } = &mut declaration.kind {
+                        self.expr(default);
+                    }
+                }
+            },
+            TsqlStatement::SetVariable { value, .. } | TsqlStatement::Return(Some(value)) => self.expr(value),
+            TsqlStatement::If { condition, then, otherwise } => {
+                self.condition(condition);
+                self.tsql_statement(then);
+                if let Some(otherwise) = otherwise {
+                    self.tsql_statement(otherwise);
+                }
+            },
+            TsqlStatement::While { condition, body } => {
+                self.condition(condition);
+                self.tsql_statement(body);
+            },
+            TsqlStatement::TryCatch { body, handler } => {
+                for stmt in body.iter_mut().chain(handler.iter_mut()) {
+                    self.tsql_statement(stmt);
+                }
+            },
+            TsqlStatement::Block(body)
+            | TsqlStatement::CreateProcedure { body, .. }
+            | TsqlStatement::CreateTrigger { body, .. }
+            | TsqlStatement::CreateFunction { returns: FunctionReturns::Scalar { body, .. }, .. } => {
+                for stmt in body {
+                    self.tsql_statement(stmt);
+                }
+            },
+            TsqlStatement::SelectVariables { query, .. }
+            | TsqlStatement::CreateFunction { returns: FunctionReturns::InlineTable(query), .. } => self.query(query),
+            _ => (),
+        }
+    }
+
+    fn statement(&self, stmt: &mut Statement) {
+        match stmt {
+            Statement::Query(q) => self.query(q),
+            Statement::Insert { source, .. } => self.query(source),
+            Statement::Update { assignments, selection, .. } => {
+                for assignment in assignments {
+                    self.expr(&mut assignment.value);
+                }
+                if let Some(selection) = selection {
+                    self.condition(selection);
+                }
+            },
+            Statement::Delete { selection: Some(selection), .. } => self.condition(selection),
+            Statement::CreateView { query, .. } => self.query(query),
+            _ => (),
+        }
+    }
+
+    fn query(&self, q: &mut Query) {
+        self.enter_query(q);
+        for cte in &mut q.ctes {
+            self.query(&mut cte.query);
+        }
+        self.set_expr(&mut q.body);
+        for order_by in &mut q.order_by {
+            self.expr(&mut order_by.expr);
+        }
+        self.leave_query(q);
+    }
+
+    fn set_expr(&self, body: &mut SetExpr) {
+        match body {
+            SetExpr::Select(s) => self.select(s),
+            SetExpr::Query(q) => self.query(q),
+            SetExpr::SetOperation { left, right, .. } => {
+                self.set_expr(left);
+                self.set_expr(right);
+            },
+            SetExpr::Values(values) => {
+                for row in &mut values.0 {
+                    for expr in row {
+                        self.expr(expr);
+                    }
+                }
+            },
+        }
+    }
+
+    fn select(&self, s: &mut Select) {
+        self.visit_select(s);
+        for item in &mut s.projection {
+            match item {
+                SelectItem::UnnamedExpr(expr)
+                | SelectItem::ExprWithAlias { expr, .. } => self.expr(expr),
+                _ => (),
+            }
+        }
+        for table in &mut s.from {
+            self.table_with_joins(table);
+        }
+        if let Some(selection) = &mut s.selection {
+            self.condition(selection);
+        }
+        for expr in &mut s.group_by {
+            self.expr(expr);
+        }
+        if let Some(having) = &mut s.having {
+            self.condition(having);
+        }
+    }
+
+    fn table_with_joins(&self, table: &mut TableWithJoins) {
+        self.visit_table_with_joins(table);
+        self.table_factor(&mut table.relation);
+        for join in &mut table.joins {
+            self.table_factor(&mut join.relation);
+            match &mut join.join_operator {
+                JoinOperator::Inner(JoinConstraint::On(expr))
+                | JoinOperator::LeftOuter(JoinConstraint::On(expr))
+                | JoinOperator::RightOuter(JoinConstraint::On(expr))
+                | JoinOperator::FullOuter(JoinConstraint::On(expr)) => self.condition(expr),
+                _ => (),
+            }
+        }
+    }
+
+    fn table_factor(&self, factor: &mut TableFactor) {
+        match factor {
+            TableFactor::Table { args, .. } => {
+                for arg in args {
+                    self.expr(arg);
+                }
+            },
+            TableFactor::Derived { subquery, .. } => self.query(subquery),
+            TableFactor::NestedJoin(table) => self.table_with_joins(table),
+        }
+    }
+
+    /// Walk the children of an expression before
+    /// the expression itself
+    fn expr(&self, expr: &mut Expr) {
+        match expr {
+            Expr::IsNull(inner)
+            | Expr::IsNotNull(inner)
+            | Expr::UnaryOp { expr: inner, .. }
+            | Expr::Cast { expr: inner, .. }
+            | Expr::Extract { expr: inner, .. }
+            | Expr::Collate { expr: inner, .. }
+            | Expr::Nested(inner) => self.expr(inner),
+            Expr::InList { expr, list, .. } => {
+                self.expr(expr);
+                for item in list {
+                    self.expr(item);
+                }
+            },
+            Expr::InSubquery { expr, subquery, .. } => {
+                self.expr(expr);
+                self.query(subquery);
+            },
+            Expr::Between { expr, low, high, .. } => {
+                self.expr(expr);
+                self.expr(low);
+                self.expr(high);
+            },
+            Expr::BinaryOp { left, right, .. } => {
+                self.expr(left);
+                self.expr(right);
+            },
+            Expr::Function(f) => {
+                for arg in &mut f.args {
+                    self.expr(arg);
+                }
+                if let Some(over) = &mut f.over {
+                    for expr in &mut over.partition_by {
+                        self.expr(expr);
+                    }
+                    for order_by in &mut over.order_by {
+                        self.expr(&mut order_by.expr);
+                    }
+                }
+            },
+            Expr::Case { operand, conditions, results, else_result } => {
+                if let Some(operand) = operand {
+                    self.expr(operand);
+                }
+                for condition in conditions.iter_mut() {
+                    if operand.is_some() {
+                        self.expr(condition);
+                    } else {
+                        self.condition(condition);
+                    }
+                }
+                for result in results.iter_mut() {
+                    self.expr(result);
+                }
+                if let Some(else_result) = else_result {
+                    self.expr(else_result);
+                }
+            },
+            Expr::Exists(q) | Expr::Subquery(q) => self.query(q),
+            _ => (),
+        }
+        if let Some(replacement) = self.replace(expr) {
+            *expr = replacement;
+        }
+    }
+}
+
+struct Rewriter {
+    /// The dialect the statement was written for, when known
+    source: Option<Target>,
+    target: Target,
+}
+
+impl Walk for Rewriter {
+    fn leave_query(&self, q: &mut Query) {
+        if self.target == Target::MsSql {
+            limit_to_fetch(q);
+        }
+    }
+
+    fn visit_select(&self, s: &mut Select) {
+        if self.target != Target::MsSql {
+            expand_pivots(s);
+        }
+    }
+
+    fn condition(&self, expr: &mut Expr) {
+        if self.target == Target::MsSql || self.target == Target::Oracle {
+            boolean_to_predicate(expr);
+        }
+        self.expr(expr)
+    }
+
+    fn replace(&self, expr: &Expr) -> Option<Expr> {
+        match expr {
+            Expr::Value(Value::Boolean(b)) if self.target == Target::MsSql => {
+                let n = if *b { "1" } else { "0" };
+                Some(Expr::Value(Value::Number(n.to_string())))
+            },
+            Expr::BinaryOp { op: BinaryOperator::Plus, .. }
+                if self.source == Some(Target::MsSql) && self.target != Target::MsSql && is_string_expr(expr) => {
+                let mut args = Vec::new();
+                flatten_concat(expr, &mut args);
+                Some(self.concat(args))
+            },
+            _ => None,
+        }
+    }
+}
+
+impl Rewriter {
+    /// sqlparser has no `||` operator so concatenation is
+    /// written with the `CONCAT` function, which Oracle
+    /// limits to two arguments
+    fn concat(&self, mut args: Vec<Expr>) -> Expr {
+        if self.target == Target::Oracle && args.len() > 2 {
+            let first = args.remove(0);
+            let rest = self.concat(args);
+            return function("CONCAT", vec![first, rest]);
+        }
+        function("CONCAT", args)
+    }
+}
+
+/// T-SQL and Oracle have no boolean values, a literal that is
+/// a condition becomes a comparison that is always or never true
+/// and a column becomes a comparison with 1. The literals left
+/// behind are values and become a `BIT` or `NUMBER(1)`
+fn boolean_to_predicate(expr: &mut Expr) {
+    let number = |n: &str| Box::new(Expr::Value(Value::Number(n.to_string())));
+    match expr {
+        Expr::Value(Value::Boolean(b)) => {
+            *expr = Expr::BinaryOp {
+                left: number("1"),
+                op: BinaryOperator::Eq,
+                right: number(if *b { "1" } else { "0" }),
+            };
+        },
+        Expr::Identifier(_) | Expr::CompoundIdentifier(_) => {
+            *expr = Expr::BinaryOp {
+                left: Box::new(expr.clone()),
+                op: BinaryOperator::Eq,
+                right: number("1"),
+            };
+        },
+        Expr::BinaryOp { left, op: BinaryOperator::And, right }
+        | Expr::BinaryOp { left, op: BinaryOperator::Or, right } => {
+            boolean_to_predicate(left);
+            boolean_to_predicate(right);
+        },
+        Expr::UnaryOp { op: UnaryOperator::Not, expr } | Expr::Nested(expr) => boolean_to_predicate(expr),
+        _ => (),
+    }
+}
+
+/// T-SQL has no LIMIT, without an OFFSET it becomes a `FETCH`
+/// that is written as `TOP` on a plain `SELECT`. Otherwise the
+/// OFFSET/FETCH form requires an ORDER BY so one that keeps the
+/// existing order is added when missing
+fn limit_to_fetch(q: &mut Query) {
+    let limit = match q.limit.take() {
+        Some(limit) => limit,
+        None => return,
+    };
+    let fetch = Fetch {
+        with_ties: false,
+        percent: false,
+        quantity: Some(limit),
+    };
+    if q.offset.is_none() && matches!(q.body, SetExpr::Select(_)) {
+        q.fetch = Some(fetch);
+        return;
+    }
+    if q.order_by.is_empty() {
+        q.order_by.push(OrderByExpr {
+            expr: Expr::Subquery(Box::new(select_null())),
+            asc: None,
+        });
+    }
+    if q.offset.is_none() {
+        q.offset = Some(Expr::Value(Value::Number("0".to_string())));
+    }
+    q.fetch = Some(fetch);
+}
+
+fn select_null() -> Query {
+    Query {
+        ctes: vec![],
+        body: SetExpr::Select(Box::new(Select {
+            distinct: false,
+            projection: vec![SelectItem::UnnamedExpr(Expr::Value(Value::Null))],
+            from: vec![],
+            selection: None,
+            group_by: vec![],
+            having: None,
+        })),
+        order_by: vec![],
+        limit: None,
+        offset: None,
+        fetch: None,
+    }
+}
+
+/// Without type information a `+` is only known to be
+/// concatenation when one side is a string
+fn is_string_expr(expr: &Expr) -> bool {
+    match expr {
+        Expr::Value(Value::SingleQuotedString(_))
+        | Expr::Value(Value::NationalStringLiteral(_)) => true,
+        Expr::Nested(inner) => is_string_expr(inner),
+        Expr::BinaryOp { left, op: BinaryOperator::Plus, right } => {
+            is_string_expr(left) || is_string_expr(right)
+        },
+        Expr::Cast { data_type, .. } => match data_type {
+            DataType::Char(_) | DataType::Varchar(_) | DataType::Text | DataType::Clob(_) => true,
+            DataType::Custom(name) => is_named(name, "NVARCHAR") || is_named(name, "NCHAR"),
+            _ => false,
+        },
+        Expr::Function(f) => is_named(&f.name, "CONCAT"),
+        _ => false,
+    }
+}
+
+/// The operands of a T-SQL string `+`, a `+` among them that
+/// adds numbers stays a single operand
+fn flatten_concat(expr: &Expr, args: &mut Vec<Expr>) {
+    match expr {
+        Expr::BinaryOp { left, op: BinaryOperator::Plus, right } if is_string_expr(expr) => {
+            flatten_concat(left, args);
+            flatten_concat(right, args);
+        },
+        Expr::Function(f) if is_named(&f.name, "CONCAT") => {
+            args.extend(f.args.iter().cloned());
+        },
+        _ => args.push(expr.clone()),
+    }
+}
+
+fn is_named(name: &ObjectName, expected: &str) -> bool {
+    name.0.len() == 1 && name.0[0].eq_ignore_ascii_case(expected)
+}
+
+fn function(name: &str, args: Vec<Expr>) -> Expr {
+    Expr::Function(Function {
+        name: ObjectName(vec![name.to_string()]),
+        args,
+        over: None,
+        distinct: false,
+    })
+}
@@ -0,0 +1,242 @@
+use crate::{error::unsupported, generic, output::{self, Output}, write_clause, unquote_ident, write_bare_name, SqlWriter, Res};
+use std::io::Write;
+use sqlparser::{ast::*, dialect::keywords::ALL_KEYWORDS};
+
+/// Writes SQL for Snowflake.
+///
+/// Snowflake folds unquoted identifiers to upper case and
+/// treats quoted identifiers as case sensitive, so any
+/// identifier that was quoted in the source stays quoted
+/// (with `"`) unless `quoted_identifiers_ignore_case` is set,
+/// which mirrors the session parameter of the same name.
+pub struct SnowflakeWriter<W> {
+    out: Output<W>,
+    quoted_identifiers_ignore_case: bool,
+}
+
+impl<W> SnowflakeWriter<W>
+where W: Write {
+    pub fn new(prefix: &'static str, writer: W) -> Self {
+        Self {
+            out: Output::new(prefix, writer),
+            quoted_identifiers_ignore_case: false,
+        }
+    }
+
+    /// Upper case quoted identifiers, dropping the quotes
+    /// when they are no longer needed
+    pub fn quoted_identifiers_ignore_case(mut self, ignore: bool) -> Self {
+        self.quoted_identifiers_ignore_case = ignore;
+        self
+    }
+
+    output::writer_builders!();
+}
+
+impl<W> SqlWriter for SnowflakeWriter<W>
+where W: Write {
+    output::output_primitives!();
+
+    fn write_ident(&mut self, id: &str) -> Res<()> {
+        let (quote, inner) = match unquote_ident(id) {
+            Some(parts) => parts,
+            None => return self.write_unquoted_ident(id),
+        };
+        let inner = if quote == '"' {
+            inner.to_string()
+        } else {
+            inner.replace('"', "\"\"")
+        };
+        if self.quoted_identifiers_ignore_case {
+            let upper = inner.to_uppercase();
+            if is_simple_ident(&upper) {
+                return self.write(&upper);
+            }
+            return self.write(&format!("\"{}\"", upper));
+        }
+        self.write(&format!("\"{}\"", inner))
+    }
+
+    /// Snowflake spells both `TOP n` and `FETCH FIRST n ROWS ONLY`
+    /// as `LIMIT n`, an OFFSET without a limit becomes `LIMIT NULL`
+    fn write_limit(&mut self, node: &Query) -> Res<()> {
+        let limit = match (&node.limit, &node.fetch) {
+            (Some(_), Some(_)) => {
+                return unsupported("Snowflake does not allow LIMIT and FETCH in the same query")
+            },
+            (Some(limit), None) => Some(limit.clone()),
+            (None, Some(fetch)) => Some(generic::fetch_as_limit("Snowflake", fetch)?),
+            (None, None) => None,
+        };
+        if limit.is_none() && node.offset.is_none() {
+            return Ok(());
+        }
+        self.write_new_line()?;
+        self.write_prefix()?;
+        write_clause(self, "LIMIT ")?;
+        match &limit {
+            Some(limit) => self.write_expr(limit)?,
+            None => self.write_keyword("NULL")?,
+        }
+        if let Some(offset) = &node.offset {
+            self.write_new_line()?;
+            self.write_prefix()?;
+            write_clause(self, "OFFSET ")?;
+            self.write_expr(offset)?;
+        }
+        Ok(())
+    }
+
+    fn write_window_frame(&mut self, node: &WindowFrame) -> Res<()> {
+        match node.units {
+            WindowFrameUnits::Groups => {
+                return unsupported("Snowflake does not support GROUPS window frames")
+            },
+            WindowFrameUnits::Range => {
+                for bound in Some(&node.start_bound).into_iter().chain(node.end_bound.as_ref()) {
+                    if let WindowFrameBound::Preceding(Some(_)) | WindowFrameBound::Following(Some(_)) = bound {
+                        return unsupported("Snowflake only supports UNBOUNDED and CURRENT ROW in RANGE window frames");
+                    }
+                }
+            },
+            WindowFrameUnits::Rows => (),
+        }
+        generic::write_window_frame(self, node)
+    }
+
+    fn write_join(&mut self, node: &Join) -> Res<()> {
+        generic::write_lateral_join(self, node, true)
+    }
+
+    fn write_data_type(&mut self, node: &DataType) -> Res<()> {
+        if !self.out.translate_types {
+            return generic::write_data_type(self, node);
+        }
+        match node {
+            DataType::Text | DataType::Clob(_) => self.write_keyword("VARCHAR"),
+            DataType::Uuid => self.write_keyword("VARCHAR(36)"),
+            DataType::Bytea | DataType::Blob(_) => self.write_keyword("BINARY"),
+            DataType::Binary(size) | DataType::Varbinary(size) => {
+                self.write_keyword(&format!("BINARY({})", size))
+            },
+            DataType::Decimal(precision, scale) => {
+                self.write_keyword("NUMBER")?;
+                if let Some(precision) = precision {
+                    self.write(&format!("({}", precision))?;
+                    if let Some(scale) = scale {
+                        self.write(&format!(", {}", scale))?;
+                    }
+                    self.write(")")?;
+                }
+                Ok(())
+            },
+            DataType::Double => self.write_keyword("DOUBLE"),
+            DataType::Timestamp => self.write_keyword("TIMESTAMP_NTZ"),
+            DataType::Array(_) => self.write_keyword("ARRAY"),
+            DataType::Interval | DataType::Regclass => {
+                unsupported(format!("Snowflake has no {:?} data type", node))
+            },
+            DataType::Custom(name) => {
+                let last = name.0.last().map(|n| n.to_uppercase()).unwrap_or_default();
+                match last.as_str() {
+                    "VARIANT" | "JSON" | "JSONB" => self.write_keyword("VARIANT"),
+                    "OBJECT" | "MAP" | "STRUCT" => self.write_keyword("OBJECT"),
+                    "ARRAY" => self.write_keyword("ARRAY"),
+                    "NVARCHAR" | "NCHAR" | "NTEXT" | "STRING" => self.write_keyword("VARCHAR"),
+                    "DATETIME" | "DATETIME2" | "SMALLDATETIME" => self.write_keyword("TIMESTAMP_NTZ"),
+                    "DATETIMEOFFSET" => self.write_keyword("TIMESTAMP_TZ"),
+                    "BIT" => self.write_keyword("BOOLEAN"),
+                    "UNIQUEIDENTIFIER" => self.write_keyword("VARCHAR(36)"),
+                    "MONEY" => self.write_keyword("NUMBER(19, 4)"),
+                    _ => write_bare_name(self, name),
+                }
+            },
+            _ => generic::write_data_type(self, node),
+        }
+    }
+
+    fn write_value(&mut self, node: &Value) -> Res<()> {
+        match node {
+            Value::NationalStringLiteral(s) => self.write(&generic::quote_string(s)),
+            Value::HexStringLiteral(s) => self.write(&format!("TO_BINARY('{}', 'HEX')", s)),
+            Value::Boolean(true) => self.write_keyword("TRUE"),
+            Value::Boolean(false) => self.write_keyword("FALSE"),
+            _ => generic::write_value(self, node),
+        }
+    }
+}
+
+/// Would this identifier survive Snowflake's case folding
+/// without being quoted, keywords (`ORDER`) never do
+fn is_simple_ident(id: &str) -> bool {
+    let mut chars = id.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_uppercase() || c == '_' => (),
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_' || c == '$')
+        && !ALL_KEYWORDS.contains(&id)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn fetch_as_limit() {
+        let q = Query {
+            ctes: vec![],
+            body: SetExpr::Select(Box::new(Select {
+                distinct: false,
+                projection: vec![
+                    SelectItem::UnnamedExpr(Expr::Identifier("[Name]".to_string())),
+                    SelectItem::UnnamedExpr(Expr::Cast {
+                        expr: Box::new(Expr::Identifier("payload".to_string())),
+                        data_type: DataType::Custom(ObjectName(vec!["JSON".to_string()])),
+                    }),
+                ],
+                from: vec![TableWithJoins {
+                    relation: TableFactor::Table {
+                        name: ObjectName(vec!["\"Customers\"".to_string()]),
+                        alias: None,
+                        args: vec![],
+                        with_hints: vec![],
+                    },
+                    joins: vec![],
+                }],
+                selection: None,
+                group_by: vec![],
+                having: None,
+            })),
+            order_by: vec![],
+            limit: None,
+            offset: None,
+            fetch: Some(Fetch {
+                with_ties: false,
+                percent: false,
+                quantity: Some(Expr::Value(Value::Number("10".to_string()))),
+            }),
+        };
+        let mut w = SnowflakeWriter::new("    ", Vec::new());
+        w.write_query(&q).unwrap();
+        let out = String::from_utf8(w.into_inner()).unwrap();
+        assert_eq!(out, "SELECT \"Name\", CAST(payload AS VARIANT)
+FROM \"Customers\"
+LIMIT 10");
+        let mut w = SnowflakeWriter::new("    ", Vec::new())
+            .quoted_identifiers_ignore_case(true);
+        w.write_query(&q).unwrap();
+        let out = String::from_utf8(w.into_inner()).unwrap();
+        assert_eq!(out, "SELECT NAME, CAST(payload AS VARIANT)
+FROM CUSTOMERS
+LIMIT 10");
+    }
+
+    #[test]
+    fn keywords_stay_quoted() {
+        let mut w = SnowflakeWriter::new("    ", Vec::new())
+            .quoted_identifiers_ignore_case(true);
+        w.write_expr(&Expr::CompoundIdentifier(vec!["[Order]".to_string(), "[Total]".to_string()])).unwrap();
+        let out = String::from_utf8(w.into_inner()).unwrap();
+        assert_eq!(out, "\"ORDER\".TOTAL");
+    }
+}
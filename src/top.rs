@@ -0,0 +1,227 @@
+//! T-SQL's `SELECT TOP (n) [PERCENT] [WITH TIES]`, which sqlparser
+//! 0.5 cannot read. A `TOP` limits the rows of the query it
+//! starts the same way `FETCH FIRST` does, so it is kept as
+//! the `fetch` of that query and the T-SQL writer spells a
+//! `FETCH` without an `OFFSET` as `TOP` again.
+//!
+//! Before parsing, the `TOP` is turned into a marker call that
+//! becomes the first column, `TOP(n, percent, with_ties),`,
+//! which is then moved into the query once it is parsed.
+use crate::{
+    rewrite::Walk,
+    tsql::{is_word, TsqlStatement},
+};
+use sqlparser::{
+    ast::*,
+    parser::ParserError,
+    tokenizer::{Token, Whitespace},
+};
+use std::cell::RefCell;
+
+/// Replace every `TOP` that follows a `SELECT [ALL | DISTINCT]`
+/// with the marker column. A `top` that no number, `(` or
+/// variable follows is a column of that name.
+pub(crate) fn mark_top(tokens: Vec<Token>) -> Result<Vec<Token>, ParserError> {
+    let mut marked = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i].clone();
+        i += 1;
+        let select = is_word(Some(token.clone()), "SELECT");
+        marked.push(token);
+        if !select {
+            continue;
+        }
+        let mut top = skip_whitespace(&tokens, i);
+        if is_word(tokens.get(top).cloned(), "ALL") || is_word(tokens.get(top).cloned(), "DISTINCT") {
+            top = skip_whitespace(&tokens, top + 1);
+        }
+        let start = skip_whitespace(&tokens, top + 1);
+        if !is_word(tokens.get(top).cloned(), "TOP") || !starts_quantity(tokens.get(start)) {
+            continue;
+        }
+        marked.extend_from_slice(&tokens[i..top]);
+        let (quantity, end) = top_quantity(&tokens, start)?;
+        let mut end = skip_whitespace(&tokens, end);
+        let percent = is_word(tokens.get(end).cloned(), "PERCENT");
+        if percent {
+            end = skip_whitespace(&tokens, end + 1);
+        }
+        let ties = skip_whitespace(&tokens, end + 1);
+        let with_ties = is_word(tokens.get(end).cloned(), "WITH") && is_word(tokens.get(ties).cloned(), "TIES");
+        if with_ties {
+            end = ties + 1;
+        }
+        marked.push(tokens[top].clone());
+        marked.push(Token::LParen);
+        marked.extend_from_slice(quantity);
+        for flag in &[percent, with_ties] {
+            marked.push(Token::Comma);
+            marked.push(Token::Number(if *flag { "1" } else { "0" }.to_string()));
+        }
+        marked.push(Token::RParen);
+        marked.push(Token::Comma);
+        marked.push(Token::Whitespace(Whitespace::Space));
+        i = end;
+    }
+    Ok(marked)
+}
+
+/// The tokens of the quantity at `start`, the inside of
+/// the parentheses or a single number or variable, and the
+/// index after it
+fn starts_quantity(token: Option<&Token>) -> bool {
+    match token {
+        Some(Token::Number(_)) | Some(Token::LParen) => true,
+        Some(Token::Word(w)) => w.quote_style.is_none() && w.value.starts_with('@'),
+        _ => false,
+    }
+}
+
+fn top_quantity(tokens: &[Token], start: usize) -> Result<(&[Token], usize), ParserError> {
+    match tokens.get(start) {
+        Some(Token::LParen) => {
+            let mut depth = 0;
+            for (i, token) in tokens.iter().enumerate().skip(start) {
+                match token {
+                    Token::LParen => depth += 1,
+                    Token::RParen if depth == 1 => return Ok((&tokens[start + 1..i], i + 1)),
+                    Token::RParen => depth -= 1,
+                    _ => (),
+                }
+            }
+            Err(ParserError::ParserError("Expected ), found EOF".to_string()))
+        },
+        Some(Token::Number(_)) | Some(Token::Word(_)) => Ok((&tokens[start..start + 1], start + 1)),
+        other => Err(ParserError::ParserError(format!("Expected the quantity of TOP, found {:?}", other))),
+    }
+}
+
+fn skip_whitespace(tokens: &[Token], mut i: usize) -> usize {
+    while let Some(Token::Whitespace(_)) = tokens.get(i) {
+        i += 1;
+    }
+    i
+}
+
+/// Move the marker columns left by `mark_top` into the
+/// `fetch` of their queries
+pub(crate) fn resolve_top(stmts: &mut [TsqlStatement]) -> Result<(), ParserError> {
+    let pass = TopPass { error: RefCell::new(None) };
+    for stmt in stmts {
+        pass.tsql_statement(stmt);
+    }
+    match pass.error.into_inner() {
+        Some(error) => Err(ParserError::ParserError(error)),
+        None => Ok(()),
+    }
+}
+
+struct TopPass {
+    error: RefCell<Option<String>>,
+}
+
+impl TopPass {
+    fn fail(&self, error: &str) {
+        self.error.borrow_mut().get_or_insert_with(|| error.to_string());
+    }
+}
+
+impl Walk for TopPass {
+    fn enter_query(&self, q: &mut Query) {
+        let select = match &mut q.body {
+            SetExpr::Select(select) => select,
+            _ => return,
+        };
+        let top = match take_marker(select) {
+            Some(top) => top,
+            None => return,
+        };
+        if q.limit.is_some() || q.offset.is_some() || q.fetch.is_some() {
+            return self.fail("TOP cannot be combined with OFFSET or FETCH");
+        }
+        q.fetch = Some(top);
+    }
+
+    /// Any marker still here belongs to one side of a set operation
+    fn visit_select(&self, s: &mut Select) {
+        if take_marker(s).is_some() {
+            self.fail("TOP is only supported on a SELECT that is not part of a UNION, EXCEPT or INTERSECT");
+        }
+    }
+}
+
+fn take_marker(select: &mut Select) -> Option<Fetch> {
+    let mut args = match select.projection.first() {
+        Some(SelectItem::UnnamedExpr(Expr::Function(f)))
+            if f.args.len() == 3 && f.name.0.len() == 1 && f.name.0[0].eq_ignore_ascii_case("TOP") => f.args.clone(),
+        _ => return None,
+    };
+    select.projection.remove(0);
+    let flag = |expr: &Expr| *expr == Expr::Value(Value::Number("1".to_string()));
+    Some(Fetch {
+        with_ties: flag(&args[2]),
+        percent: flag(&args[1]),
+        quantity: Some(args.remove(0)),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn select(projection: Vec<SelectItem>) -> SetExpr {
+        SetExpr::Select(Box::new(Select {
+            distinct: false,
+            projection,
+            from: vec![],
+            selection: None,
+            group_by: vec![],
+            having: None,
+        }))
+    }
+
+    #[test]
+    fn marker_becomes_fetch() {
+        let num = |s: &str| Expr::Value(Value::Number(s.to_string()));
+        let marker = SelectItem::UnnamedExpr(Expr::Function(Function {
+            name: ObjectName(vec!["TOP".to_string()]),
+            args: vec![num("5"), num("1"), num("0")],
+            over: None,
+            distinct: false,
+        }));
+        let column = SelectItem::UnnamedExpr(Expr::Identifier("a".to_string()));
+        let query = Query {
+            ctes: vec![],
+            body: select(vec![marker.clone(), column.clone()]),
+            order_by: vec![],
+            limit: None,
+            offset: None,
+            fetch: None,
+        };
+        let mut stmts = vec![TsqlStatement::Statement(Statement::Query(Box::new(query.clone())))];
+        resolve_top(&mut stmts).unwrap();
+        let expected = Query {
+            body: select(vec![column.clone()]),
+            fetch: Some(Fetch {
+                with_ties: false,
+                percent: true,
+                quantity: Some(num("5")),
+            }),
+            ..query.clone()
+        };
+        assert_eq!(stmts, vec![TsqlStatement::Statement(Statement::Query(Box::new(expected)))]);
+
+        let union = Query {
+            body: SetExpr::SetOperation {
+                op: SetOperator::Union,
+                all: false,
+                left: Box::new(select(vec![marker, column.clone()])),
+                right: Box::new(select(vec![column])),
+            },
+            ..query
+        };
+        let mut stmts = vec![TsqlStatement::Statement(Statement::Query(Box::new(union)))];
+        assert!(resolve_top(&mut stmts).is_err());
+    }
+}
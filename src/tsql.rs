@@ -0,0 +1,1197 @@
+//! T-SQL procedural statements that sqlparser does not know
+//! about. `parse_tsql` reads them itself and hands everything
+//! else to sqlparser, so a batch becomes a list of
+//! `TsqlStatement`s that wrap the regular `Statement`s.
+//!
+//! Assigning a column to a variable, `SELECT @v = col`, parses as
+//! an `=` comparison and is turned into `SelectVariables` after.
+use crate::{
+    dml::{self, parse_delete, parse_insert, parse_update, OutputClause},
+    for_clause::{check_for_clauses, mark_for},
+    merge::{parse_merge, Merge},
+    pivot::{check_pivots, mark_pivot},
+    start_list_line,
+    top::{mark_top, resolve_top},
+    write_table_elements, Res, SqlWriter,
+};
+use sqlparser::{
+    ast::{BinaryOperator, ColumnDef, DataType, Expr, Ident, ObjectName, Query, SelectItem, SetExpr, Statement, TableConstraint, TableWithJoins},
+    dialect::Dialect,
+    parser::{Parser, ParserError},
+    tokenizer::{Token, Tokenizer},
+};
+
+/// A statement of a T-SQL batch
+#[derive(Debug, Clone, PartialEq)]
+pub enum TsqlStatement {
+    /// Anything sqlparser can read on its own
+    Statement(Statement),
+    /// `INSERT`, `UPDATE` or `DELETE` with an `OUTPUT` clause
+    Dml {
+        statement: Statement,
+        output: OutputClause,
+    },
+    /// `UPDATE ... SET ... [OUTPUT ...] FROM ... [WHERE ...]`, which
+    /// takes the new values from the rows of a join
+    UpdateFrom {
+        statement: Statement,
+        from: Vec<TableWithJoins>,
+        output: Option<OutputClause>,
+    },
+    Merge(Box<Merge>),
+    /// `DECLARE @a INT = 1, @t TABLE (...)`
+    Declare(Vec<Declaration>),
+    /// `SET @a = expr`
+    SetVariable { name: Ident, value: Expr },
+    /// `SELECT @a = expr, ... [FROM ...]`, which sets the variables
+    /// from the rows of the query instead of returning them. The
+    /// columns of the query are the values, in the same order
+    SelectVariables {
+        variables: Vec<Ident>,
+        query: Box<Query>,
+    },
+    /// `BEGIN ... END`
+    Block(Vec<TsqlStatement>),
+    /// `IF condition statement [ELSE statement]`
+    If {
+        condition: Expr,
+        then: Box<TsqlStatement>,
+        otherwise: Option<Box<TsqlStatement>>,
+    },
+    /// `WHILE condition statement`
+    While {
+        condition: Expr,
+        body: Box<TsqlStatement>,
+    },
+    Break,
+    Continue,
+    /// `BEGIN TRY ... END TRY BEGIN CATCH ... END CATCH`
+    TryCatch {
+        body: Vec<TsqlStatement>,
+        handler: Vec<TsqlStatement>,
+    },
+    /// `RETURN [expr]`
+    Return(Option<Expr>),
+    /// `THROW [number, message, state]`, without the
+    /// arguments it re-throws the error being handled
+    Throw(Option<ThrowError>),
+    /// `RAISERROR(message, severity, state [, argument ...]) [WITH option, ...]`
+    Raiserror {
+        message: Expr,
+        severity: Expr,
+        state: Expr,
+        arguments: Vec<Expr>,
+        options: Vec<Ident>,
+    },
+    /// `SET NOCOUNT ON`
+    SetOption { name: Ident, on: bool },
+    /// `CREATE [OR ALTER] PROCEDURE name @p type [= default] [OUTPUT], ... AS body`,
+    /// the body is the rest of the batch
+    CreateProcedure {
+        or_alter: bool,
+        name: ObjectName,
+        parameters: Vec<Parameter>,
+        body: Vec<TsqlStatement>,
+    },
+    /// `CREATE [OR ALTER] FUNCTION name(@p type, ...) RETURNS ...`
+    CreateFunction {
+        or_alter: bool,
+        name: ObjectName,
+        parameters: Vec<Parameter>,
+        returns: FunctionReturns,
+    },
+    /// `CREATE [OR ALTER] TRIGGER name ON table AFTER INSERT, ... AS body`,
+    /// the body is the rest of the batch
+    CreateTrigger {
+        or_alter: bool,
+        name: ObjectName,
+        table: ObjectName,
+        timing: TriggerTiming,
+        events: Vec<TriggerEvent>,
+        body: Vec<TsqlStatement>,
+    },
+}
+
+/// A parameter of a procedure or function
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    /// The name including its `@`
+    pub name: Ident,
+    pub data_type: DataType,
+    pub default: Option<Expr>,
+    /// `OUTPUT`, only procedures have output parameters
+    pub output: bool,
+}
+
+/// What a function returns and the body that produces it
+#[derive(Debug, Clone, PartialEq)]
+pub enum FunctionReturns {
+    /// `RETURNS type AS BEGIN ... RETURN expr END`
+    Scalar {
+        data_type: DataType,
+        body: Vec<TsqlStatement>,
+    },
+    /// An inline table-valued function, `RETURNS TABLE AS RETURN (query)`
+    InlineTable(Box<Query>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerTiming {
+    /// `AFTER`, or its older spelling `FOR`
+    After,
+    InsteadOf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerEvent {
+    Insert,
+    Update,
+    Delete,
+}
+
+/// The arguments of `THROW`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThrowError {
+    pub number: Expr,
+    pub message: Expr,
+    pub state: Expr,
+}
+
+/// One variable of a `DECLARE`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declaration {
+    /// The name including its `@`
+    pub name: Ident,
+    pub kind: DeclarationKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeclarationKind {
+    /// `@a INT [= expr]`
+    Scalar {
+        data_type: DataType,
+        default: Option<Expr>,
+    },
+    /// `@t TABLE (column, ..., constraint, ...)`
+    Table {
+        columns: Vec<ColumnDef>,
+        constraints: Vec<TableConstraint>,
+    },
+}
+
+/// Parse `sql` into statements, reading `DECLARE` and `SET @v`
+/// here and everything else with sqlparser. As in T-SQL the `;`
+/// between statements is optional.
+pub fn parse_tsql(sql: &str, dialect: &dyn Dialect) -> Result<Vec<TsqlStatement>, ParserError> {
+    parse_tsql_lines(sql, dialect).map_err(|(e, _)| e)
+}
+
+/// `parse_tsql` that also reports the line of `sql` the error
+/// was found on, counting from 0
+pub(crate) fn parse_tsql_lines(sql: &str, dialect: &dyn Dialect) -> Result<Vec<TsqlStatement>, (ParserError, usize)> {
+    let tokens = Tokenizer::new(dialect, sql)
+        .tokenize()
+        .map_err(|e| (ParserError::TokenizerError(format!("{:?}", e)), 0))?;
+    let tokens = mark_statement_ends(mark_for(mark_top(mark_pivot(tokens)).map_err(|e| (e, 0))?));
+    let mut parser = Parser::new(tokens.clone());
+    let mut stmts = parse_to_end(&mut parser, dialect).map_err(|e| (e, error_line(&mut parser, &tokens)))?;
+    resolve_top(&mut stmts).map_err(|e| (e, 0))?;
+    check_pivots(&mut stmts).map_err(|e| (e, 0))?;
+    check_for_clauses(&mut stmts).map_err(|e| (e, 0))?;
+    Ok(stmts)
+}
+
+/// T-SQL reserved words that start a statement, sqlparser
+/// would read one that follows a column or table as its alias
+const STATEMENT_WORDS: &[&str] = &[
+    "BEGIN", "BREAK", "COMMIT", "CONTINUE", "DECLARE", "DELETE", "ELSE", "END", "IF", "INSERT",
+    "MERGE", "RAISERROR", "RETURN", "ROLLBACK", "SET", "THROW", "UPDATE", "WHILE",
+];
+
+/// Words a statement word can follow in the same statement, as in
+/// `ON DELETE`, `AFTER INSERT`, `THEN UPDATE` or `DROP TABLE IF`
+const CONTINUED_BY_STATEMENT_WORDS: &[&str] = &[
+    "AFTER", "AND", "AS", "FOR", "FUNCTION", "NOT", "OF", "ON", "OR", "PROC", "PROCEDURE", "TABLE",
+    "THEN", "TRIGGER", "VIEW",
+];
+
+/// The `;` between statements is optional in T-SQL, so one is put
+/// before every `ELSE` and `END` that is not part of a `CASE` and
+/// before a statement word that follows a value
+fn mark_statement_ends(tokens: Vec<Token>) -> Vec<Token> {
+    let mut marked = Vec::with_capacity(tokens.len());
+    let mut depth = 0;
+    let mut cases = 0;
+    let mut after_value = false;
+    // the `SET` of an `UPDATE` continues it
+    let mut update_set = false;
+    let mut previous = None;
+    for token in tokens {
+        if let Token::Whitespace(_) = token {
+            marked.push(token);
+            continue;
+        }
+        let is = |word: &str| is_word(Some(token.clone()), word);
+        let statement_word = STATEMENT_WORDS.iter().any(|w| is(w));
+        let mut ends_value = match &token {
+            Token::Number(_)
+            | Token::SingleQuotedString(_)
+            | Token::NationalStringLiteral(_)
+            | Token::HexStringLiteral(_)
+            | Token::RParen => true,
+            Token::Word(_) => !statement_word && !CONTINUED_BY_STATEMENT_WORDS.iter().any(|w| is(w)),
+            _ => false,
+        };
+        let mark = if is("CASE") {
+            cases += 1;
+            false
+        } else if is("END") && cases > 0 {
+            cases -= 1;
+            ends_value = true;
+            false
+        } else if is("ELSE") {
+            cases == 0
+        } else if is("END") {
+            true
+        } else if is("SET") && update_set {
+            update_set = false;
+            false
+        } else {
+            if is("UPDATE") {
+                update_set = ![",", "AFTER", "OF", "FOR"].iter().any(|w| match &previous {
+                    Some(Token::Comma) => *w == ",",
+                    previous => is_word(previous.clone(), w),
+                });
+            }
+            statement_word && after_value && depth == 0
+        };
+        match token {
+            Token::LParen => depth += 1,
+            Token::RParen => depth -= 1,
+            _ => (),
+        }
+        if mark {
+            marked.push(Token::SemiColon);
+        }
+        after_value = ends_value;
+        previous = Some(token.clone());
+        marked.push(token);
+    }
+    marked
+}
+
+/// The line of the last token a failed parser read, sqlparser
+/// errors do not say where they happened
+fn error_line(parser: &mut Parser, tokens: &[Token]) -> usize {
+    let mut unread = 0;
+    while parser.next_token().is_some() {
+        unread += 1;
+    }
+    let mut read = tokens.iter().filter(|t| !matches!(t, Token::Whitespace(_))).count() - unread;
+    let mut line = 0;
+    for token in tokens {
+        if !matches!(token, Token::Whitespace(_)) {
+            if read <= 1 {
+                break;
+            }
+            read -= 1;
+        }
+        line += token.to_string().matches('\n').count();
+    }
+    line
+}
+
+/// The statements up to the end of the batch
+fn parse_to_end(parser: &mut Parser, dialect: &dyn Dialect) -> Result<Vec<TsqlStatement>, ParserError> {
+    let mut stmts = Vec::new();
+    loop {
+        while parser.consume_token(&Token::SemiColon) {}
+        if parser.peek_token().is_none() {
+            return Ok(stmts);
+        }
+        stmts.push(parse_statement(parser, dialect)?);
+    }
+}
+
+fn parse_statement(parser: &mut Parser, dialect: &dyn Dialect) -> Result<TsqlStatement, ParserError> {
+    // `mark_statement_ends` may have put one after a condition
+    while parser.consume_token(&Token::SemiColon) {}
+    if parse_word(parser, "CREATE") {
+        if let Some(stmt) = parse_create(parser, dialect)? {
+            return Ok(stmt);
+        }
+        parser.prev_token();
+    }
+    if parse_word(parser, "BEGIN") {
+        if parse_word(parser, "TRY") {
+            let body = parse_block_body(parser, dialect)?;
+            expect_word(parser, "TRY")?;
+            while parser.consume_token(&Token::SemiColon) {}
+            expect_word(parser, "BEGIN")?;
+            expect_word(parser, "CATCH")?;
+            let handler = parse_block_body(parser, dialect)?;
+            expect_word(parser, "CATCH")?;
+            return Ok(TsqlStatement::TryCatch { body, handler });
+        }
+        if parse_word(parser, "TRAN") || parse_word(parser, "TRANSACTION") {
+            expect_no_transaction_name(parser)?;
+            return Ok(TsqlStatement::Statement(Statement::StartTransaction { modes: vec![] }));
+        }
+        return Ok(TsqlStatement::Block(parse_block_body(parser, dialect)?));
+    }
+    for word in &["COMMIT", "ROLLBACK"] {
+        if parse_word(parser, word) {
+            if parse_word(parser, "TRAN") || parse_word(parser, "TRANSACTION") {
+                expect_no_transaction_name(parser)?;
+                return Ok(TsqlStatement::Statement(match *word {
+                    "COMMIT" => Statement::Commit { chain: false },
+                    _ => Statement::Rollback { chain: false },
+                }));
+            }
+            parser.prev_token();
+        }
+    }
+    if parse_word(parser, "IF") {
+        let condition = parser.parse_expr()?;
+        let then = Box::new(parse_statement(parser, dialect)?);
+        while parser.consume_token(&Token::SemiColon) {}
+        let otherwise = if parse_word(parser, "ELSE") {
+            Some(Box::new(parse_statement(parser, dialect)?))
+        } else {
+            None
+        };
+        return Ok(TsqlStatement::If { condition, then, otherwise });
+    }
+    if parse_word(parser, "WHILE") {
+        let condition = parser.parse_expr()?;
+        let body = Box::new(parse_statement(parser, dialect)?);
+        return Ok(TsqlStatement::While { condition, body });
+    }
+    if parse_word(parser, "BREAK") {
+        return Ok(TsqlStatement::Break);
+    }
+    if parse_word(parser, "CONTINUE") {
+        return Ok(TsqlStatement::Continue);
+    }
+    if parse_word(parser, "RETURN") {
+        let value = if starts_expr(parser.peek_token()) {
+            Some(parser.parse_expr()?)
+        } else {
+            None
+        };
+        return Ok(TsqlStatement::Return(value));
+    }
+    if parse_word(parser, "THROW") {
+        if !starts_expr(parser.peek_token()) {
+            return Ok(TsqlStatement::Throw(None));
+        }
+        let number = parser.parse_expr()?;
+        parser.expect_token(&Token::Comma)?;
+        let message = parser.parse_expr()?;
+        parser.expect_token(&Token::Comma)?;
+        let state = parser.parse_expr()?;
+        return Ok(TsqlStatement::Throw(Some(ThrowError { number, message, state })));
+    }
+    if parse_word(parser, "RAISERROR") {
+        return parse_raiserror(parser);
+    }
+    if parse_word(parser, "INSERT") {
+        return parse_insert(parser);
+    }
+    if parse_word(parser, "UPDATE") {
+        return parse_update(parser);
+    }
+    if parse_word(parser, "DELETE") {
+        return parse_delete(parser);
+    }
+    if parse_word(parser, "MERGE") {
+        return Ok(TsqlStatement::Merge(Box::new(parse_merge(parser)?)));
+    }
+    if parse_word(parser, "DECLARE") {
+        let mut declarations = vec![parse_declaration(parser, dialect)?];
+        while parser.consume_token(&Token::Comma) {
+            declarations.push(parse_declaration(parser, dialect)?);
+        }
+        return Ok(TsqlStatement::Declare(declarations));
+    }
+    if parse_word(parser, "SET") {
+        if is_variable(parser.peek_token()) {
+            let name = parser.parse_identifier()?;
+            parser.expect_token(&Token::Eq)?;
+            let value = parser.parse_expr()?;
+            return Ok(TsqlStatement::SetVariable { name, value });
+        }
+        if let Some(stmt) = parse_set_option(parser)? {
+            return Ok(stmt);
+        }
+        parser.prev_token();
+    }
+    Ok(select_variables(parser.parse_statement()?))
+}
+
+/// A `SELECT` whose columns all assign to variables
+/// as `SelectVariables`, any other statement as is
+fn select_variables(stmt: Statement) -> TsqlStatement {
+    let mut query = match stmt {
+        Statement::Query(query) => query,
+        stmt => return TsqlStatement::Statement(stmt),
+    };
+    let select = match &mut query.body {
+        SetExpr::Select(select) => select,
+        _ => return TsqlStatement::Statement(Statement::Query(query)),
+    };
+    let assignments = select.projection.iter()
+        .map(|item| match item {
+            SelectItem::UnnamedExpr(Expr::BinaryOp { left, op: BinaryOperator::Eq, right }) => match &**left {
+                Expr::Identifier(name) if name.starts_with('@') => Some((name.clone(), (**right).clone())),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>();
+    let (variables, values): (Vec<_>, Vec<_>) = match assignments {
+        Some(assignments) => assignments.into_iter().unzip(),
+        None => return TsqlStatement::Statement(Statement::Query(query)),
+    };
+    select.projection = values.into_iter().map(SelectItem::UnnamedExpr).collect();
+    TsqlStatement::SelectVariables { variables, query }
+}
+
+fn parse_declaration(parser: &mut Parser, dialect: &dyn Dialect) -> Result<Declaration, ParserError> {
+    if !is_variable(parser.peek_token()) {
+        return Err(ParserError::ParserError(format!(
+            "Expected a variable name after DECLARE, found {:?}",
+            parser.peek_token()
+        )));
+    }
+    let name = parser.parse_identifier()?;
+    parse_word(parser, "AS");
+    if parse_word(parser, "TABLE") {
+        let (columns, constraints) = parse_table_columns(parser, dialect)?;
+        return Ok(Declaration {
+            name,
+            kind: DeclarationKind::Table { columns, constraints },
+        });
+    }
+    let data_type = parser.parse_data_type()?;
+    let default = if parser.consume_token(&Token::Eq) {
+        Some(parser.parse_expr()?)
+    } else {
+        None
+    };
+    Ok(Declaration {
+        name,
+        kind: DeclarationKind::Scalar { data_type, default },
+    })
+}
+
+/// The column list of a table variable is the same as the one
+/// of `CREATE TABLE`, so its tokens are parsed as one
+fn parse_table_columns(parser: &mut Parser, dialect: &dyn Dialect) -> Result<(Vec<ColumnDef>, Vec<TableConstraint>), ParserError> {
+    let mut tokens = Tokenizer::new(dialect, "CREATE TABLE t ")
+        .tokenize()
+        .map_err(|e| ParserError::TokenizerError(format!("{:?}", e)))?;
+    parser.expect_token(&Token::LParen)?;
+    tokens.push(Token::LParen);
+    let mut depth = 1;
+    while depth > 0 {
+        let token = match parser.next_token() {
+            Some(token) => token,
+            None => return Err(ParserError::ParserError("Expected ), found EOF".to_string())),
+        };
+        match token {
+            Token::LParen => depth += 1,
+            Token::RParen => depth -= 1,
+            _ => (),
+        }
+        tokens.push(token);
+    }
+    match Parser::new(tokens).parse_statement()? {
+        Statement::CreateTable { columns, constraints, .. } => Ok((columns, constraints)),
+        _ => Err(ParserError::ParserError("Expected a column list after TABLE".to_string())),
+    }
+}
+
+/// `SET name ON|OFF`, leaves the parser where it was for
+/// any other `SET`
+fn parse_set_option(parser: &mut Parser) -> Result<Option<TsqlStatement>, ParserError> {
+    match parser.peek_token() {
+        Some(Token::Word(w)) if w.quote_style.is_none() => (),
+        _ => return Ok(None),
+    }
+    let name = parser.parse_identifier()?;
+    let on = if parse_word(parser, "ON") {
+        true
+    } else if parse_word(parser, "OFF") {
+        false
+    } else {
+        parser.prev_token();
+        return Ok(None);
+    };
+    Ok(Some(TsqlStatement::SetOption { name, on }))
+}
+
+/// The procedures, functions and triggers that follow `CREATE`,
+/// `None` leaves the parser after `CREATE` for sqlparser
+fn parse_create(parser: &mut Parser, dialect: &dyn Dialect) -> Result<Option<TsqlStatement>, ParserError> {
+    let or_alter = is_word(parser.peek_token(), "OR");
+    if or_alter {
+        parser.next_token();
+        expect_word(parser, "ALTER")?;
+    }
+    if parse_word(parser, "PROCEDURE") || parse_word(parser, "PROC") {
+        let name = parser.parse_object_name()?;
+        let parens = parser.consume_token(&Token::LParen);
+        let parameters = parse_parameters(parser)?;
+        if parens {
+            parser.expect_token(&Token::RParen)?;
+        }
+        expect_word(parser, "AS")?;
+        let body = parse_to_end(parser, dialect)?;
+        return Ok(Some(TsqlStatement::CreateProcedure { or_alter, name, parameters, body }));
+    }
+    if parse_word(parser, "FUNCTION") {
+        let name = parser.parse_object_name()?;
+        parser.expect_token(&Token::LParen)?;
+        let parameters = parse_parameters(parser)?;
+        parser.expect_token(&Token::RParen)?;
+        expect_word(parser, "RETURNS")?;
+        let returns = if parse_word(parser, "TABLE") {
+            expect_word(parser, "AS")?;
+            expect_word(parser, "RETURN")?;
+            let parens = parser.consume_token(&Token::LParen);
+            let query = parser.parse_query()?;
+            if parens {
+                parser.expect_token(&Token::RParen)?;
+            }
+            FunctionReturns::InlineTable(Box::new(query))
+        } else {
+            let data_type = parser.parse_data_type()?;
+            expect_word(parser, "AS")?;
+            FunctionReturns::Scalar {
+                data_type,
+                body: parse_to_end(parser, dialect)?,
+            }
+        };
+        return Ok(Some(TsqlStatement::CreateFunction { or_alter, name, parameters, returns }));
+    }
+    if parse_word(parser, "TRIGGER") {
+        let name = parser.parse_object_name()?;
+        expect_word(parser, "ON")?;
+        let table = parser.parse_object_name()?;
+        let timing = if parse_word(parser, "AFTER") || parse_word(parser, "FOR") {
+            TriggerTiming::After
+        } else {
+            expect_word(parser, "INSTEAD")?;
+            expect_word(parser, "OF")?;
+            TriggerTiming::InsteadOf
+        };
+        let mut events = vec![parse_trigger_event(parser)?];
+        while parser.consume_token(&Token::Comma) {
+            events.push(parse_trigger_event(parser)?);
+        }
+        expect_word(parser, "AS")?;
+        let body = parse_to_end(parser, dialect)?;
+        return Ok(Some(TsqlStatement::CreateTrigger { or_alter, name, table, timing, events, body }));
+    }
+    if or_alter {
+        return Err(ParserError::ParserError(format!(
+            "Expected PROCEDURE, FUNCTION or TRIGGER after CREATE OR ALTER, found {:?}",
+            parser.peek_token()
+        )));
+    }
+    Ok(None)
+}
+
+/// Parameters separated by `,`, there may be none
+fn parse_parameters(parser: &mut Parser) -> Result<Vec<Parameter>, ParserError> {
+    let mut parameters = Vec::new();
+    if !is_variable(parser.peek_token()) {
+        return Ok(parameters);
+    }
+    loop {
+        let name = parser.parse_identifier()?;
+        parse_word(parser, "AS");
+        let data_type = parser.parse_data_type()?;
+        let default = if parser.consume_token(&Token::Eq) {
+            Some(parser.parse_expr()?)
+        } else {
+            None
+        };
+        let output = parse_word(parser, "OUTPUT") || parse_word(parser, "OUT");
+        parameters.push(Parameter { name, data_type, default, output });
+        if !parser.consume_token(&Token::Comma) {
+            return Ok(parameters);
+        }
+    }
+}
+
+fn parse_trigger_event(parser: &mut Parser) -> Result<TriggerEvent, ParserError> {
+    if parse_word(parser, "INSERT") {
+        Ok(TriggerEvent::Insert)
+    } else if parse_word(parser, "UPDATE") {
+        Ok(TriggerEvent::Update)
+    } else if parse_word(parser, "DELETE") {
+        Ok(TriggerEvent::Delete)
+    } else {
+        Err(ParserError::ParserError(format!("Expected INSERT, UPDATE or DELETE, found {:?}", parser.peek_token())))
+    }
+}
+
+/// The statements up to and including the `END` of a block
+fn parse_block_body(parser: &mut Parser, dialect: &dyn Dialect) -> Result<Vec<TsqlStatement>, ParserError> {
+    let mut stmts = Vec::new();
+    loop {
+        while parser.consume_token(&Token::SemiColon) {}
+        if parse_word(parser, "END") {
+            return Ok(stmts);
+        }
+        if parser.peek_token().is_none() {
+            return Err(ParserError::ParserError("Expected END, found EOF".to_string()));
+        }
+        stmts.push(parse_statement(parser, dialect)?);
+    }
+}
+
+/// Named transactions have no place in the sqlparser AST
+fn expect_no_transaction_name(parser: &mut Parser) -> Result<(), ParserError> {
+    if let Some(name) = parser.peek_token().filter(|t| starts_expr(Some(t.clone()))) {
+        return Err(ParserError::ParserError(format!("Named transactions are not supported, found {}", name)));
+    }
+    Ok(())
+}
+
+fn parse_raiserror(parser: &mut Parser) -> Result<TsqlStatement, ParserError> {
+    parser.expect_token(&Token::LParen)?;
+    let mut arguments = vec![parser.parse_expr()?];
+    while parser.consume_token(&Token::Comma) {
+        arguments.push(parser.parse_expr()?);
+    }
+    parser.expect_token(&Token::RParen)?;
+    let rest = if arguments.len() > 3 { arguments.split_off(3) } else { Vec::new() };
+    let mut required = arguments.into_iter();
+    let (message, severity, state) = match (required.next(), required.next(), required.next()) {
+        (Some(message), Some(severity), Some(state)) => (message, severity, state),
+        _ => return Err(ParserError::ParserError("RAISERROR needs a message, severity and state".to_string())),
+    };
+    let mut options = Vec::new();
+    if parse_word(parser, "WITH") {
+        options.push(parser.parse_identifier()?);
+        while parser.consume_token(&Token::Comma) {
+            options.push(parser.parse_identifier()?);
+        }
+    }
+    Ok(TsqlStatement::Raiserror {
+        message,
+        severity,
+        state,
+        arguments: rest,
+        options,
+    })
+}
+
+/// Consume the next token if it is the unquoted word `expected`,
+/// T-SQL words like `DECLARE` are not all sqlparser keywords
+pub(crate) fn parse_word(parser: &mut Parser, expected: &str) -> bool {
+    if is_word(parser.peek_token(), expected) {
+        parser.next_token();
+        return true;
+    }
+    false
+}
+
+pub(crate) fn expect_word(parser: &mut Parser, expected: &str) -> Result<(), ParserError> {
+    if parse_word(parser, expected) {
+        return Ok(());
+    }
+    Err(ParserError::ParserError(format!("Expected {}, found {:?}", expected, parser.peek_token())))
+}
+
+pub(crate) fn is_word(token: Option<Token>, expected: &str) -> bool {
+    match token {
+        Some(Token::Word(w)) => w.quote_style.is_none() && w.value.eq_ignore_ascii_case(expected),
+        _ => false,
+    }
+}
+
+/// Whether the optional argument of `RETURN` or `THROW` follows
+/// rather than the next statement or the end of the block
+fn starts_expr(token: Option<Token>) -> bool {
+    match token {
+        Some(Token::SemiColon) | None => false,
+        token => !STATEMENT_WORDS.iter().chain(&["SELECT", "WITH"]).any(|w| is_word(token.clone(), w)),
+    }
+}
+
+fn is_variable(token: Option<Token>) -> bool {
+    match token {
+        Some(Token::Word(w)) => w.quote_style.is_none() && w.value.starts_with('@'),
+        _ => false,
+    }
+}
+
+/// Write a T-SQL statement, for the writers of dialects
+/// that have local variables
+pub(crate) fn write_tsql_statement<S>(w: &mut S, node: &TsqlStatement) -> Res<()>
+where S: SqlWriter + ?Sized {
+    match node {
+        TsqlStatement::Statement(Statement::StartTransaction { modes }) if modes.is_empty() => {
+            w.write_keyword("BEGIN TRANSACTION")
+        },
+        TsqlStatement::Statement(stmt) => w.write_statement(stmt),
+        TsqlStatement::Dml { statement, output } => w.write_dml_output(statement, output),
+        TsqlStatement::UpdateFrom { statement, from, output } => {
+            dml::write_update_from(w, statement, from, output.as_ref())
+        },
+        TsqlStatement::Merge(merge) => w.write_merge(merge),
+        TsqlStatement::Declare(declarations) => {
+            w.write_keyword("DECLARE ")?;
+            let mut after_first = false;
+            for declaration in declarations {
+                if after_first {
+                    w.write(", ")?;
+                }
+                write_declaration(w, declaration)?;
+                after_first = true;
+            }
+            Ok(())
+        },
+        TsqlStatement::SetVariable { name, value } => {
+            w.write_keyword("SET ")?;
+            w.write_ident(name)?;
+            w.write(" = ")?;
+            w.write_expr(value)
+        },
+        TsqlStatement::SelectVariables { variables, query } => {
+            let mut assigning = (**query).clone();
+            if let SetExpr::Select(select) = &mut assigning.body {
+                for (item, variable) in select.projection.iter_mut().zip(variables) {
+                    if let SelectItem::UnnamedExpr(value) = item {
+                        *value = Expr::BinaryOp {
+                            left: Box::new(Expr::Identifier(variable.clone())),
+                            op: BinaryOperator::Eq,
+                            right: Box::new(value.clone()),
+                        };
+                    }
+                }
+            }
+            w.write_query(&assigning)
+        },
+        TsqlStatement::Block(stmts) => {
+            w.write_keyword("BEGIN")?;
+            write_block_body(w, stmts)?;
+            w.write_keyword("END")
+        },
+        TsqlStatement::If { condition, then, otherwise } => {
+            w.write_keyword("IF ")?;
+            w.write_expr(condition)?;
+            write_branch(w, then)?;
+            let otherwise = match otherwise {
+                Some(otherwise) => otherwise,
+                None => return Ok(()),
+            };
+            if !ends_itself(then) {
+                w.write(";")?;
+            }
+            w.write_new_line()?;
+            w.write_prefix()?;
+            w.write_keyword("ELSE")?;
+            if let TsqlStatement::If { .. } = **otherwise {
+                w.write(" ")?;
+                return write_tsql_statement(w, otherwise);
+            }
+            write_branch(w, otherwise)
+        },
+        TsqlStatement::While { condition, body } => {
+            w.write_keyword("WHILE ")?;
+            w.write_expr(condition)?;
+            write_branch(w, body)
+        },
+        TsqlStatement::Break => w.write_keyword("BREAK"),
+        TsqlStatement::Continue => w.write_keyword("CONTINUE"),
+        TsqlStatement::TryCatch { body, handler } => {
+            w.write_keyword("BEGIN TRY")?;
+            write_block_body(w, body)?;
+            w.write_keyword("END TRY")?;
+            w.write_new_line()?;
+            w.write_prefix()?;
+            w.write_keyword("BEGIN CATCH")?;
+            write_block_body(w, handler)?;
+            w.write_keyword("END CATCH")
+        },
+        TsqlStatement::Return(value) => {
+            w.write_keyword("RETURN")?;
+            if let Some(value) = value {
+                w.write(" ")?;
+                w.write_expr(value)?;
+            }
+            Ok(())
+        },
+        TsqlStatement::Throw(error) => {
+            w.write_keyword("THROW")?;
+            if let Some(error) = error {
+                w.write(" ")?;
+                w.write_expr(&error.number)?;
+                w.write(", ")?;
+                w.write_expr(&error.message)?;
+                w.write(", ")?;
+                w.write_expr(&error.state)?;
+            }
+            Ok(())
+        },
+        TsqlStatement::Raiserror { message, severity, state, arguments, options } => {
+            w.write_keyword("RAISERROR(")?;
+            w.write_expr(message)?;
+            w.write(", ")?;
+            w.write_expr(severity)?;
+            w.write(", ")?;
+            w.write_expr(state)?;
+            for argument in arguments {
+                w.write(", ")?;
+                w.write_expr(argument)?;
+            }
+            w.write(")")?;
+            if !options.is_empty() {
+                w.write_keyword(" WITH ")?;
+                let mut after_first = false;
+                for option in options {
+                    if after_first {
+                        w.write(", ")?;
+                    }
+                    w.write_keyword(option)?;
+                    after_first = true;
+                }
+            }
+            Ok(())
+        },
+        TsqlStatement::SetOption { name, on } => {
+            w.write_keyword("SET ")?;
+            w.write_keyword(name)?;
+            w.write_keyword(if *on { " ON" } else { " OFF" })
+        },
+        TsqlStatement::CreateProcedure { or_alter, name, parameters, body } => {
+            write_create(w, *or_alter, "PROCEDURE ", name)?;
+            w.indent();
+            let mut after_first = false;
+            for parameter in parameters {
+                start_list_line(w, after_first)?;
+                write_parameter(w, parameter)?;
+                after_first = true;
+            }
+            w.dedent();
+            write_body(w, body)
+        },
+        TsqlStatement::CreateFunction { or_alter, name, parameters, returns } => {
+            write_create(w, *or_alter, "FUNCTION ", name)?;
+            w.write("(")?;
+            let mut after_first = false;
+            for parameter in parameters {
+                if after_first {
+                    w.write(", ")?;
+                }
+                write_parameter(w, parameter)?;
+                after_first = true;
+            }
+            w.write(")")?;
+            w.write_new_line()?;
+            w.write_prefix()?;
+            w.write_keyword("RETURNS ")?;
+            match returns {
+                FunctionReturns::Scalar { data_type, body } => {
+                    w.write_data_type(data_type)?;
+                    write_body(w, body)
+                },
+                FunctionReturns::InlineTable(query) => {
+                    w.write_keyword("TABLE")?;
+                    w.write_new_line()?;
+                    w.write_prefix()?;
+                    w.write_keyword("AS")?;
+                    w.write_new_line()?;
+                    w.write_prefix()?;
+                    w.write_keyword("RETURN (")?;
+                    w.indent();
+                    w.write_new_line()?;
+                    w.write_prefix()?;
+                    w.write_query(query)?;
+                    w.dedent();
+                    w.write_new_line()?;
+                    w.write_prefix()?;
+                    w.write(")")
+                },
+            }
+        },
+        TsqlStatement::CreateTrigger { or_alter, name, table, timing, events, body } => {
+            write_create(w, *or_alter, "TRIGGER ", name)?;
+            w.write_new_line()?;
+            w.write_prefix()?;
+            w.write_keyword("ON ")?;
+            w.write_object_name(table)?;
+            w.write_new_line()?;
+            w.write_prefix()?;
+            w.write_keyword(match timing {
+                TriggerTiming::After => "AFTER ",
+                TriggerTiming::InsteadOf => "INSTEAD OF ",
+            })?;
+            let mut after_first = false;
+            for event in events {
+                if after_first {
+                    w.write(", ")?;
+                }
+                w.write_keyword(match event {
+                    TriggerEvent::Insert => "INSERT",
+                    TriggerEvent::Update => "UPDATE",
+                    TriggerEvent::Delete => "DELETE",
+                })?;
+                after_first = true;
+            }
+            write_body(w, body)
+        },
+    }
+}
+
+fn write_create<S>(w: &mut S, or_alter: bool, kind: &str, name: &ObjectName) -> Res<()>
+where S: SqlWriter + ?Sized {
+    w.write_keyword("CREATE ")?;
+    if or_alter {
+        w.write_keyword("OR ALTER ")?;
+    }
+    w.write_keyword(kind)?;
+    w.write_object_name(name)
+}
+
+fn write_parameter<S>(w: &mut S, node: &Parameter) -> Res<()>
+where S: SqlWriter + ?Sized {
+    w.write_ident(&node.name)?;
+    w.write(" ")?;
+    w.write_data_type(&node.data_type)?;
+    if let Some(default) = &node.default {
+        w.write(" = ")?;
+        w.write_expr(default)?;
+    }
+    if node.output {
+        w.write_keyword(" OUTPUT")?;
+    }
+    Ok(())
+}
+
+/// `AS` and the statements of a procedure, function or trigger,
+/// which start on their own lines at the same level
+fn write_body<S>(w: &mut S, stmts: &[TsqlStatement]) -> Res<()>
+where S: SqlWriter + ?Sized {
+    w.write_new_line()?;
+    w.write_prefix()?;
+    w.write_keyword("AS")?;
+    for stmt in stmts {
+        w.write_new_line()?;
+        w.write_prefix()?;
+        write_tsql_statement(w, stmt)?;
+        if !ends_itself(stmt) {
+            w.write(";")?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the statements between `BEGIN` and `END` one level
+/// deeper, each ends with `;` so the block reads back the same
+fn write_block_body<S>(w: &mut S, stmts: &[TsqlStatement]) -> Res<()>
+where S: SqlWriter + ?Sized {
+    w.indent();
+    for stmt in stmts {
+        w.write_new_line()?;
+        w.write_prefix()?;
+        write_tsql_statement(w, stmt)?;
+        if !ends_itself(stmt) {
+            w.write(";")?;
+        }
+    }
+    w.dedent();
+    w.write_new_line()?;
+    w.write_prefix()
+}
+
+/// The body of an `IF`, `ELSE` or `WHILE`, a block starts on
+/// the next line at the same level and anything else is indented
+fn write_branch<S>(w: &mut S, node: &TsqlStatement) -> Res<()>
+where S: SqlWriter + ?Sized {
+    let block = matches!(node, TsqlStatement::Block(_) | TsqlStatement::TryCatch { .. });
+    if !block {
+        w.indent();
+    }
+    w.write_new_line()?;
+    w.write_prefix()?;
+    write_tsql_statement(w, node)?;
+    if !block {
+        w.dedent();
+    }
+    Ok(())
+}
+
+/// Statements that end with an `END`, or with the `;` a `MERGE`
+/// is always written with, need no `;` to separate them from
+/// what follows
+fn ends_itself(node: &TsqlStatement) -> bool {
+    match node {
+        TsqlStatement::Block(_) | TsqlStatement::TryCatch { .. } | TsqlStatement::Merge(_) => true,
+        TsqlStatement::If { then, otherwise, .. } => ends_itself(otherwise.as_ref().unwrap_or(then)),
+        TsqlStatement::While { body, .. } => ends_itself(body),
+        _ => false,
+    }
+}
+
+fn write_declaration<S>(w: &mut S, node: &Declaration) -> Res<()>
+where S: SqlWriter + ?Sized {
+    w.write_ident(&node.name)?;
+    match &node.kind {
+        DeclarationKind::Scalar { data_type, default } => {
+            w.write(" ")?;
+            w.write_data_type(data_type)?;
+            if let Some(default) = default {
+                w.write(" = ")?;
+                w.write_expr(default)?;
+            }
+            Ok(())
+        },
+        DeclarationKind::Table { columns, constraints } => {
+            w.write_keyword(" TABLE")?;
+            write_table_elements(w, columns, constraints)
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::MsSqlWriter;
+    use sqlparser::ast::{BinaryOperator, ColumnOption, ColumnOptionDef, Value};
+
+    #[test]
+    fn declare_and_set() {
+        let stmts = vec![
+            TsqlStatement::Declare(vec![
+                Declaration {
+                    name: "@count".to_string(),
+                    kind: DeclarationKind::Scalar {
+                        data_type: DataType::Int,
+                        default: Some(Expr::Value(Value::Number("0".to_string()))),
+                    },
+                },
+                Declaration {
+                    name: "@ids".to_string(),
+                    kind: DeclarationKind::Table {
+                        columns: vec![ColumnDef {
+                            name: "id".to_string(),
+                            data_type: DataType::Int,
+                            collation: None,
+                            options: vec![ColumnOptionDef {
+                                name: None,
+                                option: ColumnOption::NotNull,
+                            }],
+                        }],
+                        constraints: vec![],
+                    },
+                },
+            ]),
+            TsqlStatement::SetVariable {
+                name: "@count".to_string(),
+                value: Expr::BinaryOp {
+                    left: Box::new(Expr::Identifier("@count".to_string())),
+                    op: BinaryOperator::Plus,
+                    right: Box::new(Expr::Value(Value::Number("1".to_string()))),
+                },
+            },
+        ];
+        let mut w = MsSqlWriter::new("    ", Vec::new());
+        for stmt in &stmts {
+            w.write_tsql_statement(stmt).unwrap();
+            w.write_new_line().unwrap();
+        }
+        let sql = String::from_utf8(w.into_inner()).unwrap();
+        assert_eq!(sql, "DECLARE @count INT = 0, @ids TABLE (
+    id INT NOT NULL
+)
+SET @count = @count + 1
+");
+    }
+
+    #[test]
+    fn control_flow() {
+        let var = || Expr::Identifier("@n".to_string());
+        let stmt = TsqlStatement::If {
+            condition: var(),
+            then: Box::new(TsqlStatement::Block(vec![
+                TsqlStatement::While {
+                    condition: var(),
+                    body: Box::new(TsqlStatement::Break),
+                },
+                TsqlStatement::Return(Some(var())),
+            ])),
+            otherwise: Some(Box::new(TsqlStatement::If {
+                condition: var(),
+                then: Box::new(TsqlStatement::Throw(None)),
+                otherwise: Some(Box::new(TsqlStatement::TryCatch {
+                    body: vec![TsqlStatement::Return(None)],
+                    handler: vec![TsqlStatement::Continue],
+                })),
+            })),
+        };
+        let mut w = MsSqlWriter::new("    ", Vec::new());
+        w.write_tsql_statement(&stmt).unwrap();
+        let sql = String::from_utf8(w.into_inner()).unwrap();
+        assert_eq!(sql, "IF @n
+BEGIN
+    WHILE @n
+        BREAK;
+    RETURN @n;
+END
+ELSE IF @n
+    THROW;
+ELSE
+BEGIN TRY
+    RETURN;
+END TRY
+BEGIN CATCH
+    CONTINUE;
+END CATCH");
+    }
+
+    #[test]
+    fn create_procedure() {
+        let stmt = TsqlStatement::CreateProcedure {
+            or_alter: true,
+            name: ObjectName(vec!["dbo".to_string(), "Touch".to_string()]),
+            parameters: vec![
+                Parameter {
+                    name: "@id".to_string(),
+                    data_type: DataType::Int,
+                    default: None,
+                    output: false,
+                },
+                Parameter {
+                    name: "@count".to_string(),
+                    data_type: DataType::Int,
+                    default: Some(Expr::Value(Value::Number("0".to_string()))),
+                    output: true,
+                },
+            ],
+            body: vec![TsqlStatement::Block(vec![
+                TsqlStatement::SetOption {
+                    name: "NOCOUNT".to_string(),
+                    on: true,
+                },
+                TsqlStatement::Return(None),
+            ])],
+        };
+        let mut w = MsSqlWriter::new("    ", Vec::new());
+        w.write_tsql_statement(&stmt).unwrap();
+        let sql = String::from_utf8(w.into_inner()).unwrap();
+        assert_eq!(sql, "CREATE OR ALTER PROCEDURE dbo.Touch
+    @id INT,
+    @count INT = 0 OUTPUT
+AS
+BEGIN
+    SET NOCOUNT ON;
+    RETURN;
+END");
+    }
+}
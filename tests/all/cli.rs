@@ -0,0 +1,69 @@
+use std::{io::Write, process::{Command, Stdio}};
+
+fn sql_generate() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_sql-generate"))
+}
+
+#[test]
+fn fmt_stdin() {
+    let mut child = sql_generate()
+        .args(["fmt", "--dialect", "postgres"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"select a,b from t where a=1").unwrap();
+    let out = child.wait_with_output().unwrap();
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8(out.stdout).unwrap(), "SELECT a, b
+FROM t
+WHERE a = 1
+");
+}
+
+/// Formatting leaves the functions and operators alone,
+/// only `transpile` rewrites them for another dialect
+#[test]
+fn fmt_keeps_functions_and_operators() {
+    for (dialect, sql) in &[
+        ("mysql", "SELECT a + '1'\nFROM t\n"),
+        ("postgres", "SELECT NVL(a, 1), RAND()\nFROM t\n"),
+        ("mssql", "SELECT ISNULL(a, 1), LEN(b) + 'x'\nFROM t\n"),
+        ("mssql", "CREATE TABLE t (\n    a TEXT\n)\n"),
+    ] {
+        let mut child = sql_generate()
+            .args(["fmt", "--dialect", dialect])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(sql.as_bytes()).unwrap();
+        let out = child.wait_with_output().unwrap();
+        assert!(out.status.success());
+        assert_eq!(String::from_utf8(out.stdout).unwrap(), *sql);
+    }
+}
+
+#[test]
+fn check_and_fmt_dir() {
+    let root = std::env::temp_dir().join(format!("sqlgenerate-cli-{}", std::process::id()));
+    let nested = root.join("reports");
+    std::fs::create_dir_all(&nested).unwrap();
+    std::fs::write(root.join(".sqlgenerate.toml"), "indent = 2\ndialect = \"mssql\"\n").unwrap();
+    std::fs::write(nested.join("daily.sql"), "select [id] from orders").unwrap();
+    std::fs::write(nested.join("notes.txt"), "select nothing").unwrap();
+    let check = sql_generate().arg("check").arg(&root).output().unwrap();
+    assert_eq!(check.status.code(), Some(1));
+    let diff = String::from_utf8(check.stdout).unwrap();
+    assert!(diff.contains("-select [id] from orders"), "{}", diff);
+    assert!(diff.contains("+SELECT [id]"), "{}", diff);
+    let fmt = sql_generate().arg("fmt").arg(&root).output().unwrap();
+    assert!(fmt.status.success());
+    let formatted = std::fs::read_to_string(nested.join("daily.sql")).unwrap();
+    let check = sql_generate().arg("check").arg(&root).output().unwrap();
+    std::fs::remove_dir_all(&root).unwrap();
+    assert_eq!(formatted, "SELECT [id]
+FROM orders
+");
+    assert!(check.status.success());
+}
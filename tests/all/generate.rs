@@ -0,0 +1,454 @@
+//! Random ASTs for the writers. The generator only builds trees
+//! sqlparser could have produced itself, every compound operand
+//! is wrapped in `Expr::Nested` just like the parser does for a
+//! parenthesized expression, so a correct writer has to give the
+//! same tree back when its output is parsed again.
+use proptest::{prelude::*, test_runner::{Config, TestCaseError, TestRunner}};
+use sql_generate::{parse_tsql, TsqlStatement, Unsupported};
+use sqlparser::ast::*;
+use super::writers;
+
+/// How deep subqueries nest
+const DEPTH: u32 = 2;
+
+/// The part of the AST a writer can express without
+/// rewriting it, the generator stays inside it
+#[derive(Debug, Clone, Copy)]
+pub struct Profile {
+    /// The quotes the dialect puts around identifiers
+    quotes: (char, char),
+    /// Schema qualified table names
+    qualified_names: bool,
+    booleans: bool,
+    national_strings: bool,
+    /// A `SELECT` without `FROM`, Oracle reads from `DUAL` instead
+    no_from: bool,
+    /// `LIMIT n`
+    limit: bool,
+    /// `OFFSET n ROWS`, when `fetch_needs_offset` it
+    /// has to be there for `FETCH NEXT n ROWS ONLY`
+    offset: bool,
+    fetch: bool,
+    fetch_needs_offset: bool,
+    set_operators: &'static [(SetOperator, bool)],
+    data_types: &'static [DataType],
+}
+
+const ALL_SET_OPERATORS: &[(SetOperator, bool)] = &[
+    (SetOperator::Union, false),
+    (SetOperator::Union, true),
+    (SetOperator::Intersect, false),
+    (SetOperator::Except, false),
+];
+
+impl Profile {
+    /// The profile for one of `writers()`, by the first word of its name
+    pub fn for_writer(name: &str) -> Self {
+        let base = Self {
+            quotes: ('"', '"'),
+            qualified_names: true,
+            booleans: true,
+            national_strings: false,
+            no_from: true,
+            limit: true,
+            offset: false,
+            fetch: false,
+            fetch_needs_offset: false,
+            set_operators: ALL_SET_OPERATORS,
+            data_types: &[DataType::Int, DataType::BigInt, DataType::Date],
+        };
+        match name.split(' ').next().unwrap_or_default() {
+            // LIMIT is kept to check that T-SQL refuses it
+            "mssql" => Self {
+                quotes: ('[', ']'),
+                national_strings: true,
+                offset: true,
+                fetch: true,
+                fetch_needs_offset: true,
+                data_types: &[
+                    DataType::Int,
+                    DataType::BigInt,
+                    DataType::SmallInt,
+                    DataType::Varchar(Some(20)),
+                    DataType::Char(Some(2)),
+                    DataType::Decimal(Some(10), Some(2)),
+                    DataType::Date,
+                ],
+                ..base
+            },
+            "snowflake" => Self {
+                data_types: &[DataType::Int, DataType::BigInt, DataType::Varchar(Some(20)), DataType::Boolean, DataType::Date],
+                ..base
+            },
+            "bigquery" => Self {
+                quotes: ('`', '`'),
+                qualified_names: false,
+                set_operators: &[(SetOperator::Union, true)],
+                data_types: &[DataType::Date, DataType::Decimal(None, None)],
+                ..base
+            },
+            "oracle" => Self {
+                booleans: false,
+                national_strings: true,
+                no_from: false,
+                limit: false,
+                offset: true,
+                fetch: true,
+                set_operators: &[(SetOperator::Union, false), (SetOperator::Union, true), (SetOperator::Intersect, false)],
+                data_types: &[DataType::Date, DataType::Char(Some(2))],
+                ..base
+            },
+            "postgres" => Self {
+                offset: true,
+                fetch: true,
+                data_types: &[
+                    DataType::Int,
+                    DataType::BigInt,
+                    DataType::Varchar(Some(20)),
+                    DataType::Boolean,
+                    DataType::Text,
+                    DataType::Decimal(Some(10), Some(2)),
+                    DataType::Date,
+                ],
+                ..base
+            },
+            "mysql" => Self {
+                quotes: ('`', '`'),
+                data_types: &[DataType::Date, DataType::Decimal(Some(10), Some(2))],
+                ..base
+            },
+            other => panic!("no generator profile for {}", other),
+        }
+    }
+}
+
+fn ident(p: Profile) -> BoxedStrategy<Ident> {
+    let (open, close) = p.quotes;
+    prop_oneof![
+        4 => prop::sample::select(vec!["a", "b", "total", "price", "qty", "name_1"]).prop_map(String::from),
+        1 => prop::sample::select(vec!["Order Id", "select", "Mixed"])
+            .prop_map(move |name| format!("{}{}{}", open, name, close)),
+    ].boxed()
+}
+
+fn table_name(p: Profile) -> BoxedStrategy<ObjectName> {
+    let table = prop::sample::select(vec!["t1", "orders", "items"]).prop_map(String::from);
+    if !p.qualified_names {
+        return table.prop_map(|t| ObjectName(vec![t])).boxed();
+    }
+    (prop::option::of(prop::sample::select(vec!["dbo", "sales"])), table)
+        .prop_map(|(schema, table)| {
+            ObjectName(schema.map(String::from).into_iter().chain(Some(table)).collect())
+        })
+        .boxed()
+}
+
+fn alias(p: Profile) -> BoxedStrategy<TableAlias> {
+    (prop::sample::select(vec!["x", "y", "z_1"]), prop::collection::vec(ident(p), 0..2))
+        .prop_map(|(name, columns)| TableAlias { name: name.to_string(), columns })
+        .boxed()
+}
+
+fn number() -> BoxedStrategy<Expr> {
+    prop_oneof![
+        (0u32..1000).prop_map(|n| n.to_string()),
+        (0u32..100, 0u32..100).prop_map(|(a, b)| format!("{}.{}", a, b)),
+    ].prop_map(|n| Expr::Value(Value::Number(n))).boxed()
+}
+
+/// A row count for LIMIT, OFFSET or FETCH
+fn count() -> BoxedStrategy<Expr> {
+    (0u32..1000).prop_map(|n| Expr::Value(Value::Number(n.to_string()))).boxed()
+}
+
+fn value(p: Profile) -> BoxedStrategy<Expr> {
+    let mut values = vec![
+        number(),
+        "[a-z' ]{0,8}".prop_map(|s| Expr::Value(Value::SingleQuotedString(s))).boxed(),
+        Just(Expr::Value(Value::Null)).boxed(),
+    ];
+    if p.booleans {
+        values.push(any::<bool>().prop_map(|b| Expr::Value(Value::Boolean(b))).boxed());
+    }
+    if p.national_strings {
+        values.push("[a-z']{1,8}".prop_map(|s| Expr::Value(Value::NationalStringLiteral(s))).boxed());
+    }
+    prop::strategy::Union::new(values).boxed()
+}
+
+fn column(p: Profile) -> BoxedStrategy<Expr> {
+    prop_oneof![
+        3 => ident(p).prop_map(Expr::Identifier),
+        1 => (prop::sample::select(vec!["x", "y"]), ident(p))
+            .prop_map(|(table, col)| Expr::CompoundIdentifier(vec![table.to_string(), col])),
+    ].boxed()
+}
+
+/// The strategies for one level of nesting, each level
+/// builds on the one below it
+#[derive(Clone)]
+struct Level {
+    /// Expressions that can be the operand of an operator
+    /// as is, anything else has to be nested
+    operand: BoxedStrategy<Expr>,
+    expr: BoxedStrategy<Expr>,
+    query: BoxedStrategy<Query>,
+}
+
+const BINARY_OPERATORS: &[BinaryOperator] = &[
+    BinaryOperator::Plus,
+    BinaryOperator::Minus,
+    BinaryOperator::Multiply,
+    BinaryOperator::Divide,
+    BinaryOperator::Modulus,
+    BinaryOperator::Gt,
+    BinaryOperator::Lt,
+    BinaryOperator::GtEq,
+    BinaryOperator::LtEq,
+    BinaryOperator::Eq,
+    BinaryOperator::NotEq,
+    BinaryOperator::And,
+    BinaryOperator::Or,
+    BinaryOperator::Like,
+    BinaryOperator::NotLike,
+];
+
+const UNARY_OPERATORS: &[UnaryOperator] = &[UnaryOperator::Plus, UnaryOperator::Minus, UnaryOperator::Not];
+
+fn level(p: Profile, depth: u32) -> Level {
+    let leaf = prop_oneof![column(p), value(p)].boxed();
+    let mut below: Option<Level> = None;
+    for _ in 0..=depth {
+        let operand = match &below {
+            None => leaf.clone(),
+            Some(below) => operand(p, leaf.clone(), below),
+        };
+        let expr = expr(operand.clone(), below.as_ref());
+        let query = query(p, expr.clone(), below.as_ref().map(|b| b.query.clone()));
+        below = Some(Level { operand, expr, query });
+    }
+    below.unwrap()
+}
+
+fn operand(p: Profile, leaf: BoxedStrategy<Expr>, below: &Level) -> BoxedStrategy<Expr> {
+    let inner = below.expr.clone();
+    prop_oneof![
+        6 => leaf,
+        2 => inner.clone().prop_map(|e| Expr::Nested(Box::new(e))),
+        1 => (prop::sample::select(vec!["fn_a", "fn_b"]), any::<bool>(), prop::collection::vec(inner.clone(), 0..3))
+            .prop_map(|(name, distinct, args)| Expr::Function(Function {
+                name: ObjectName(vec![name.to_string()]),
+                args,
+                over: None,
+                distinct,
+            })),
+        1 => (inner.clone(), prop::sample::select(p.data_types.to_vec()))
+            .prop_map(|(expr, data_type)| Expr::Cast { expr: Box::new(expr), data_type }),
+        1 => (
+            prop::option::of(below.operand.clone()),
+            prop::collection::vec((inner.clone(), inner.clone()), 1..3),
+            prop::option::of(inner),
+        ).prop_map(|(operand, arms, else_result)| {
+            let (conditions, results) = arms.into_iter().unzip();
+            Expr::Case {
+                operand: operand.map(Box::new),
+                conditions,
+                results,
+                else_result: else_result.map(Box::new),
+            }
+        }),
+        1 => below.query.clone().prop_map(|q| Expr::Subquery(Box::new(q))),
+        1 => below.query.clone().prop_map(|q| Expr::Exists(Box::new(q))),
+    ].boxed()
+}
+
+fn expr(op: BoxedStrategy<Expr>, below: Option<&Level>) -> BoxedStrategy<Expr> {
+    let list_item = below.map(|b| b.expr.clone()).unwrap_or_else(|| op.clone());
+    let mut exprs = vec![
+        (4, op.clone()),
+        (4, (op.clone(), prop::sample::select(BINARY_OPERATORS.to_vec()), op.clone())
+            .prop_map(|(left, op, right)| Expr::BinaryOp { left: Box::new(left), op, right: Box::new(right) })
+            .boxed()),
+        (1, (prop::sample::select(UNARY_OPERATORS.to_vec()), op.clone())
+            .prop_map(|(op, expr)| Expr::UnaryOp { op, expr: Box::new(expr) })
+            .boxed()),
+        (1, (op.clone(), any::<bool>()).prop_map(|(expr, not)| if not {
+            Expr::IsNotNull(Box::new(expr))
+        } else {
+            Expr::IsNull(Box::new(expr))
+        }).boxed()),
+        (1, (op.clone(), prop::collection::vec(list_item, 1..4), any::<bool>())
+            .prop_map(|(expr, list, negated)| Expr::InList { expr: Box::new(expr), list, negated })
+            .boxed()),
+        (1, (op.clone(), any::<bool>(), op.clone(), op.clone())
+            .prop_map(|(expr, negated, low, high)| Expr::Between {
+                expr: Box::new(expr),
+                negated,
+                low: Box::new(low),
+                high: Box::new(high),
+            })
+            .boxed()),
+    ];
+    if let Some(below) = below {
+        exprs.push((1, (op, below.query.clone(), any::<bool>())
+            .prop_map(|(expr, subquery, negated)| Expr::InSubquery {
+                expr: Box::new(expr),
+                subquery: Box::new(subquery),
+                negated,
+            })
+            .boxed()));
+    }
+    prop::strategy::Union::new_weighted(exprs).boxed()
+}
+
+fn select_item(p: Profile, expr: BoxedStrategy<Expr>) -> BoxedStrategy<SelectItem> {
+    prop_oneof![
+        4 => expr.clone().prop_map(SelectItem::UnnamedExpr),
+        2 => (expr, ident(p)).prop_map(|(expr, alias)| SelectItem::ExprWithAlias { expr, alias }),
+        1 => Just(SelectItem::Wildcard),
+        1 => prop::sample::select(vec!["x", "y"])
+            .prop_map(|t| SelectItem::QualifiedWildcard(ObjectName(vec![t.to_string()]))),
+    ].boxed()
+}
+
+fn table_factor(p: Profile, sub: Option<BoxedStrategy<Query>>) -> BoxedStrategy<TableFactor> {
+    let table = (table_name(p), prop::option::of(alias(p)))
+        .prop_map(|(name, alias)| TableFactor::Table { name, alias, args: vec![], with_hints: vec![] });
+    let sub = match sub {
+        Some(sub) => sub,
+        None => return table.boxed(),
+    };
+    prop_oneof![
+        3 => table,
+        1 => (sub, alias(p)).prop_map(|(subquery, alias)| TableFactor::Derived {
+            lateral: false,
+            subquery: Box::new(subquery),
+            alias: Some(alias),
+        }),
+    ].boxed()
+}
+
+fn join(p: Profile, expr: BoxedStrategy<Expr>, factor: BoxedStrategy<TableFactor>) -> BoxedStrategy<Join> {
+    let constraint = prop_oneof![
+        3 => expr.prop_map(JoinConstraint::On),
+        1 => prop::collection::vec(ident(p), 1..3).prop_map(JoinConstraint::Using),
+    ];
+    let operator = prop_oneof![
+        (constraint, 0..4).prop_map(|(c, kind)| match kind {
+            0 => JoinOperator::Inner(c),
+            1 => JoinOperator::LeftOuter(c),
+            2 => JoinOperator::RightOuter(c),
+            _ => JoinOperator::FullOuter(c),
+        }),
+        Just(JoinOperator::CrossJoin),
+    ];
+    (factor, operator)
+        .prop_map(|(relation, join_operator)| Join { relation, join_operator })
+        .boxed()
+}
+
+fn select(p: Profile, expr: BoxedStrategy<Expr>, sub: Option<BoxedStrategy<Query>>) -> BoxedStrategy<Select> {
+    let factor = table_factor(p, sub);
+    let from = (factor.clone(), prop::collection::vec(join(p, expr.clone(), factor), 0..2))
+        .prop_map(|(relation, joins)| TableWithJoins { relation, joins });
+    (
+        any::<bool>(),
+        prop::collection::vec(select_item(p, expr.clone()), 1..4),
+        prop::collection::vec(from, if p.no_from { 0 } else { 1 }..2),
+        prop::option::of(expr.clone()),
+        prop::collection::vec(expr.clone(), 0..3),
+        prop::option::of(expr),
+    ).prop_map(|(distinct, projection, from, selection, group_by, having)| Select {
+        distinct,
+        projection,
+        from,
+        selection,
+        group_by,
+        having,
+    }).boxed()
+}
+
+/// A chain of set operations, `INTERSECT` binds tighter than
+/// `UNION` and `EXCEPT` so it never has one of them on its left
+fn set_expr(p: Profile, select: BoxedStrategy<Select>) -> BoxedStrategy<SetExpr> {
+    let ops = prop::collection::vec((prop::sample::select(p.set_operators.to_vec()), select.clone()), 0..3);
+    (select, ops).prop_map(|(first, ops)| {
+        let first = SetExpr::Select(Box::new(first));
+        ops.into_iter().fold(first, |left, ((op, all), right)| {
+            if let (SetOperator::Intersect, SetExpr::SetOperation { op: prev, .. }) = (&op, &left) {
+                if *prev != SetOperator::Intersect {
+                    return left;
+                }
+            }
+            SetExpr::SetOperation {
+                op,
+                all,
+                left: Box::new(left),
+                right: Box::new(SetExpr::Select(Box::new(right))),
+            }
+        })
+    }).boxed()
+}
+
+fn query(p: Profile, expr: BoxedStrategy<Expr>, sub: Option<BoxedStrategy<Query>>) -> BoxedStrategy<Query> {
+    let ctes = match &sub {
+        Some(sub) => prop::collection::vec((alias(p), sub.clone()).prop_map(|(alias, query)| Cte { alias, query }), 0..2).boxed(),
+        None => Just(vec![]).boxed(),
+    };
+    let order_by = (expr.clone(), prop::option::of(any::<bool>()))
+        .prop_map(|(expr, asc)| OrderByExpr { expr, asc });
+    let limit = if p.limit { prop::option::of(count()).boxed() } else { Just(None).boxed() };
+    let offset = if p.offset { prop::option::of(count()).boxed() } else { Just(None).boxed() };
+    let fetch = if p.fetch {
+        prop::option::of(count().prop_map(|n| Fetch { with_ties: false, percent: false, quantity: Some(n) })).boxed()
+    } else {
+        Just(None).boxed()
+    };
+    (
+        ctes,
+        set_expr(p, select(p, expr, sub)),
+        prop::collection::vec(order_by, 0..3),
+        limit,
+        offset,
+        fetch,
+    ).prop_map(move |(ctes, body, order_by, limit, offset, fetch)| {
+        let fetch = if p.fetch_needs_offset && offset.is_none() { None } else { fetch };
+        Query { ctes, body, order_by, limit, offset, fetch }
+    }).boxed()
+}
+
+/// Any query, with subqueries at most `depth` levels deep
+pub fn arbitrary_query(p: Profile, depth: u32) -> BoxedStrategy<Query> {
+    level(p, depth).query
+}
+
+/// Every writer either refuses a generated query with `Unsupported`
+/// or writes SQL that parses back into the same query
+#[test]
+fn written_queries_parse_back() {
+    for (name, reparse, format) in writers() {
+        let profile = Profile::for_writer(name);
+        let mut runner = TestRunner::new(Config {
+            cases: 64,
+            failure_persistence: None,
+            ..Config::default()
+        });
+        let result = runner.run(&arbitrary_query(profile, DEPTH), |q| {
+            let stmts = vec![TsqlStatement::Statement(Statement::Query(Box::new(q)))];
+            let sql = match format(&stmts) {
+                Ok(sql) => sql,
+                Err(e) => {
+                    prop_assert!(e.downcast_ref::<Unsupported>().is_some(), "{}: {}", name, e);
+                    return Ok(());
+                },
+            };
+            let again = parse_tsql(&sql, &*reparse)
+                .map_err(|e| TestCaseError::fail(format!("{}: output does not parse: {}\n{}", name, e, sql)))?;
+            prop_assert_eq!(again, stmts, "{}:\n{}", name, sql);
+            Ok(())
+        });
+        if let Err(e) = result {
+            panic!("{}", e);
+        }
+    }
+}
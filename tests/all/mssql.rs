@@ -1,4 +1,4 @@
-use sql_generate::MsSqlWriter;
+use sql_generate::{parse_batches, transpile, Comments, MsSqlWriter, SqlWriter, Target, Unsupported};
 use sqlparser::dialect::MsSqlDialect;
 
 #[test]
@@ -6,9 +6,160 @@ fn simple_select() {
     run_test("tests/sql/ms/simple-select.sql").unwrap();
 }
 
+#[test]
+fn create_table() {
+    run_test("tests/sql/ms/create-table.sql").unwrap();
+}
+
+#[test]
+fn expressions() {
+    run_test("tests/sql/ms/expressions.sql").unwrap();
+}
+
 #[test]
 fn decalre() {
-    run_test("tests/sql/ms/declare.sql").unwrap();
+    let sql = std::fs::read_to_string("tests/sql/ms/declare.sql").unwrap();
+    assert_eq!(transpile(&sql, &MsSqlDialect {}, Target::MsSql).unwrap(), sql);
+    let err = transpile(&sql, &MsSqlDialect {}, Target::Postgres).unwrap_err();
+    assert!(err.is::<Unsupported>());
+}
+
+#[test]
+fn control_flow() {
+    let sql = std::fs::read_to_string("tests/sql/ms/control-flow.sql").unwrap();
+    assert_eq!(transpile(&sql, &MsSqlDialect {}, Target::MsSql).unwrap(), sql);
+}
+
+#[test]
+fn no_semicolons() {
+    let sql = std::fs::read_to_string("tests/sql/ms/no-semicolons.sql").unwrap();
+    let control_flow = "IF @Count > 0
+BEGIN
+    SET @Count = @Count - 1;
+    SELECT name
+    FROM people
+    WHERE id = @Count;
+END
+ELSE IF @Count = 0
+    SELECT 1;
+ELSE
+    SELECT 2;
+WHILE @Count < 10
+    SET @Count = @Count + 1;
+BEGIN TRY
+    BEGIN TRANSACTION;
+    DELETE FROM people
+    WHERE id = @Id;
+    COMMIT;
+END TRY
+BEGIN CATCH
+    ROLLBACK;
+    SELECT CASE WHEN @Count > 0 THEN 1 ELSE 0 END AS positive
+    FROM people;
+    THROW;
+END CATCH;
+RETURN CASE WHEN @Count > 0 THEN @Count ELSE 0 END";
+    assert_eq!(transpile(&sql, &MsSqlDialect {}, Target::MsSql).unwrap(), control_flow);
+    assert!(transpile("BEGIN TRAN t1", &MsSqlDialect {}, Target::MsSql).is_err());
+}
+
+#[test]
+fn procedures() {
+    let sql = std::fs::read_to_string("tests/sql/ms/procedures.sql").unwrap();
+    assert_eq!(transpile(&sql, &MsSqlDialect {}, Target::MsSql).unwrap(), sql);
+}
+
+#[test]
+fn procedures_without_semicolons() {
+    let sql = std::fs::read_to_string("tests/sql/ms/procedures-no-semicolons.sql").unwrap();
+    let procedures = "CREATE TRIGGER tr
+ON t
+AFTER INSERT
+AS
+BEGIN
+    INSERT INTO log (id)
+    SELECT id
+    FROM inserted;
+END
+GO
+CREATE PROCEDURE p
+AS
+BEGIN
+    SELECT name
+    FROM t;
+END
+GO";
+    assert_eq!(transpile(&sql, &MsSqlDialect {}, Target::MsSql).unwrap(), procedures);
+}
+
+#[test]
+fn merge() {
+    let sql = std::fs::read_to_string("tests/sql/ms/merge.sql").unwrap();
+    assert_eq!(transpile(&sql, &MsSqlDialect {}, Target::MsSql).unwrap(), sql);
+    let merge = "MERGE INTO t USING s ON t.id = s.id WHEN MATCHED THEN DELETE SELECT 1";
+    assert!(transpile(merge, &MsSqlDialect {}, Target::MsSql).unwrap().ends_with("DELETE;\nSELECT 1"));
+}
+
+#[test]
+fn output() {
+    let sql = std::fs::read_to_string("tests/sql/ms/output.sql").unwrap();
+    assert_eq!(transpile(&sql, &MsSqlDialect {}, Target::MsSql).unwrap(), sql);
+}
+
+#[test]
+fn update_from() {
+    let sql = std::fs::read_to_string("tests/sql/ms/update-from.sql").unwrap();
+    assert_eq!(transpile(&sql, &MsSqlDialect {}, Target::MsSql).unwrap(), sql);
+    let err = transpile(&sql, &MsSqlDialect {}, Target::Postgres).unwrap_err();
+    assert!(err.is::<Unsupported>());
+}
+
+#[test]
+fn top() {
+    let sql = std::fs::read_to_string("tests/sql/ms/top.sql").unwrap();
+    assert_eq!(transpile(&sql, &MsSqlDialect {}, Target::MsSql).unwrap(), sql);
+    assert!(transpile("SELECT TOP 1 a FROM t UNION SELECT b FROM u", &MsSqlDialect {}, Target::MsSql).is_err());
+    assert_eq!(transpile("SELECT top FROM t", &MsSqlDialect {}, Target::MsSql).unwrap(), "SELECT top\nFROM t");
+}
+
+#[test]
+fn apply() {
+    let sql = std::fs::read_to_string("tests/sql/ms/apply.sql").unwrap();
+    assert_eq!(transpile(&sql, &MsSqlDialect {}, Target::MsSql).unwrap(), sql);
+}
+
+#[test]
+fn pivot() {
+    let sql = std::fs::read_to_string("tests/sql/ms/pivot.sql").unwrap();
+    assert_eq!(transpile(&sql, &MsSqlDialect {}, Target::MsSql).unwrap(), sql);
+}
+
+#[test]
+fn for_json() {
+    let sql = std::fs::read_to_string("tests/sql/ms/for-json.sql").unwrap();
+    assert_eq!(transpile(&sql, &MsSqlDialect {}, Target::MsSql).unwrap(), sql);
+    let err = transpile(&sql, &MsSqlDialect {}, Target::Postgres).unwrap_err();
+    assert!(err.is::<Unsupported>());
+}
+
+#[test]
+fn comments() {
+    let path = "tests/sql/ms/comments.sql";
+    let comments = Comments::extract(&std::fs::read_to_string(path).unwrap());
+    let mut w = MsSqlWriter::new("    ", Vec::new()).comments(comments);
+    let s = super::run_test(path, MsSqlDialect {}, &mut w).unwrap();
+    w.flush_comments().unwrap();
+    let s2 = String::from_utf8(w.into_inner()).unwrap();
+    assert_eq!(s, s2);
+}
+
+#[test]
+fn batches() {
+    let sql = std::fs::read_to_string("tests/sql/ms/batches.sql").unwrap();
+    assert_eq!(transpile(&sql, &MsSqlDialect {}, Target::MsSql).unwrap(), sql);
+    let errors = parse_batches("SELECT 1\nGO\nSELECT a,\n    (\nGO\nSELECT 2", &MsSqlDialect {}).unwrap_err();
+    assert_eq!(errors.0.len(), 1);
+    assert_eq!((errors.0[0].batch, errors.0[0].line), (2, 4));
 }
 
 fn run_test(path: &str) -> Result<(), Box<dyn std::error::Error>> {
@@ -0,0 +1,23 @@
+use sql_generate::{BigQueryWriter, MsSqlWriter, OracleWriter, SnowflakeWriter, Target};
+use sqlparser::dialect::{GenericDialect, MsSqlDialect};
+use super::round_trip_dir;
+
+#[test]
+fn mssql() {
+    round_trip_dir("tests/sql/ms", &MsSqlDialect {}, &MsSqlDialect {}, writer!(MsSqlWriter::new("    ", Vec::new()))).unwrap();
+}
+
+#[test]
+fn snowflake() {
+    round_trip_dir("tests/sql/snowflake", &GenericDialect {}, &GenericDialect {}, writer!(SnowflakeWriter::new("    ", Vec::new()))).unwrap();
+}
+
+#[test]
+fn bigquery() {
+    round_trip_dir("tests/sql/bigquery", &GenericDialect {}, &*Target::BigQuery.dialect(), writer!(BigQueryWriter::new("    ", Vec::new()))).unwrap();
+}
+
+#[test]
+fn oracle() {
+    round_trip_dir("tests/sql/oracle", &GenericDialect {}, &GenericDialect {}, writer!(OracleWriter::new("    ", Vec::new()))).unwrap();
+}
@@ -0,0 +1,17 @@
+use sql_generate::SnowflakeWriter;
+use sqlparser::dialect::GenericDialect;
+
+#[test]
+fn limit() {
+    run_test("tests/sql/snowflake/limit.sql").unwrap();
+}
+
+fn run_test(path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let buf: Vec<u8> = Vec::new();
+    let mut w = SnowflakeWriter::new("    ", buf);
+    let s = super::run_test(path, GenericDialect {}, &mut w)?;
+    let buf = w.into_inner();
+    let s2 = String::from_utf8(buf)?;
+    assert_eq!(s, s2);
+    Ok(())
+}
@@ -0,0 +1,268 @@
+use sql_generate::{format, transpile, transpile_with_functions, transpile_with_warnings, FormatOptions, FunctionMap, Target, Unsupported};
+use sqlparser::dialect::{GenericDialect, MsSqlDialect, PostgreSqlDialect};
+
+#[test]
+fn limit_to_mssql() {
+    let out = transpile("SELECT a FROM t LIMIT 10", &GenericDialect {}, Target::MsSql).unwrap();
+    assert_eq!(out, "SELECT TOP (10) a
+FROM t");
+    let out = transpile("SELECT a FROM t LIMIT 10 OFFSET 20 ROWS", &GenericDialect {}, Target::MsSql).unwrap();
+    assert_eq!(out, "SELECT a
+FROM t
+ORDER BY (SELECT NULL)
+OFFSET 20 ROWS
+FETCH NEXT 10 ROWS ONLY");
+}
+
+#[test]
+fn top_percent_to_postgres() {
+    let err = transpile("SELECT TOP 5 PERCENT a FROM t ORDER BY a", &MsSqlDialect {}, Target::Postgres).unwrap_err();
+    assert!(err.is::<Unsupported>());
+    let out = transpile("SELECT TOP 5 WITH TIES a FROM t ORDER BY a", &MsSqlDialect {}, Target::Postgres).unwrap();
+    assert_eq!(out, "SELECT a\nFROM t\nORDER BY a\nFETCH FIRST 5 ROWS WITH TIES");
+}
+
+#[test]
+fn top_to_mysql() {
+    let out = transpile("SELECT TOP 5 name FROM people ORDER BY age", &MsSqlDialect {}, Target::MySql).unwrap();
+    assert_eq!(out, "SELECT name
+FROM people
+ORDER BY age
+LIMIT 5");
+}
+
+#[test]
+fn stuff_to_postgres() {
+    let out = transpile(
+        "SELECT STUFF((SELECT ', ' + name FROM people FOR XML PATH('')), 1, 2, '') AS names",
+        &MsSqlDialect {},
+        Target::Postgres,
+    ).unwrap();
+    assert_eq!(out, "SELECT (SELECT STRING_AGG(name, ', ')
+FROM people) AS names");
+}
+
+#[test]
+fn ordered_stuff_to_string_agg() {
+    let sql = "SELECT STUFF((SELECT ',' + name FROM u ORDER BY name DESC FOR XML PATH('')), 1, 1, '')";
+    let out = transpile(sql, &MsSqlDialect {}, Target::Postgres).unwrap();
+    assert_eq!(out, "SELECT (SELECT STRING_AGG(name, ',' ORDER BY name DESC)
+FROM u)");
+    let mut functions = FunctionMap::new();
+    functions.string_agg();
+    let out = transpile_with_functions(sql, &MsSqlDialect {}, Target::MsSql, functions).unwrap();
+    assert_eq!(out, "SELECT (SELECT STRING_AGG(name, ',') WITHIN GROUP (ORDER BY name DESC)
+FROM u)");
+}
+
+#[test]
+fn quotes_in_strings() {
+    let out = transpile("SELECT 'it''s', N'x'", &MsSqlDialect {}, Target::MsSql).unwrap();
+    assert_eq!(out, "SELECT 'it''s', N'x'");
+}
+
+#[test]
+fn copy_is_unsupported() {
+    let err = transpile("COPY t (a) FROM stdin;\n1\n\\.\n", &PostgreSqlDialect {}, Target::Postgres).unwrap_err();
+    assert!(err.is::<Unsupported>());
+}
+
+#[test]
+fn select_variables_to_postgres() {
+    let err = transpile("SELECT @n = COUNT(*) FROM people", &MsSqlDialect {}, Target::Postgres).unwrap_err();
+    assert!(err.is::<Unsupported>());
+}
+
+#[test]
+fn mssql_to_snowflake() {
+    let out = transpile("SELECT ISNULL(name, 'none') + '!' FROM [users]", &MsSqlDialect {}, Target::Snowflake).unwrap();
+    assert_eq!(out, "SELECT CONCAT(COALESCE(name, 'none'), '!')
+FROM \"users\"");
+}
+
+#[test]
+fn concat_only_from_tsql() {
+    let out = transpile("SELECT a + 1 + 'x' FROM t", &MsSqlDialect {}, Target::Snowflake).unwrap();
+    assert_eq!(out, "SELECT CONCAT(a + 1, 'x')\nFROM t");
+    let out = transpile("SELECT a + '1' FROM t", &PostgreSqlDialect {}, Target::MySql).unwrap();
+    assert_eq!(out, "SELECT a + '1'\nFROM t");
+}
+
+#[test]
+fn types_kept_for_the_same_dialect() {
+    let sql = "CREATE TABLE t (a TEXT, b TIMESTAMP)";
+    assert_eq!(transpile(sql, &MsSqlDialect {}, Target::MsSql).unwrap(), "CREATE TABLE t (\n    a TEXT,\n    b TIMESTAMP\n)");
+    assert!(transpile(sql, &PostgreSqlDialect {}, Target::MsSql).unwrap().contains("a NVARCHAR(MAX)"));
+}
+
+#[test]
+fn booleans_to_oracle() {
+    let out = transpile("SELECT a FROM t WHERE true; SELECT a FROM t WHERE active = true AND b", &PostgreSqlDialect {}, Target::Oracle).unwrap();
+    assert_eq!(out, "SELECT a
+FROM t
+WHERE 1 = 1;
+SELECT a
+FROM t
+WHERE active = 1 AND b = 1");
+}
+
+#[test]
+fn oracle_warnings() {
+    let (out, warnings) = transpile_with_warnings("SELECT '' FROM t", &PostgreSqlDialect {}, Target::Oracle, FormatOptions::new()).unwrap();
+    assert_eq!(out, "SELECT ''\nFROM t");
+    assert_eq!(warnings.len(), 1);
+}
+
+#[test]
+fn booleans_to_mssql() {
+    let out = transpile("SELECT a FROM t WHERE active = true; SELECT b FROM u", &GenericDialect {}, Target::MsSql).unwrap();
+    assert_eq!(out, "SELECT a
+FROM t
+WHERE active = 1;
+SELECT b
+FROM u");
+    let out = transpile("SELECT CASE WHEN true THEN false END FROM t WHERE true AND NOT (false)", &GenericDialect {}, Target::MsSql).unwrap();
+    assert_eq!(out, "SELECT CASE WHEN 1 = 1 THEN 0 END
+FROM t
+WHERE 1 = 1 AND NOT (1 = 0)");
+}
+
+#[test]
+fn functions_to_postgres() {
+    let out = transpile("SELECT GETDATE(), LEN(name), DATEADD(day, 7, created) FROM users", &MsSqlDialect {}, Target::Postgres).unwrap();
+    assert_eq!(out, "SELECT NOW(), LENGTH(name), (created + 7 * INTERVAL '1' DAY)
+FROM users");
+}
+
+const UPSERT: &str = "MERGE INTO people AS t
+USING staging AS s ON t.id = s.id
+WHEN MATCHED THEN UPDATE SET name = s.name
+WHEN NOT MATCHED THEN INSERT (id, name) VALUES (s.id, s.name);";
+
+#[test]
+fn merge_to_postgres() {
+    let out = transpile(UPSERT, &MsSqlDialect {}, Target::Postgres).unwrap();
+    assert_eq!(out, "INSERT INTO people (id, name)
+SELECT s.id, s.name
+FROM staging AS s
+ON CONFLICT (id) DO UPDATE SET name = EXCLUDED.name");
+    let err = transpile(
+        "MERGE INTO people AS t USING staging AS s ON t.id = s.id WHEN NOT MATCHED BY SOURCE THEN DELETE",
+        &MsSqlDialect {},
+        Target::Postgres,
+    ).unwrap_err();
+    assert!(err.is::<Unsupported>());
+}
+
+#[test]
+fn merge_to_mysql() {
+    let out = transpile(UPSERT, &MsSqlDialect {}, Target::MySql).unwrap();
+    assert_eq!(out, "INSERT INTO people (id, name)
+SELECT s.id, s.name
+FROM staging AS s
+ON DUPLICATE KEY UPDATE name = VALUES(name)");
+}
+
+#[test]
+fn output_to_postgres() {
+    let out = transpile(
+        "INSERT INTO people (name) OUTPUT inserted.id VALUES ('Ann'); DELETE people OUTPUT deleted.* WHERE id = 2",
+        &MsSqlDialect {},
+        Target::Postgres,
+    ).unwrap();
+    assert_eq!(out, "INSERT INTO people (name)
+VALUES ('Ann')
+RETURNING id;
+DELETE FROM people
+WHERE id = 2
+RETURNING *");
+    let err = transpile("UPDATE people SET name = 'Bob' OUTPUT deleted.name", &MsSqlDialect {}, Target::Postgres).unwrap_err();
+    assert!(err.is::<Unsupported>());
+}
+
+#[test]
+fn apply_to_postgres() {
+    let out = transpile(
+        "SELECT p.name, o.total FROM people AS p CROSS APPLY orders_for(p.id) AS o OUTER APPLY (SELECT TOP 1 total FROM orders WHERE person_id = p.id) AS l",
+        &MsSqlDialect {},
+        Target::Postgres,
+    ).unwrap();
+    assert_eq!(out, "SELECT p.name, o.total
+FROM people AS p
+CROSS JOIN orders_for(p.id) AS o
+LEFT OUTER JOIN LATERAL (
+    SELECT total
+    FROM orders
+    WHERE person_id = p.id
+    FETCH FIRST 1 ROWS ONLY
+) AS l
+    ON TRUE");
+}
+
+#[test]
+fn apply_to_other_targets() {
+    let sql = std::fs::read_to_string("tests/sql/ms/apply.sql").unwrap();
+    let err = transpile(&sql, &MsSqlDialect {}, Target::BigQuery).unwrap_err();
+    assert!(err.is::<Unsupported>());
+    let err = transpile(&sql, &MsSqlDialect {}, Target::MySql).unwrap_err();
+    assert!(err.is::<Unsupported>());
+    let out = transpile(&sql, &MsSqlDialect {}, Target::Snowflake).unwrap();
+    assert!(out.contains("CROSS JOIN LATERAL dbo.orders_for(p.id, @since) AS o\nLEFT OUTER JOIN LATERAL ("));
+    let out = transpile(
+        "SELECT * FROM t OUTER APPLY (SELECT u.a FROM u WHERE u.id = t.id) AS x",
+        &MsSqlDialect {},
+        Target::MySql,
+    ).unwrap();
+    assert_eq!(out, "SELECT *
+FROM t
+LEFT OUTER JOIN LATERAL (
+    SELECT u.a
+    FROM u
+    WHERE u.id = t.id
+) AS x
+    ON TRUE");
+    let out = transpile("SELECT * FROM dbo.f(1) AS x", &MsSqlDialect {}, Target::BigQuery).unwrap();
+    assert_eq!(out, "SELECT *\nFROM dbo.f(1) AS x");
+}
+
+/// A function in `FROM` sees the tables before it in PostgreSQL,
+/// so its APPLY reads back in. Snowflake needs `LATERAL` in front
+/// of the function and sqlparser only reads a subquery after it
+#[test]
+fn function_apply_round_trips() {
+    let sql = "SELECT p.name, o.total FROM people AS p CROSS APPLY dbo.orders_for(p.id) AS o OUTER APPLY dbo.last_order(p.id) AS l";
+    let out = transpile(sql, &MsSqlDialect {}, Target::Postgres).unwrap();
+    assert_eq!(out, "SELECT p.name, o.total
+FROM people AS p
+CROSS JOIN dbo.orders_for(p.id) AS o
+LEFT OUTER JOIN dbo.last_order(p.id) AS l
+    ON TRUE");
+    assert_eq!(format(&out, Target::Postgres, FormatOptions::new()).unwrap(), out);
+    let out = transpile(sql, &MsSqlDialect {}, Target::Snowflake).unwrap();
+    let err = format(&out, Target::Snowflake, FormatOptions::new()).unwrap_err();
+    assert!(err.to_string().contains("Expected subquery after LATERAL"), "{}", err);
+}
+
+#[test]
+fn pivot_to_postgres() {
+    let out = transpile(
+        "SELECT * FROM (SELECT region, quarter, amount FROM sales) AS s PIVOT (SUM(amount) FOR quarter IN ([Q1], [Q2])) AS p",
+        &MsSqlDialect {},
+        Target::Postgres,
+    ).unwrap();
+    assert_eq!(out, "SELECT *
+FROM (
+    SELECT region, SUM(CASE WHEN quarter = 'Q1' THEN amount END) AS \"Q1\", SUM(CASE WHEN quarter = 'Q2' THEN amount END) AS \"Q2\"
+    FROM (
+        SELECT region, quarter, amount
+        FROM sales
+    ) AS s
+    GROUP BY region
+) AS p");
+    let err = transpile(
+        "SELECT * FROM sales PIVOT (SUM(amount) FOR quarter IN ([Q1], [Q2])) AS p",
+        &MsSqlDialect {},
+        Target::Postgres,
+    ).unwrap_err();
+    assert!(err.is::<Unsupported>());
+}
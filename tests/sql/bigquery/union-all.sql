@@ -0,0 +1,5 @@
+SELECT id, total
+FROM orders
+UNION ALL
+SELECT id, total
+FROM archived_orders
\ No newline at end of file
@@ -0,0 +1,9 @@
+SELECT p.name, o.total, last_order.total
+FROM people AS p
+CROSS APPLY dbo.orders_for(p.id, @since) AS o
+OUTER APPLY (
+    SELECT TOP (1) total
+    FROM orders
+    WHERE orders.person_id = p.id
+    ORDER BY total DESC
+) AS last_order
\ No newline at end of file
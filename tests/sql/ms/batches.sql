@@ -0,0 +1,7 @@
+SELECT id
+FROM people;
+SELECT name
+FROM pets
+GO
+SELECT 1
+GO 2
\ No newline at end of file
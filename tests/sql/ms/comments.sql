@@ -0,0 +1,5 @@
+-- monthly totals
+SELECT id, total -- both columns
+FROM orders /* every order */
+WHERE total > 10
+-- done
\ No newline at end of file
@@ -0,0 +1,25 @@
+IF @Count > 0
+BEGIN
+    SET @Count = @Count - 1;
+    RETURN @Count;
+END
+ELSE IF @Count = 0
+    RETURN 0;
+ELSE
+BEGIN
+    RAISERROR('negative count %d', 16, 1, @Count) WITH NOWAIT;
+END;
+WHILE @Count < 10
+BEGIN
+    SET @Count = @Count + 1;
+    IF @Count = 5
+        BREAK;
+END;
+BEGIN TRY
+    DELETE FROM people
+    WHERE id = @Id;
+END TRY
+BEGIN CATCH
+    THROW;
+END CATCH;
+THROW 50000, 'done', 1
\ No newline at end of file
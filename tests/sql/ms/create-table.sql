@@ -0,0 +1,5 @@
+CREATE TABLE orders (
+    id INT NOT NULL PRIMARY KEY,
+    status VARCHAR(20) DEFAULT 'new',
+    customer_id INT REFERENCES customers (id)
+)
\ No newline at end of file
@@ -1,2 +1,10 @@
-
-DECLARE @Var1 INTEGER;
+DECLARE @Var1 INT;
+DECLARE @Name VARCHAR(20) = 'none', @Count INT = 0;
+DECLARE @People TABLE (
+    id INT NOT NULL,
+    name VARCHAR(20)
+);
+SET @Count = @Count + 1;
+SELECT @Name = name
+FROM people
+WHERE id = @Var1
\ No newline at end of file
@@ -0,0 +1,3 @@
+SELECT price + tax, price - discount, total * 2
+FROM orders
+WHERE total BETWEEN 10 AND 20
\ No newline at end of file
@@ -0,0 +1,7 @@
+SELECT id, name
+FROM people
+ORDER BY name
+FOR JSON PATH, ROOT('items');
+SELECT name
+FROM people
+FOR XML PATH(''), TYPE
\ No newline at end of file
@@ -0,0 +1,11 @@
+MERGE INTO people AS t
+USING staging AS s
+ON t.id = s.id
+WHEN MATCHED AND t.name != s.name THEN
+    UPDATE SET name = s.name
+WHEN NOT MATCHED THEN
+    INSERT (id, name)
+    VALUES (s.id, s.name)
+WHEN NOT MATCHED BY SOURCE THEN
+    DELETE
+OUTPUT inserted.id, deleted.id;
\ No newline at end of file
@@ -0,0 +1,22 @@
+IF @Count > 0
+BEGIN
+    SET @Count = @Count - 1
+    SELECT name FROM people WHERE id = @Count
+END
+ELSE IF @Count = 0
+    SELECT 1
+ELSE
+    SELECT 2
+WHILE @Count < 10
+    SET @Count = @Count + 1
+BEGIN TRY
+    BEGIN TRANSACTION
+    DELETE FROM people WHERE id = @Id
+    COMMIT TRANSACTION
+END TRY
+BEGIN CATCH
+    ROLLBACK TRAN
+    SELECT CASE WHEN @Count > 0 THEN 1 ELSE 0 END AS positive FROM people
+    THROW
+END CATCH
+RETURN CASE WHEN @Count > 0 THEN @Count ELSE 0 END
\ No newline at end of file
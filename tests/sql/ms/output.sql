@@ -0,0 +1,13 @@
+DECLARE @ids TABLE (
+    id INT
+);
+INSERT INTO people (name)
+OUTPUT inserted.id INTO @ids
+VALUES ('Ann');
+UPDATE people
+SET name = 'Bob'
+OUTPUT deleted.name AS old_name, inserted.name
+WHERE id = 1;
+DELETE FROM people
+OUTPUT deleted.*
+WHERE id = 2
\ No newline at end of file
@@ -0,0 +1,6 @@
+SELECT region, [Q1], [Q2]
+FROM sales
+PIVOT (SUM(amount) FOR quarter IN ([Q1], [Q2])) AS p;
+SELECT region, quarter, amount
+FROM quarterly
+UNPIVOT (amount FOR quarter IN (q1, q2)) AS u
\ No newline at end of file
@@ -0,0 +1,4 @@
+CREATE TRIGGER tr ON t AFTER INSERT AS BEGIN INSERT INTO log (id) SELECT id FROM inserted END
+GO
+CREATE PROCEDURE p AS BEGIN SELECT name FROM t END
+GO
\ No newline at end of file
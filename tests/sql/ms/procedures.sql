@@ -0,0 +1,38 @@
+CREATE OR ALTER PROCEDURE dbo.InsertPerson
+    @Name VARCHAR(20),
+    @Age INT = NULL,
+    @Id INT OUTPUT
+AS
+BEGIN
+    SET NOCOUNT ON;
+    INSERT INTO people (name, age)
+    VALUES (@Name, @Age);
+    SET @Id = SCOPE_IDENTITY();
+END
+GO
+CREATE FUNCTION dbo.FullName(@First VARCHAR(20), @Last VARCHAR(20))
+RETURNS VARCHAR(41)
+AS
+BEGIN
+    RETURN @First + ' ' + @Last;
+END
+GO
+CREATE FUNCTION dbo.PeopleNamed(@Name VARCHAR(20))
+RETURNS TABLE
+AS
+RETURN (
+    SELECT id, age
+    FROM people
+    WHERE name = @Name
+)
+GO
+CREATE TRIGGER dbo.PeopleInserted
+ON people
+AFTER INSERT
+AS
+BEGIN
+    INSERT INTO audit (person_id)
+    SELECT id
+    FROM inserted;
+END
+GO
\ No newline at end of file
@@ -0,0 +1,8 @@
+SELECT TOP (5) name, age
+FROM people
+ORDER BY age DESC;
+SELECT DISTINCT TOP (10) PERCENT WITH TIES name
+FROM people
+ORDER BY name;
+SELECT TOP (@n) name
+FROM people
\ No newline at end of file
@@ -0,0 +1,11 @@
+UPDATE t
+SET a = u.b
+FROM t
+INNER JOIN u
+    ON t.id = u.id
+WHERE u.c = 1;
+UPDATE people
+SET name = s.name
+OUTPUT inserted.id INTO @ids
+FROM people, staging AS s
+WHERE people.id = s.id
\ No newline at end of file
@@ -0,0 +1,5 @@
+SELECT o.id, o.total
+FROM orders o
+ORDER BY o.total DESC
+OFFSET 10 ROWS
+FETCH FIRST 5 ROWS ONLY
\ No newline at end of file
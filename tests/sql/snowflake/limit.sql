@@ -0,0 +1,4 @@
+SELECT "Name", total
+FROM orders
+ORDER BY total DESC
+LIMIT 10
\ No newline at end of file